@@ -0,0 +1,214 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Reusable I/O buffers
+//!
+//! Chunk bodies are staged in pooled buffers before they are written, so a
+//! multi-GB download allocates a bounded set of buffers up front instead of
+//! one allocation per network frame.
+
+use crate::memory_tracker::{self, MemoryPressure};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tracing::debug;
+
+/// Buffer pool for zero-copy operations
+#[derive(Debug)]
+pub struct BufferPool {
+    /// Available buffers
+    buffers: Mutex<Vec<Vec<u8>>>,
+    /// Buffer size in bytes
+    buffer_size: usize,
+    /// Maximum number of buffers kept in the pool
+    max_buffers: usize,
+    /// Current number of pooled buffers created
+    current_buffers: AtomicUsize,
+}
+
+impl BufferPool {
+    /// Create a new buffer pool with specified buffer size and max buffers
+    pub fn new(buffer_size: usize, max_buffers: usize) -> Self {
+        Self {
+            buffers: Mutex::new(Vec::with_capacity(max_buffers)),
+            buffer_size: buffer_size.max(1),
+            max_buffers,
+            current_buffers: AtomicUsize::new(0),
+        }
+    }
+
+    /// Create a buffer pool with adaptive sizing based on memory pressure
+    pub fn adaptive(file_size: u64) -> Self {
+        let memory_pressure = memory_tracker::check_global_memory_pressure();
+
+        let (buffer_size, max_buffers) = match memory_pressure {
+            // Very conservative: 64KB buffers, max 4 buffers
+            MemoryPressure::Critical => (64 * 1024, 4),
+            // Conservative: 128KB buffers, max 8 buffers
+            MemoryPressure::High => (128 * 1024, 8),
+            // Balanced: 256KB buffers, max 16 buffers
+            MemoryPressure::Moderate => (256 * 1024, 16),
+            // Optimal: scale based on file size
+            MemoryPressure::Low => {
+                let buffer_size = if file_size > 100 * 1024 * 1024 {
+                    1024 * 1024 // 1MB for large files
+                } else if file_size > 10 * 1024 * 1024 {
+                    512 * 1024 // 512KB for medium files
+                } else {
+                    256 * 1024 // 256KB for small files
+                };
+                (buffer_size, 32)
+            }
+        };
+
+        debug!(
+            "Created adaptive buffer pool: {}KB buffers, max {} buffers (pressure: {:?})",
+            buffer_size / 1024,
+            max_buffers,
+            memory_pressure
+        );
+
+        Self::new(buffer_size, max_buffers)
+    }
+
+    /// Size of the buffers handed out by the pool
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    /// Get a buffer from the pool, creating one if necessary
+    pub fn get_buffer(&self) -> Vec<u8> {
+        if let Some(mut buffer) = self.lock().pop() {
+            buffer.clear();
+            return buffer;
+        }
+
+        // Create a pooled buffer if we haven't reached the limit
+        let current = self.current_buffers.load(Ordering::Relaxed);
+        if current < self.max_buffers {
+            self.current_buffers.fetch_add(1, Ordering::Relaxed);
+            memory_tracker::record_allocation(self.buffer_size);
+            debug!(
+                "Created new buffer #{} ({}KB)",
+                current + 1,
+                self.buffer_size / 1024
+            );
+        } else {
+            // More concurrent users than pooled buffers; the extra buffer is
+            // dropped instead of pooled when returned
+            debug!("Buffer pool exhausted, creating temporary buffer");
+        }
+
+        Vec::with_capacity(self.buffer_size)
+    }
+
+    /// Return a buffer to the pool
+    pub fn return_buffer(&self, buffer: Vec<u8>) {
+        // Only pool buffers of the expected size to avoid memory bloat
+        if buffer.capacity() != self.buffer_size {
+            debug!(
+                "Dropping buffer with wrong capacity: {} vs {}",
+                buffer.capacity(),
+                self.buffer_size
+            );
+            return;
+        }
+
+        let mut buffers = self.lock();
+        if buffers.len() < self.max_buffers {
+            buffers.push(buffer);
+        }
+    }
+
+    /// Get pool statistics
+    pub fn stats(&self) -> BufferPoolStats {
+        BufferPoolStats {
+            buffer_size: self.buffer_size,
+            max_buffers: self.max_buffers,
+            current_buffers: self.current_buffers.load(Ordering::Relaxed),
+            available_buffers: self.lock().len(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Vec<u8>>> {
+        self.buffers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Drop for BufferPool {
+    fn drop(&mut self) {
+        // Record deallocation of all pooled buffers
+        let current_buffers = self.current_buffers.load(Ordering::Relaxed);
+        memory_tracker::record_deallocation(current_buffers * self.buffer_size);
+        debug!("Dropped buffer pool with {} buffers", current_buffers);
+    }
+}
+
+/// Buffer pool statistics
+#[derive(Debug, Clone)]
+pub struct BufferPoolStats {
+    pub buffer_size: usize,
+    pub max_buffers: usize,
+    pub current_buffers: usize,
+    pub available_buffers: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_pool_creation() {
+        let pool = BufferPool::new(1024, 10);
+        let stats = pool.stats();
+
+        assert_eq!(stats.buffer_size, 1024);
+        assert_eq!(stats.max_buffers, 10);
+        assert_eq!(stats.current_buffers, 0);
+        assert_eq!(stats.available_buffers, 0);
+    }
+
+    #[test]
+    fn test_buffer_pool_get_return() {
+        let pool = BufferPool::new(1024, 10);
+
+        let buffer = pool.get_buffer();
+        assert_eq!(buffer.capacity(), 1024);
+        assert_eq!(pool.stats().current_buffers, 1);
+
+        pool.return_buffer(buffer);
+        assert_eq!(pool.stats().available_buffers, 1);
+
+        // The returned buffer is reused rather than a new one allocated
+        let buffer = pool.get_buffer();
+        assert_eq!(pool.stats().current_buffers, 1);
+        assert_eq!(pool.stats().available_buffers, 0);
+
+        // Grown buffers are not pooled
+        let mut grown = buffer;
+        grown.resize(4096, 0);
+        pool.return_buffer(grown);
+        assert_eq!(pool.stats().available_buffers, 0);
+    }
+
+    #[test]
+    fn test_pool_is_bounded() {
+        let pool = BufferPool::new(64, 2);
+        let buffers: Vec<_> = (0..3).map(|_| pool.get_buffer()).collect();
+        assert_eq!(pool.stats().current_buffers, 2);
+
+        for buffer in buffers {
+            pool.return_buffer(buffer);
+        }
+        assert_eq!(pool.stats().available_buffers, 2);
+    }
+
+    #[test]
+    fn test_adaptive_buffer_pool() {
+        // Buffer sizes depend on the current memory pressure
+        let stats = BufferPool::adaptive(100 * 1024 * 1024).stats();
+        assert!(stats.buffer_size > 0);
+        assert!(stats.max_buffers > 0);
+    }
+}
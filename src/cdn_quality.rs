@@ -8,6 +8,7 @@
 
 use crate::config::TurboCdnConfig;
 use crate::error::Result;
+use crate::http_backend::HttpBackend;
 use crate::http_client::HttpClient;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -73,14 +74,24 @@ impl CdnQualityAssessor {
     /// Create a new CDN quality assessor
     pub fn new(config: TurboCdnConfig) -> Result<Self> {
         let timeout = Duration::from_secs(config.performance.timeout);
-        let http_client = HttpClient::new(timeout)?;
+        let http_client = HttpClient::with_config(timeout, &config)?;
+        Ok(Self::with_client(config, http_client))
+    }
+
+    /// Create a CDN quality assessor sending its probes with a shared backend
+    pub fn with_backend(config: TurboCdnConfig, backend: Arc<dyn HttpBackend>) -> Self {
+        let timeout = Duration::from_secs(config.performance.timeout);
+        let http_client = HttpClient::with_backend(backend, timeout, &config);
+        Self::with_client(config, http_client)
+    }
 
-        Ok(Self {
+    fn with_client(config: TurboCdnConfig, http_client: HttpClient) -> Self {
+        Self {
             metrics: Arc::new(RwLock::new(HashMap::new())),
             http_client,
             test_urls: config.testing.test_urls.clone(),
             config,
-        })
+        }
     }
 
     /// Assess quality of a specific CDN URL
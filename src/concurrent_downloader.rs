@@ -9,22 +9,32 @@
 //! - Resume capability
 //! - Progress tracking
 
-use crate::constants::{
-    DEFAULT_RETRY_ATTEMPTS, DEFAULT_RETRY_DELAY_BASE, HTTP2_FRAME_SIZE, MAX_REDIRECTS,
-    MAX_URLS_TO_TRY,
-};
+use crate::buffer_pool::BufferPool;
+use crate::config::WriteBackendMode;
+use crate::constants::{DEFAULT_RETRY_ATTEMPTS, DEFAULT_RETRY_DELAY_BASE, MAX_URLS_TO_TRY};
+use crate::credentials::CredentialStore;
+use crate::download_watch::{DownloadWatch, WatchedAttempt};
 use crate::error::{Result, TurboCdnError};
+use crate::http_backend::{HttpBackend, ReqwestBackend};
+use crate::middleware::{MiddlewareStack, RequestKind};
+use crate::mirror::{MirrorCatalog, RequestShape, SharedMirrorCatalog};
 use crate::progress::ProgressTracker;
 use crate::server_tracker::ServerTracker;
+use crate::write_backend::FileWriter;
 use reqwest::Client;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::fs::{File, OpenOptions};
-use tokio::io::{AsyncSeekExt, AsyncWriteExt, SeekFrom};
 use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 use tracing::{debug, info, warn};
 
+/// Client used only to assemble requests that a custom backend sends
+static REQUEST_BUILDER: once_cell::sync::Lazy<Client> = once_cell::sync::Lazy::new(|| {
+    crate::init_rustls_provider();
+    Client::new()
+});
+
 /// Chunk information for concurrent downloads
 #[derive(Debug, Clone)]
 pub struct ChunkInfo {
@@ -51,6 +61,14 @@ pub struct DownloadResult {
     pub url: String,
     /// Whether resume was used
     pub resumed: bool,
+    /// `ETag` validator returned by the server
+    pub etag: Option<String>,
+    /// `Last-Modified` validator returned by the server
+    pub last_modified: Option<String>,
+    /// Whether the file was served from the local download cache
+    pub from_cache: bool,
+    /// Release tag a `releases/latest` URL was resolved to
+    pub resolved_tag: Option<String>,
 }
 
 /// High-performance concurrent downloader with dynamic segmentation
@@ -60,6 +78,7 @@ pub struct DownloadResult {
 #[derive(Debug)]
 pub struct ConcurrentDownloader {
     http_client: Client,
+    http_backend: Arc<dyn HttpBackend>,
     max_concurrent_chunks: usize,
     initial_chunk_size: u64,
     min_chunk_size: u64,
@@ -68,6 +87,14 @@ pub struct ConcurrentDownloader {
     request_timeout: Duration,
     adaptive_chunking_enabled: bool,
     speed_threshold_bytes_per_sec: u64,
+    offline: bool,
+    security: crate::config::SecurityConfig,
+    credentials: Arc<CredentialStore>,
+    middleware: MiddlewareStack,
+    write_backend: WriteBackendMode,
+    mmap_threshold: u64,
+    buffer_pool: Arc<BufferPool>,
+    mirrors: SharedMirrorCatalog,
     server_performance_tracker: std::sync::Arc<std::sync::Mutex<ServerTracker>>,
 }
 
@@ -80,33 +107,29 @@ impl ConcurrentDownloader {
 
     /// Create a new concurrent downloader with configuration
     pub fn with_config(config: &crate::config::TurboCdnConfig) -> Result<Self> {
-        // Initialize rustls provider before creating reqwest client
-        crate::init_rustls_provider();
-        let mut client_builder = Client::builder()
-            .timeout(Duration::from_secs(config.performance.timeout))
-            .user_agent(&config.general.user_agent)
-            .pool_max_idle_per_host(config.performance.pool_max_idle_per_host)
-            .pool_idle_timeout(Duration::from_secs(config.performance.pool_idle_timeout))
-            .tcp_keepalive(Duration::from_secs(config.performance.tcp_keepalive))
-            .tcp_nodelay(true) // Disable Nagle's algorithm for lower latency
-            .connection_verbose(config.general.debug) // Enable connection debugging if debug mode
-            .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS));
-
-        if config.performance.http2_prior_knowledge {
-            client_builder = client_builder.http2_prior_knowledge();
-        }
-
-        // Configure TLS settings for better performance
-        client_builder = client_builder
-            .http2_adaptive_window(true)
-            .http2_max_frame_size(Some(HTTP2_FRAME_SIZE));
-
-        let client = client_builder
-            .build()
-            .map_err(|e| TurboCdnError::network(format!("Failed to create HTTP client: {e}")))?;
+        let backend = ReqwestBackend::from_config(config)?;
+        Self::build(config, backend.client().clone(), Arc::new(backend))
+    }
+
+    /// Create a concurrent downloader sending every request with `backend`
+    ///
+    /// No connection pool of its own is built; requests are only assembled
+    /// with a plain client shared by the process.
+    pub fn with_backend(
+        config: &crate::config::TurboCdnConfig,
+        backend: Arc<dyn HttpBackend>,
+    ) -> Result<Self> {
+        Self::build(config, REQUEST_BUILDER.clone(), backend)
+    }
 
+    fn build(
+        config: &crate::config::TurboCdnConfig,
+        http_client: Client,
+        http_backend: Arc<dyn HttpBackend>,
+    ) -> Result<Self> {
         Ok(Self {
-            http_client: client,
+            http_client,
+            http_backend,
             max_concurrent_chunks: config.performance.max_concurrent_downloads,
             initial_chunk_size: config.performance.chunk_size,
             min_chunk_size: config.performance.min_chunk_size,
@@ -114,27 +137,132 @@ impl ConcurrentDownloader {
             request_timeout: Duration::from_secs(config.performance.timeout),
             adaptive_chunking_enabled: config.performance.adaptive_chunking,
             speed_threshold_bytes_per_sec: config.performance.speed_threshold_bytes_per_sec,
+            offline: config.general.offline,
+            security: config.security.clone(),
+            credentials: Arc::new(CredentialStore::from_config(&config.credentials)?),
+            middleware: MiddlewareStack::new(),
+            write_backend: config.performance.write_backend,
+            mmap_threshold: config.performance.mmap_threshold,
+            // One buffer per in-flight chunk, shared by all downloads
+            buffer_pool: Arc::new(BufferPool::new(
+                config.performance.write_buffer_size,
+                config.performance.max_concurrent_downloads,
+            )),
+            mirrors: Arc::new(std::sync::RwLock::new(Arc::new(MirrorCatalog::new(
+                &config.mirrors,
+                &config.mapping.disabled_mirrors,
+            )?))),
             server_performance_tracker: std::sync::Arc::new(std::sync::Mutex::new(
                 ServerTracker::new(),
             )),
         })
     }
 
+    /// Send every request through the given middleware
+    pub fn with_middleware(mut self, middleware: MiddlewareStack) -> Self {
+        self.middleware = middleware;
+        self
+    }
+
+    /// Send every request with the given backend
+    ///
+    /// The downloader's client is still used to build the requests. Prefer
+    /// [`Self::with_backend`], which does not build a client first.
+    pub fn with_http_backend(mut self, backend: Arc<dyn HttpBackend>) -> Self {
+        self.http_backend = backend;
+        self
+    }
+
     /// Download a file from URL with automatic optimization and retry logic
+    ///
+    /// Candidates are re-ranked by observed server performance.
     pub async fn download<P: AsRef<Path>>(
         &self,
         urls: &[String],
         output_path: P,
         progress_tracker: Option<Arc<ProgressTracker>>,
     ) -> Result<DownloadResult> {
-        let output_path = output_path.as_ref();
+        self.download_candidates(urls, output_path.as_ref(), progress_tracker, None, true)
+            .await
+    }
+
+    /// Download a file trying the URLs in the given order
+    ///
+    /// For candidates that were already ranked, e.g. by the URL mapper.
+    pub async fn download_in_order<P: AsRef<Path>>(
+        &self,
+        urls: &[String],
+        output_path: P,
+        progress_tracker: Option<Arc<ProgressTracker>>,
+    ) -> Result<DownloadResult> {
+        self.download_candidates(urls, output_path.as_ref(), progress_tracker, None, false)
+            .await
+    }
+
+    /// Download trying the URLs in the given order, reporting written bytes to `watch`
+    ///
+    /// Followers of `watch` can read the output file while it downloads.
+    /// Restarted attempts start a new [`WatchState`] generation, bytes read
+    /// before that may have been discarded.
+    ///
+    /// [`WatchState`]: crate::download_watch::WatchState
+    pub async fn download_watched<P: AsRef<Path>>(
+        &self,
+        urls: &[String],
+        output_path: P,
+        watch: Arc<DownloadWatch>,
+    ) -> Result<DownloadResult> {
+        self.download_candidates(urls, output_path.as_ref(), None, Some(watch), false)
+            .await
+    }
+
+    async fn download_candidates(
+        &self,
+        urls: &[String],
+        output_path: &Path,
+        progress_tracker: Option<Arc<ProgressTracker>>,
+        watch: Option<Arc<DownloadWatch>>,
+        rerank: bool,
+    ) -> Result<DownloadResult> {
+        if self.offline {
+            let url = urls.last().map(String::as_str).unwrap_or_default();
+            return Err(TurboCdnError::offline(url));
+        }
+
+        let urls = self.allowed_urls(urls)?;
         let start_time = Instant::now();
 
+        // Mirrors that can't serve the request are dropped before any
+        // attempt, and before the candidate list is cut down
+        let mirrors = self.mirror_snapshot();
+        let resume = !incomplete_marker(output_path).exists()
+            && std::fs::metadata(output_path).is_ok_and(|m| m.len() > 0);
+        let size_limited = urls.iter().any(|url| {
+            mirrors
+                .find_by_url(url)
+                .is_some_and(|m| m.capabilities.max_file_size.is_some())
+        });
+        let known_size = match size_limited {
+            true => self.probe_size(&mirrors, &urls).await,
+            false => None,
+        };
+        let mut skipped = None;
+        let urls = compatible_urls(&mirrors, &urls, known_size, resume, &mut skipped);
+        if urls.is_empty() {
+            return Err(TurboCdnError::download(format!(
+                "No download URL can serve this request: {}",
+                skipped.unwrap_or_default()
+            )));
+        }
+        let urls = urls.as_slice();
+
         // Use intelligent server selection - select more URLs for better redundancy
         let max_urls_to_try = (urls.len()).min(MAX_URLS_TO_TRY);
-        let selected_urls = {
+        let selected_urls = if rerank {
             let tracker = self.server_performance_tracker.lock().unwrap();
             tracker.select_best_servers(urls, max_urls_to_try)
+        } else {
+            urls[..max_urls_to_try].to_vec()
         };
 
         info!(
@@ -147,8 +275,16 @@ impl ConcurrentDownloader {
         // Get retry attempts from config or use default
         let retry_attempts = DEFAULT_RETRY_ATTEMPTS;
 
+        // Remember why the mirrors failed so callers can tell "not found" from outages
+        let mut last_error = None;
+        let mut all_client_errors = true;
+
         // Try each URL with retry logic
         for (index, url) in selected_urls.iter().enumerate() {
+            let ranges = mirrors
+                .find_by_url(url)
+                .is_none_or(|m| m.capabilities.range_requests);
+
             debug!("Trying URL {}/{}: {}", index + 1, selected_urls.len(), url);
 
             // Retry logic for each URL
@@ -164,7 +300,13 @@ impl ConcurrentDownloader {
                 }
 
                 match self
-                    .download_single_url(url, output_path, progress_tracker.clone())
+                    .download_single_url(
+                        url,
+                        output_path,
+                        ranges,
+                        progress_tracker.clone(),
+                        watch.as_ref(),
+                    )
                     .await
                 {
                     Ok(mut result) => {
@@ -203,49 +345,181 @@ impl ConcurrentDownloader {
 
                         if is_not_found {
                             warn!("HTTP 404 Not Found for {}, trying next mirror...", url);
-                            break; // Skip retries, try next URL immediately
                         } else if should_skip_retries {
                             warn!(
                                 "Non-retryable error for {}: {}, trying next mirror...",
                                 url, e
                             );
-                            break; // Skip retries, try next URL
                         } else {
                             warn!("Attempt {} failed for {}: {}", retry_attempt + 1, url, e);
                         }
 
-                        // If this was the last retry for this URL, try next URL
-                        if retry_attempt == retry_attempts {
+                        // Another mirror won't make the disk any bigger
+                        if matches!(e, TurboCdnError::InsufficientSpace { .. }) {
+                            return Err(e);
+                        }
+
+                        all_client_errors &= matches!(
+                            e,
+                            TurboCdnError::HttpStatus { .. } | TurboCdnError::Compliance { .. }
+                        );
+                        last_error = Some(e);
+
+                        // Skip retries for non-retryable errors, and move on after the last retry
+                        if is_not_found || should_skip_retries || retry_attempt == retry_attempts {
                             break;
                         }
                     }
                 }
             }
+        }
+
+        match last_error {
+            // Every mirror rejected the request (e.g. 404 or a blocked redirect), report it as such
+            Some(e) if all_client_errors => Err(e),
+            Some(e) => Err(TurboCdnError::download(format!(
+                "All download URLs failed after retries: {e}"
+            ))),
+            None => Err(TurboCdnError::download("All download URLs failed")),
+        }
+    }
+
+    /// Reject disallowed protocols before any request is made
+    fn allowed_urls(&self, urls: &[String]) -> Result<Vec<String>> {
+        let mut rejected = None;
+        let urls: Vec<String> = urls
+            .iter()
+            .filter(|url| match self.security.check_url(url) {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("Skipping {}: {}", url, e);
+                    rejected = Some(e);
+                    false
+                }
+            })
+            .cloned()
+            .collect();
+        if urls.is_empty() {
+            return Err(rejected.unwrap_or_else(|| TurboCdnError::download("No download URLs")));
+        }
+        Ok(urls)
+    }
+
+    /// Open a `GET` response on the first candidate that answers, in order
+    ///
+    /// The body is left unread so callers can relay it while it arrives.
+    /// `range` is sent as the `Range` header; servers, including mirrors
+    /// without range support, may still answer with the full body. Returns
+    /// the URL that answered with its response.
+    pub async fn open_stream(
+        &self,
+        urls: &[String],
+        range: Option<&str>,
+    ) -> Result<(String, reqwest::Response)> {
+        if self.offline {
+            let url = urls.last().map(String::as_str).unwrap_or_default();
+            return Err(TurboCdnError::offline(url));
+        }
+        let urls = self.allowed_urls(urls)?;
+        let mirrors = self.mirror_snapshot();
+
+        let mut last_error = None;
+        let mut skipped = None;
+        let urls = compatible_urls(&mirrors, &urls, None, false, &mut skipped);
+        for url in urls.iter().take(MAX_URLS_TO_TRY) {
+            let mirror = mirrors.find_by_url(url);
+
+            let mut request = self.credentials.authorize(self.http_client.get(url), url);
+            if let Some(range) = range {
+                request = request.header("Range", range);
+            }
 
-            // If we've exhausted all retries for all URLs, return error
-            if index == selected_urls.len() - 1 {
-                return Err(TurboCdnError::download(
-                    "All download URLs failed after retries".to_string(),
-                ));
+            let start_time = Instant::now();
+            let result = self
+                .middleware
+                .send(
+                    self.http_backend.as_ref(),
+                    request,
+                    RequestKind::Download,
+                    |e| crate::client_builder::request_error("Failed to start download", e),
+                )
+                .await
+                .and_then(|response| {
+                    let status = response.status();
+                    if status.is_success() {
+                        Ok(response)
+                    } else {
+                        Err(TurboCdnError::from_status_code(status.as_u16(), url))
+                    }
+                });
+
+            match result {
+                Ok(response) => {
+                    // Size limits can only be checked once the server told the size
+                    let shape = RequestShape::for_url(url, response.content_length(), false);
+                    if let Some(mirror) = mirror {
+                        if let Some(reason) = mirror.capabilities.incompatibility(&shape) {
+                            info!("Skipping mirror '{}' for {}: {}", mirror.name, url, reason);
+                            skipped = Some(reason);
+                            continue;
+                        }
+                    }
+                    return Ok((url.clone(), response));
+                }
+                Err(e) => {
+                    let mut tracker = self.server_performance_tracker.lock().unwrap();
+                    tracker.record_failure(url, start_time.elapsed());
+                    warn!("Failed to open {}: {}, trying next mirror...", url, e);
+                    last_error = Some(e);
+                }
             }
         }
 
-        Err(TurboCdnError::download(
-            "All download URLs failed".to_string(),
-        ))
+        Err(match (last_error, skipped) {
+            (Some(e), _) => e,
+            (None, Some(reason)) => {
+                TurboCdnError::download(format!("No download URL can serve this request: {reason}"))
+            }
+            (None, None) => TurboCdnError::download("All download URLs failed"),
+        })
+    }
+
+    /// Learn the file size from a candidate that isn't size limited
+    async fn probe_size(&self, mirrors: &MirrorCatalog, urls: &[String]) -> Option<u64> {
+        for url in urls {
+            let limited = mirrors
+                .find_by_url(url)
+                .is_some_and(|m| m.capabilities.max_file_size.is_some());
+            if limited {
+                continue;
+            }
+            match self.get_file_info(url).await {
+                Ok(info) if info.total_size > 0 => return Some(info.total_size),
+                Ok(_) => {}
+                Err(e) => debug!("Size probe failed for {}: {}", url, e),
+            }
+        }
+        None
     }
 
-    /// Download from a single URL
+    /// Download from a single URL, in chunks only if `ranges` is allowed
     async fn download_single_url<P: AsRef<Path>>(
         &self,
         url: &str,
         output_path: P,
+        ranges: bool,
         progress_tracker: Option<Arc<ProgressTracker>>,
+        watch: Option<&Arc<DownloadWatch>>,
     ) -> Result<DownloadResult> {
         let output_path = output_path.as_ref();
 
-        // Check if file already exists and get its size
-        let existing_size = if output_path.exists() {
+        // Check if file already exists and get its size. A preallocated file
+        // left by an interrupted download is full size but has gaps, so it
+        // can't be resumed by size
+        let existing_size = if incomplete_marker(output_path).exists() {
+            debug!("Restarting interrupted download: {}", output_path.display());
+            0
+        } else if output_path.exists() {
             tokio::fs::metadata(output_path)
                 .await
                 .map(|m| m.len())
@@ -256,9 +530,18 @@ impl ConcurrentDownloader {
 
         // Get file info from server
         let file_info = self.get_file_info(url).await?;
+        if let Some(watch) = watch {
+            let attempt = WatchedAttempt {
+                url: url.to_string(),
+                total_size: file_info.total_size,
+                etag: file_info.etag.clone(),
+                last_modified: file_info.last_modified.clone(),
+            };
+            watch.begin(attempt, existing_size);
+        }
 
-        // Check if file is already complete
-        if existing_size == file_info.total_size {
+        // Check if file is already complete (unknown sizes are always downloaded)
+        if file_info.total_size > 0 && existing_size == file_info.total_size {
             info!(
                 "File already exists and is complete: {}",
                 output_path.display()
@@ -270,11 +553,15 @@ impl ConcurrentDownloader {
                 speed: 0.0,
                 url: url.to_string(),
                 resumed: false,
+                etag: file_info.etag,
+                last_modified: file_info.last_modified,
+                from_cache: false,
+                resolved_tag: None,
             });
         }
 
         // Determine download strategy
-        if file_info.supports_ranges && file_info.total_size > self.min_chunk_size * 2 {
+        if ranges && file_info.supports_ranges && file_info.total_size > self.min_chunk_size * 2 {
             // Use concurrent chunked download
             self.download_with_chunks(
                 url,
@@ -282,11 +569,12 @@ impl ConcurrentDownloader {
                 &file_info,
                 existing_size,
                 progress_tracker,
+                watch,
             )
             .await
         } else {
             // Use single-threaded download
-            self.download_single_thread(url, output_path, existing_size, progress_tracker)
+            self.download_single_thread(url, output_path, existing_size, progress_tracker, watch)
                 .await
         }
     }
@@ -295,12 +583,16 @@ impl ConcurrentDownloader {
     async fn get_file_info(&self, url: &str) -> Result<FileInfo> {
         debug!("Getting file info for: {}", url);
 
+        let request = self.credentials.authorize(self.http_client.head(url), url);
         let response = self
-            .http_client
-            .head(url)
-            .send()
-            .await
-            .map_err(|e| TurboCdnError::network(format!("Failed to get file info: {e}")))?;
+            .middleware
+            .send(
+                self.http_backend.as_ref(),
+                request,
+                RequestKind::Probe,
+                |e| crate::client_builder::request_error("Failed to get file info", e),
+            )
+            .await?;
 
         let status = response.status();
         if !status.is_success() {
@@ -329,6 +621,8 @@ impl ConcurrentDownloader {
         Ok(FileInfo {
             total_size,
             supports_ranges,
+            etag: header_string(response.headers(), "etag"),
+            last_modified: header_string(response.headers(), "last-modified"),
         })
     }
 
@@ -339,7 +633,8 @@ impl ConcurrentDownloader {
         output_path: P,
         file_info: &FileInfo,
         existing_size: u64,
-        progress_tracker: Option<Arc<ProgressTracker>>,
+        _progress_tracker: Option<Arc<ProgressTracker>>,
+        watch: Option<&Arc<DownloadWatch>>,
     ) -> Result<DownloadResult> {
         let output_path = output_path.as_ref();
         let remaining_size = file_info.total_size - existing_size;
@@ -352,48 +647,70 @@ impl ConcurrentDownloader {
         // Calculate chunks
         let chunks = self.calculate_chunks(existing_size, file_info.total_size);
         debug!("Created {} chunks", chunks.len());
+        if let Some(watch) = watch {
+            watch.set_chunks(&chunks);
+        }
 
-        // Create or open output file
-        let file = if existing_size > 0 {
-            OpenOptions::new()
-                .write(true)
-                .open(output_path)
-                .await
-                .map_err(|e| TurboCdnError::io(format!("Failed to open file: {e}")))?
-        } else {
-            File::create(output_path)
-                .await
-                .map_err(|e| TurboCdnError::io(format!("Failed to create file: {e}")))?
-        };
-
-        let file = Arc::new(tokio::sync::Mutex::new(file));
+        let writer = Arc::new(
+            self.open_writer(output_path, file_info.total_size, existing_size)
+                .await?,
+        );
 
         // Limit concurrent downloads
         let semaphore = Arc::new(Semaphore::new(self.max_concurrent_chunks));
 
-        // Download chunks concurrently
-        let mut tasks = Vec::new();
+        // Download chunks concurrently, dropping the set aborts the chunks still running
+        let mut tasks = JoinSet::new();
         for chunk in chunks {
-            let client = self.http_client.clone();
+            let request = self.credentials.authorize(self.http_client.get(url), url);
+            let backend = self.http_backend.clone();
+            let middleware = self.middleware.clone();
             let url = url.to_string();
-            let file = file.clone();
+            let writer = writer.clone();
+            let buffer_pool = self.buffer_pool.clone();
             let semaphore = semaphore.clone();
-            let progress_tracker = progress_tracker.clone();
+            let watch = watch.cloned();
 
-            let task = tokio::spawn(async move {
+            tasks.spawn(async move {
                 let _permit = semaphore.acquire().await.unwrap();
-                Self::download_chunk(client, &url, chunk, file, progress_tracker).await
+                let output = ChunkOutput {
+                    writer: &writer,
+                    buffer_pool: &buffer_pool,
+                    watch: watch.as_deref(),
+                };
+                Self::download_chunk(request, backend.as_ref(), &middleware, &url, chunk, output)
+                    .await
             });
+        }
 
-            tasks.push(task);
+        // Wait for all chunks to complete before touching the file again
+        let mut failure = None;
+        while let Some(result) = tasks.join_next().await {
+            let result = result
+                .map_err(|e| TurboCdnError::network(format!("Chunk download failed: {e}")))
+                .and_then(|result| {
+                    result.map_err(|e| {
+                        TurboCdnError::network(format!("Chunk processing failed: {e}"))
+                    })
+                });
+            if let Err(e) = result {
+                failure.get_or_insert(e);
+            }
         }
 
-        // Wait for all chunks to complete
-        for task in tasks {
-            task.await
-                .map_err(|e| TurboCdnError::network(format!("Chunk download failed: {e}")))?
-                .map_err(|e| TurboCdnError::network(format!("Chunk processing failed: {e}")))?;
+        let result = match failure {
+            Some(e) => Err(e),
+            None => writer.finish().await,
+        };
+        if let Err(e) = result {
+            drop(writer);
+            if let Some(watch) = watch {
+                watch.reset();
+            }
+            discard_partial(output_path, existing_size);
+            return Err(e);
         }
+        mark_complete(output_path);
 
         Ok(DownloadResult {
             path: output_path.to_path_buf(),
@@ -402,6 +719,10 @@ impl ConcurrentDownloader {
             speed: 0.0,                       // Will be set by caller
             url: url.to_string(),
             resumed: existing_size > 0,
+            etag: file_info.etag.clone(),
+            last_modified: file_info.last_modified.clone(),
+            from_cache: false,
+            resolved_tag: None,
         })
     }
 
@@ -509,13 +830,14 @@ impl ConcurrentDownloader {
         }
     }
 
-    /// Download a single chunk
+    /// Download a single chunk with an authorized `GET` request
     async fn download_chunk(
-        client: Client,
+        request: reqwest::RequestBuilder,
+        backend: &dyn HttpBackend,
+        middleware: &MiddlewareStack,
         url: &str,
         chunk: ChunkInfo,
-        file: Arc<tokio::sync::Mutex<File>>,
-        _progress_tracker: Option<Arc<ProgressTracker>>,
+        output: ChunkOutput<'_>,
     ) -> Result<()> {
         debug!(
             "Downloading chunk {}: bytes {}-{}",
@@ -523,12 +845,12 @@ impl ConcurrentDownloader {
         );
 
         let range_header = format!("bytes={}-{}", chunk.start, chunk.end);
-        let response = client
-            .get(url)
-            .header("Range", range_header)
-            .send()
-            .await
-            .map_err(|e| TurboCdnError::network(format!("Failed to download chunk: {e}")))?;
+        let request = request.header("Range", range_header);
+        let response = middleware
+            .send(backend, request, RequestKind::Chunk, |e| {
+                crate::client_builder::request_error("Failed to download chunk", e)
+            })
+            .await?;
 
         let status = response.status();
         if !status.is_success() && status.as_u16() != 206 {
@@ -536,27 +858,118 @@ impl ConcurrentDownloader {
             return Err(TurboCdnError::from_status_code(status_code, url));
         }
 
-        let bytes = response
-            .bytes()
-            .await
-            .map_err(|e| TurboCdnError::network(format!("Failed to read chunk data: {e}")))?;
+        // Stage the body in a pooled buffer and write it out whenever it fills up
+        let mut buffer = output.buffer_pool.get_buffer();
+        let watch = output.watch.map(|watch| (watch, chunk.index));
+        let result =
+            Self::stream_chunk(response, chunk.start, output.writer, &mut buffer, watch).await;
+        output.buffer_pool.return_buffer(buffer);
+        let written = result?;
 
-        // Write chunk to file
-        let mut file_guard = file.lock().await;
-        file_guard
-            .seek(SeekFrom::Start(chunk.start))
-            .await
-            .map_err(|e| TurboCdnError::io(format!("Failed to seek in file: {e}")))?;
+        debug!("Completed chunk {}: {} bytes", chunk.index, written);
+        Ok(())
+    }
 
-        file_guard
-            .write_all(&bytes)
-            .await
-            .map_err(|e| TurboCdnError::io(format!("Failed to write chunk: {e}")))?;
+    /// Stream a response body to `writer` starting at `offset`
+    ///
+    /// With a `watch` and the index of the chunk, written bytes are reported
+    /// to it. Followed downloads write every network frame right away rather
+    /// than staging it. Returns the number of bytes written.
+    async fn stream_chunk(
+        response: reqwest::Response,
+        offset: u64,
+        writer: &FileWriter,
+        buffer: &mut Vec<u8>,
+        watch: Option<(&DownloadWatch, usize)>,
+    ) -> Result<u64> {
+        use futures_util::StreamExt;
+
+        let capacity = buffer.capacity();
+        let followed = watch.is_some_and(|(watch, _)| watch.is_followed());
+        let mut stream = response.bytes_stream();
+        let mut position = offset;
 
-        debug!("Completed chunk {}: {} bytes", chunk.index, bytes.len());
+        while let Some(bytes) = stream.next().await {
+            let bytes = bytes
+                .map_err(|e| TurboCdnError::network(format!("Failed to read chunk data: {e}")))?;
+
+            if buffer.len() + bytes.len() > capacity && !buffer.is_empty() {
+                position = Self::write_staged(writer, position, buffer).await?;
+                Self::report_written(writer, watch, position).await?;
+            }
+
+            if bytes.len() >= capacity || followed {
+                // Write straight from the network frame
+                let len = bytes.len() as u64;
+                writer.write_owned(position, bytes).await.1?;
+                position += len;
+                Self::report_written(writer, watch, position).await?;
+            } else {
+                buffer.extend_from_slice(&bytes);
+            }
+        }
+
+        if !buffer.is_empty() {
+            position = Self::write_staged(writer, position, buffer).await?;
+            Self::report_written(writer, watch, position).await?;
+        }
+
+        Ok(position - offset)
+    }
+
+    /// Tell the watch, if any, that a chunk is written up to `position`
+    async fn report_written(
+        writer: &FileWriter,
+        watch: Option<(&DownloadWatch, usize)>,
+        position: u64,
+    ) -> Result<()> {
+        if let Some((watch, index)) = watch {
+            if watch.is_followed() {
+                writer.flush().await?;
+            }
+            watch.written(index, position);
+        }
         Ok(())
     }
 
+    /// Write the staged bytes at `position`, keeping the buffer's allocation
+    ///
+    /// Returns the position after the written bytes.
+    async fn write_staged(writer: &FileWriter, position: u64, buffer: &mut Vec<u8>) -> Result<u64> {
+        let len = buffer.len() as u64;
+        let (mut staged, result) = writer.write_owned(position, std::mem::take(buffer)).await;
+        staged.clear();
+        *buffer = staged;
+        result.map(|()| position + len)
+    }
+
+    /// Open the output file with the configured write backend
+    ///
+    /// Files of known size are preallocated, so they are marked incomplete
+    /// until the download finishes.
+    async fn open_writer(
+        &self,
+        path: &Path,
+        total_size: u64,
+        existing_size: u64,
+    ) -> Result<FileWriter> {
+        let writer = FileWriter::open(
+            path,
+            total_size,
+            existing_size,
+            self.write_backend,
+            self.mmap_threshold,
+        )
+        .await?;
+
+        if total_size > 0 {
+            if let Err(e) = std::fs::write(incomplete_marker(path), b"") {
+                warn!("Failed to mark {} as incomplete: {}", path.display(), e);
+            }
+        }
+        Ok(writer)
+    }
+
     /// Download with single thread (fallback)
     async fn download_single_thread<P: AsRef<Path>>(
         &self,
@@ -564,20 +977,26 @@ impl ConcurrentDownloader {
         output_path: P,
         existing_size: u64,
         _progress_tracker: Option<Arc<ProgressTracker>>,
+        watch: Option<&Arc<DownloadWatch>>,
     ) -> Result<DownloadResult> {
         info!("Starting single-threaded download");
 
-        let mut request = self.http_client.get(url);
+        let mut request = self.credentials.authorize(self.http_client.get(url), url);
 
         // Add range header for resume if file exists
         if existing_size > 0 {
             request = request.header("Range", format!("bytes={existing_size}-"));
         }
 
-        let response = request
-            .send()
-            .await
-            .map_err(|e| TurboCdnError::network(format!("Failed to start download: {e}")))?;
+        let response = self
+            .middleware
+            .send(
+                self.http_backend.as_ref(),
+                request,
+                RequestKind::Download,
+                |e| crate::client_builder::request_error("Failed to start download", e),
+            )
+            .await?;
 
         let status = response.status();
         if !status.is_success() && status.as_u16() != 206 {
@@ -585,38 +1004,41 @@ impl ConcurrentDownloader {
             return Err(TurboCdnError::from_status_code(status_code, url));
         }
 
-        // Open or create file
-        let mut file = if existing_size > 0 {
-            OpenOptions::new()
-                .append(true)
-                .open(output_path.as_ref())
-                .await
-                .map_err(|e| TurboCdnError::io(format!("Failed to open file: {e}")))?
-        } else {
-            File::create(output_path.as_ref())
-                .await
-                .map_err(|e| TurboCdnError::io(format!("Failed to create file: {e}")))?
-        };
-
-        // Stream download
-        let mut stream = response.bytes_stream();
-        let mut downloaded_bytes = existing_size;
-
-        use futures_util::StreamExt;
-        while let Some(chunk) = stream.next().await {
-            let chunk =
-                chunk.map_err(|e| TurboCdnError::network(format!("Failed to read chunk: {e}")))?;
-
-            file.write_all(&chunk)
-                .await
-                .map_err(|e| TurboCdnError::io(format!("Failed to write chunk: {e}")))?;
+        let etag = header_string(response.headers(), "etag");
+        let last_modified = header_string(response.headers(), "last-modified");
 
-            downloaded_bytes += chunk.len() as u64;
-        }
+        // Content-Length covers only the requested remainder when resuming
+        let total_size = response
+            .content_length()
+            .map(|length| length + existing_size)
+            .unwrap_or(0);
+        let writer = self
+            .open_writer(output_path.as_ref(), total_size, existing_size)
+            .await?;
 
-        file.flush()
-            .await
-            .map_err(|e| TurboCdnError::io(format!("Failed to flush file: {e}")))?;
+        // Stream download
+        let mut buffer = self.buffer_pool.get_buffer();
+        let watch_chunk = watch.map(|watch| (watch.as_ref(), 0));
+        let result =
+            Self::stream_chunk(response, existing_size, &writer, &mut buffer, watch_chunk).await;
+        self.buffer_pool.return_buffer(buffer);
+
+        let result = match result {
+            Ok(written) => writer.finish().await.map(|_| written),
+            Err(e) => Err(e),
+        };
+        let downloaded_bytes = match result {
+            Ok(written) => existing_size + written,
+            Err(e) => {
+                drop(writer);
+                if let Some(watch) = watch {
+                    watch.reset();
+                }
+                discard_partial(output_path.as_ref(), existing_size);
+                return Err(e);
+            }
+        };
+        mark_complete(output_path.as_ref());
 
         Ok(DownloadResult {
             path: output_path.as_ref().to_path_buf(),
@@ -625,9 +1047,102 @@ impl ConcurrentDownloader {
             speed: 0.0,                       // Will be set by caller
             url: url.to_string(),
             resumed: existing_size > 0,
+            etag,
+            last_modified,
+            from_cache: false,
+            resolved_tag: None,
         })
     }
 
+    /// Check whether a previously downloaded resource is still current
+    ///
+    /// Sends a conditional HEAD request using the stored validators and
+    /// returns `true` when the server answers `304 Not Modified` or reports
+    /// the same validators.
+    pub async fn revalidate(
+        &self,
+        url: &str,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> Result<bool> {
+        if self.offline {
+            return Err(TurboCdnError::offline(format!("revalidation of {url}")));
+        }
+        if etag.is_none() && last_modified.is_none() {
+            return Ok(false);
+        }
+        self.security.check_url(url)?;
+
+        let mut request = self.credentials.authorize(self.http_client.head(url), url);
+        if let Some(etag) = etag {
+            request = request.header("If-None-Match", etag);
+        }
+        if let Some(last_modified) = last_modified {
+            request = request.header("If-Modified-Since", last_modified);
+        }
+
+        let response = self
+            .middleware
+            .send(
+                self.http_backend.as_ref(),
+                request,
+                RequestKind::Revalidate,
+                |e| crate::client_builder::request_error(&format!("Failed to revalidate {url}"), e),
+            )
+            .await?;
+
+        let status = response.status();
+        if status.as_u16() == 304 {
+            return Ok(true);
+        }
+        if !status.is_success() {
+            return Err(TurboCdnError::from_status_code(status.as_u16(), url));
+        }
+
+        // Servers that ignore conditional headers still expose validators
+        let current_etag = header_string(response.headers(), "etag");
+        let current_last_modified = header_string(response.headers(), "last-modified");
+        let fresh = match (etag, current_etag.as_deref()) {
+            (Some(stored), Some(current)) => stored == current,
+            _ => last_modified.is_some() && last_modified == current_last_modified.as_deref(),
+        };
+
+        Ok(fresh)
+    }
+
+    /// Get the HTTP client requests are built with
+    ///
+    /// With a custom backend this client only assembles requests; send
+    /// them with [`Self::http_backend`].
+    pub fn http_client(&self) -> &Client {
+        &self.http_client
+    }
+
+    /// Get the mirror catalog handle, to share it with the URL mapper
+    pub fn shared_mirrors(&self) -> SharedMirrorCatalog {
+        self.mirrors.clone()
+    }
+
+    /// The mirror catalog in use right now
+    fn mirror_snapshot(&self) -> Arc<MirrorCatalog> {
+        self.mirrors.read().unwrap().clone()
+    }
+
+    /// Get the backend requests are sent with
+    pub fn http_backend(&self) -> &Arc<dyn HttpBackend> {
+        &self.http_backend
+    }
+
+    /// Get the middleware requests are sent through
+    pub fn middleware(&self) -> &MiddlewareStack {
+        &self.middleware
+    }
+
+    /// Get the server tracker fed by this downloader
+    pub fn server_tracker(&self) -> Arc<std::sync::Mutex<ServerTracker>> {
+        self.server_performance_tracker.clone()
+    }
+
     /// Get server performance statistics
     pub fn get_server_stats(&self) -> crate::server_tracker::PerformanceSummary {
         let tracker = self.server_performance_tracker.lock().unwrap();
@@ -641,11 +1156,94 @@ impl ConcurrentDownloader {
     }
 }
 
+/// Drop data written by a failed download
+///
+/// Chunks land out of order and memory-mapped files are sized up front, so
+/// after a failure only the part present before this attempt is known to be
+/// contiguous. Truncating back to it keeps size-based resume correct.
+fn discard_partial(path: &Path, valid_len: u64) {
+    let result = std::fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_len(valid_len));
+    match result {
+        Ok(()) => mark_complete(path),
+        Err(e) => warn!(
+            "Failed to truncate partial download {}: {}",
+            path.display(),
+            e
+        ),
+    }
+}
+
+/// Drop the candidates whose mirror can't serve the request, keeping the
+/// last reason in `skipped`
+fn compatible_urls(
+    mirrors: &MirrorCatalog,
+    urls: &[String],
+    file_size: Option<u64>,
+    resume: bool,
+    skipped: &mut Option<String>,
+) -> Vec<String> {
+    urls.iter()
+        .filter(|url| {
+            let Some(mirror) = mirrors.find_by_url(url) else {
+                return true;
+            };
+            let shape = RequestShape::for_url(url, file_size, resume);
+            match mirror.capabilities.incompatibility(&shape) {
+                Some(reason) => {
+                    info!("Skipping mirror '{}' for {}: {}", mirror.name, url, reason);
+                    *skipped = Some(reason);
+                    false
+                }
+                None => true,
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+/// Marker file kept next to a download while its contents may have gaps
+pub(crate) fn incomplete_marker(path: &Path) -> PathBuf {
+    let mut marker = path.as_os_str().to_owned();
+    marker.push(".incomplete");
+    PathBuf::from(marker)
+}
+
+/// Remove the incomplete marker of a download
+fn mark_complete(path: &Path) {
+    let marker = incomplete_marker(path);
+    if let Err(e) = std::fs::remove_file(&marker) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            warn!("Failed to remove {}: {}", marker.display(), e);
+        }
+    }
+}
+
+/// Where the chunks of a download are written
+struct ChunkOutput<'a> {
+    writer: &'a FileWriter,
+    buffer_pool: &'a BufferPool,
+    /// Follows the written bytes, if anyone reads the download while it runs
+    watch: Option<&'a DownloadWatch>,
+}
+
 /// File information from server
 #[derive(Debug, Clone)]
 struct FileInfo {
     total_size: u64,
     supports_ranges: bool,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+/// Read a header value as an owned string
+fn header_string(headers: &reqwest::header::HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
 }
 
 impl Default for ConcurrentDownloader {
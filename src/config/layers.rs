@@ -0,0 +1,647 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Layered configuration loading
+//!
+//! Layers are merged in order, later layers win:
+//!
+//! 1. the built-in `default.toml`
+//! 2. the system file (`/etc/turbo-cdn/config.toml`)
+//! 3. the user file (`~/.config/turbo-cdn/config.toml`)
+//! 4. the project file (`./turbo-cdn.toml`)
+//! 5. `TURBO_CDN_<SECTION>__<KEY>` environment variables
+//! 6. command line overrides
+//!
+//! Tables are merged key by key, other values are replaced. Entries of
+//! `url_mapping_rules` and `mirrors` are matched by `name`: an entry with a
+//! known name is merged into it, so `{ name = "...", enabled = false }` turns
+//! off a built-in rule, and entries with new names are appended.
+
+use super::TurboCdnConfig;
+use crate::error::{Result, TurboCdnError};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// Prefix of configuration environment variables
+pub const ENV_PREFIX: &str = "TURBO_CDN_";
+
+/// File name of the project configuration file
+pub const PROJECT_CONFIG_FILE: &str = "turbo-cdn.toml";
+
+/// Arrays whose entries are merged by `name`
+const NAMED_ARRAYS: &[&str] = &["url_mapping_rules", "mirrors"];
+
+/// Where a configuration value comes from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "source", rename_all = "snake_case")]
+pub enum ConfigOrigin {
+    /// The embedded `default.toml`
+    BuiltIn,
+    /// A configuration file
+    File(PathBuf),
+    /// An environment variable
+    Env(String),
+    /// A command line flag
+    Cli,
+}
+
+impl std::fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BuiltIn => write!(f, "built-in"),
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Env(name) => write!(f, "env {name}"),
+            Self::Cli => write!(f, "command line"),
+        }
+    }
+}
+
+/// Loads configuration layers and merges them
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLoader {
+    /// Files in merge order, and whether each must exist
+    files: Vec<(PathBuf, bool)>,
+    /// `TURBO_CDN_*` variables
+    env: Vec<(String, String)>,
+    /// Dotted keys and raw values from the command line
+    overrides: Vec<(String, String)>,
+}
+
+impl ConfigLoader {
+    /// Loader for the built-in defaults only
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loader for the built-in defaults, the standard files and the
+    /// `TURBO_CDN_*` environment variables
+    pub fn standard() -> Self {
+        Self::new()
+            .with_standard_files()
+            .with_env_vars(std::env::vars())
+    }
+
+    /// Add the system, user and project files that exist
+    pub fn with_standard_files(mut self) -> Self {
+        let files = [system_config_file(), user_config_file()]
+            .into_iter()
+            .flatten()
+            .chain([PathBuf::from(PROJECT_CONFIG_FILE)]);
+        self.files.extend(files.map(|path| (path, false)));
+        self
+    }
+
+    /// Add a file that must exist
+    pub fn with_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.files.push((path.into(), true));
+        self
+    }
+
+    /// Add a file that is skipped if it doesn't exist
+    pub fn with_optional_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.files.push((path.into(), false));
+        self
+    }
+
+    /// Add environment variables, those without the `TURBO_CDN_` prefix or a
+    /// `__` section separator are ignored
+    pub fn with_env_vars<I: IntoIterator<Item = (String, String)>>(mut self, vars: I) -> Self {
+        let mut vars: Vec<_> = vars
+            .into_iter()
+            .filter(|(name, _)| {
+                name.strip_prefix(ENV_PREFIX)
+                    .is_some_and(|key| key.contains("__"))
+            })
+            .collect();
+        // Deterministic order regardless of the environment's
+        vars.sort();
+        self.env.extend(vars);
+        self
+    }
+
+    /// Override a dotted key, e.g. `performance.max_concurrent_downloads`
+    ///
+    /// The value is parsed as TOML (`16`, `true`, `["a", "b"]`) and taken as
+    /// a string if that fails.
+    pub fn with_override<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.overrides.push((key.into(), value.into()));
+        self
+    }
+
+    /// Files in merge order, including optional ones that don't exist
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|(path, _)| path.as_path())
+    }
+
+    /// Merge all layers into a configuration
+    pub fn load(&self) -> Result<LoadedConfig> {
+        let mut merged = Table::new();
+        let mut origins = BTreeMap::new();
+        let mut files = Vec::new();
+
+        let builtin: Table = toml::from_str(include_str!("default.toml"))
+            .map_err(|e| TurboCdnError::config(format!("Invalid built-in configuration: {e}")))?;
+        merge(
+            &mut merged,
+            builtin,
+            &[],
+            &ConfigOrigin::BuiltIn,
+            &mut origins,
+        );
+
+        for (path, required) in &self.files {
+            let Some(layer) = read_layer(path, *required)? else {
+                continue;
+            };
+            let origin = ConfigOrigin::File(path.clone());
+            merge(&mut merged, layer, &[], &origin, &mut origins);
+            files.push(path.clone());
+        }
+
+        for (name, value) in &self.env {
+            let key = name[ENV_PREFIX.len()..].to_lowercase().replace("__", ".");
+            let layer = key_layer(&key, value)?;
+            let origin = ConfigOrigin::Env(name.clone());
+            merge(&mut merged, layer, &[], &origin, &mut origins);
+        }
+
+        for (key, value) in &self.overrides {
+            let layer = key_layer(key, value)?;
+            merge(&mut merged, layer, &[], &ConfigOrigin::Cli, &mut origins);
+        }
+
+        let config: TurboCdnConfig = merged
+            .try_into()
+            .map_err(|e| TurboCdnError::config(format!("Invalid configuration: {e}")))?;
+
+        Ok(LoadedConfig {
+            config,
+            origins,
+            files,
+        })
+    }
+}
+
+/// A merged configuration and where its values come from
+#[derive(Debug, Clone)]
+pub struct LoadedConfig {
+    /// The merged configuration
+    pub config: TurboCdnConfig,
+    /// Origin of every value set by a layer, keyed by path segments
+    origins: BTreeMap<Vec<String>, ConfigOrigin>,
+    /// Files that were found and merged, in order
+    pub files: Vec<PathBuf>,
+}
+
+/// A configuration value with its origin
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigEntry {
+    /// Dotted key, entries of named arrays are written `rules[name]`
+    pub key: String,
+    /// Effective value
+    pub value: Value,
+    /// Layer the value comes from
+    pub origin: ConfigOrigin,
+}
+
+impl LoadedConfig {
+    /// Origin of a dotted key such as `general.offline` or
+    /// `url_mapping_rules[GitHub Raw Files].enabled`
+    ///
+    /// Values no layer set, i.e. serde defaults, inherit the origin of the
+    /// closest table that was set.
+    pub fn origin(&self, key: &str) -> &ConfigOrigin {
+        let mut segments = parse_key(key);
+        loop {
+            if let Some(origin) = self.origins.get(&segments) {
+                return origin;
+            }
+            if segments.pop().is_none() {
+                return &ConfigOrigin::BuiltIn;
+            }
+        }
+    }
+
+    /// Record that a value was changed after loading, e.g. by a CLI flag
+    pub fn set_origin(&mut self, key: &str, origin: ConfigOrigin) {
+        let segments = parse_key(key);
+        self.origins
+            .retain(|path, _| !(path.len() > segments.len() && path.starts_with(&segments)));
+        self.origins.insert(segments, origin);
+    }
+
+    /// Every value of the effective configuration with its origin
+    pub fn entries(&self) -> Result<Vec<ConfigEntry>> {
+        let table = Table::try_from(&self.config)
+            .map_err(|e| TurboCdnError::config(format!("Cannot serialize configuration: {e}")))?;
+        let mut leaves = Vec::new();
+        flatten(&Value::Table(table), &mut Vec::new(), &mut leaves);
+
+        Ok(leaves
+            .into_iter()
+            .map(|(segments, value)| {
+                let key = display_key(&segments);
+                ConfigEntry {
+                    origin: self.origin(&key).clone(),
+                    key,
+                    value,
+                }
+            })
+            .collect())
+    }
+}
+
+/// System-wide configuration file
+pub fn system_config_file() -> Option<PathBuf> {
+    #[cfg(windows)]
+    let base = std::env::var_os("PROGRAMDATA").map(PathBuf::from);
+    #[cfg(not(windows))]
+    let base = Some(PathBuf::from("/etc"));
+
+    base.map(|base| base.join("turbo-cdn").join("config.toml"))
+}
+
+/// Per-user configuration file
+pub fn user_config_file() -> Option<PathBuf> {
+    #[cfg(windows)]
+    let base = std::env::var_os("APPDATA").map(PathBuf::from);
+    #[cfg(not(windows))]
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+
+    base.map(|base| base.join("turbo-cdn").join("config.toml"))
+}
+
+/// Read a configuration file, `None` if it is optional and missing
+fn read_layer(path: &Path, required: bool) -> Result<Option<Table>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && !required => return Ok(None),
+        Err(e) => {
+            return Err(TurboCdnError::config(format!(
+                "Cannot read configuration file {}: {e}",
+                path.display()
+            )))
+        }
+    };
+
+    toml::from_str(&content).map(Some).map_err(|e| {
+        TurboCdnError::config(format!(
+            "Invalid configuration file {}: {e}",
+            path.display()
+        ))
+    })
+}
+
+/// Build a layer that sets a single dotted key
+fn key_layer(key: &str, raw: &str) -> Result<Table> {
+    let segments: Vec<&str> = key.split('.').collect();
+    if segments.iter().any(|segment| segment.is_empty()) {
+        return Err(TurboCdnError::config(format!(
+            "Invalid configuration key '{key}'"
+        )));
+    }
+
+    let mut value = parse_value(raw);
+    for segment in segments.iter().skip(1).rev() {
+        value = Value::Table(Table::from_iter([(segment.to_string(), value)]));
+    }
+    Ok(Table::from_iter([(segments[0].to_string(), value)]))
+}
+
+/// Parse a raw value as TOML, falling back to a string
+fn parse_value(raw: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {raw}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+/// Merge `layer` into `base`, recording the origin of everything it sets
+fn merge(
+    base: &mut Table,
+    layer: Table,
+    path: &[String],
+    origin: &ConfigOrigin,
+    origins: &mut BTreeMap<Vec<String>, ConfigOrigin>,
+) {
+    for (key, value) in layer {
+        let child = child_path(path, key.clone());
+
+        if path.is_empty() && is_named_array(&key) && value.is_array() {
+            let existing = base
+                .entry(key.clone())
+                .or_insert_with(|| Value::Array(Vec::new()));
+            if !existing.is_array() {
+                *existing = Value::Array(Vec::new());
+            }
+            if let (Value::Array(existing), Value::Array(entries)) = (existing, value) {
+                merge_named(existing, entries, &child, origin, origins);
+            }
+            continue;
+        }
+
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(existing)), Value::Table(table)) => {
+                merge(existing, table, &child, origin, origins);
+            }
+            (_, value) => {
+                origins.retain(|p, _| !p.starts_with(&child));
+                record(&value, &child, origin, origins);
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Merge entries of a named array, matching them by `name`
+fn merge_named(
+    base: &mut Vec<Value>,
+    entries: Vec<Value>,
+    path: &[String],
+    origin: &ConfigOrigin,
+    origins: &mut BTreeMap<Vec<String>, ConfigOrigin>,
+) {
+    for entry in entries {
+        let name = entry
+            .get("name")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let existing = name.as_ref().and_then(|name| {
+            base.iter_mut()
+                .find(|item| item.get("name").and_then(Value::as_str) == Some(name))
+        });
+
+        match (existing, entry) {
+            (Some(Value::Table(existing)), Value::Table(table)) => {
+                let child = child_path(path, format!("[{}]", name.unwrap_or_default()));
+                merge(existing, table, &child, origin, origins);
+            }
+            (_, entry) => {
+                let label = name.unwrap_or_else(|| base.len().to_string());
+                let child = child_path(path, format!("[{label}]"));
+                record(&entry, &child, origin, origins);
+                base.push(entry);
+            }
+        }
+    }
+}
+
+/// Record the origin of a value and everything below it
+fn record(
+    value: &Value,
+    path: &[String],
+    origin: &ConfigOrigin,
+    origins: &mut BTreeMap<Vec<String>, ConfigOrigin>,
+) {
+    origins.insert(path.to_vec(), origin.clone());
+    if let Value::Table(table) = value {
+        for (key, value) in table {
+            record(value, &child_path(path, key.clone()), origin, origins);
+        }
+    }
+}
+
+/// Collect the leaves of a value, entries of named arrays are descended into
+fn flatten(value: &Value, path: &mut Vec<String>, leaves: &mut Vec<(Vec<String>, Value)>) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                path.push(key.clone());
+                flatten(value, path, leaves);
+                path.pop();
+            }
+        }
+        Value::Array(entries) if path.len() == 1 && is_named_array(&path[0]) => {
+            for (position, entry) in entries.iter().enumerate() {
+                let name = entry.get("name").and_then(Value::as_str);
+                path.push(format!(
+                    "[{}]",
+                    name.map_or(position.to_string(), str::to_string)
+                ));
+                flatten(entry, path, leaves);
+                path.pop();
+            }
+        }
+        value => leaves.push((path.clone(), value.clone())),
+    }
+}
+
+fn child_path(path: &[String], segment: String) -> Vec<String> {
+    let mut child = path.to_vec();
+    child.push(segment);
+    child
+}
+
+fn is_named_array(key: &str) -> bool {
+    NAMED_ARRAYS.contains(&key)
+}
+
+/// Join path segments into a dotted key
+fn display_key(segments: &[String]) -> String {
+    let mut key = String::new();
+    for segment in segments {
+        if !key.is_empty() && !segment.starts_with('[') {
+            key.push('.');
+        }
+        key.push_str(segment);
+    }
+    key
+}
+
+/// Split a dotted key into path segments, `[...]` is a segment of its own
+fn parse_key(key: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut chars = key.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => segments.extend((!current.is_empty()).then(|| std::mem::take(&mut current))),
+            '[' => {
+                segments.extend((!current.is_empty()).then(|| std::mem::take(&mut current)));
+                let name: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                segments.push(format!("[{name}]"));
+            }
+            c => current.push(c),
+        }
+    }
+    segments.extend((!current.is_empty()).then_some(current));
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_file(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::File::create(&path)
+            .unwrap()
+            .write_all(content.as_bytes())
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn test_key_round_trip() {
+        let key = "url_mapping_rules[GitHub Raw Files].enabled";
+        assert_eq!(
+            parse_key(key),
+            vec!["url_mapping_rules", "[GitHub Raw Files]", "enabled"]
+        );
+        assert_eq!(display_key(&parse_key(key)), key);
+        assert_eq!(parse_key("mirrors[a.b]"), vec!["mirrors", "[a.b]"]);
+    }
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(parse_value("16"), Value::Integer(16));
+        assert_eq!(parse_value("true"), Value::Boolean(true));
+        assert_eq!(parse_value("China"), Value::String("China".to_string()));
+        assert_eq!(
+            parse_value(r#"["a", "b"]"#),
+            Value::Array(vec!["a".into(), "b".into()])
+        );
+    }
+
+    #[test]
+    fn test_layers_deep_merge() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let user = write_file(
+            dir.path(),
+            "user.toml",
+            "[performance]\nmax_concurrent_downloads = 3\n",
+        );
+        let project = write_file(
+            dir.path(),
+            "project.toml",
+            "[performance]\ntimeout = 7\n[general]\noffline = true\n",
+        );
+
+        let loaded = ConfigLoader::new()
+            .with_file(&user)
+            .with_optional_file(dir.path().join("missing.toml"))
+            .with_file(&project)
+            .with_env_vars([
+                (
+                    "TURBO_CDN_PERFORMANCE__TIMEOUT".to_string(),
+                    "9".to_string(),
+                ),
+                ("TURBO_CDN_IGNORED".to_string(), "1".to_string()),
+                ("PATH".to_string(), "/bin".to_string()),
+            ])
+            .with_override("general.offline", "false")
+            .load()
+            .unwrap();
+
+        let builtin = TurboCdnConfig::load().unwrap();
+        let config = &loaded.config;
+        assert_eq!(config.performance.max_concurrent_downloads, 3);
+        assert_eq!(config.performance.timeout, 9);
+        assert!(!config.general.offline);
+        assert_eq!(
+            config.performance.chunk_size,
+            builtin.performance.chunk_size
+        );
+        assert_eq!(loaded.files, vec![user.clone(), project]);
+
+        assert_eq!(
+            loaded.origin("performance.max_concurrent_downloads"),
+            &ConfigOrigin::File(user)
+        );
+        assert_eq!(
+            loaded.origin("performance.timeout"),
+            &ConfigOrigin::Env("TURBO_CDN_PERFORMANCE__TIMEOUT".to_string())
+        );
+        assert_eq!(loaded.origin("general.offline"), &ConfigOrigin::Cli);
+        assert_eq!(
+            loaded.origin("performance.chunk_size"),
+            &ConfigOrigin::BuiltIn
+        );
+    }
+
+    #[test]
+    fn test_named_entries_override_and_append() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = write_file(
+            dir.path(),
+            "rules.toml",
+            r#"
+            [[url_mapping_rules]]
+            name = "GitHub Raw Files"
+            enabled = false
+
+            [[url_mapping_rules]]
+            name = "Team mirror"
+            pattern = "^https://example\\.com/(.+)$"
+            replacements = ["https://mirror.example.com/$1"]
+            regions = []
+            priority = 5
+            enabled = true
+
+            [[mirrors]]
+            name = "ghps"
+            enabled = false
+            "#,
+        );
+
+        let loaded = ConfigLoader::new().with_file(&file).load().unwrap();
+        let builtin = TurboCdnConfig::load().unwrap();
+        let rules = &loaded.config.url_mapping_rules;
+
+        assert_eq!(rules.len(), builtin.url_mapping_rules.len() + 1);
+        let raw = rules.iter().find(|r| r.name == "GitHub Raw Files").unwrap();
+        assert!(!raw.enabled);
+        assert!(!raw.replacements.is_empty());
+        assert_eq!(rules.last().unwrap().name, "Team mirror");
+        let ghps = loaded.config.mirrors.iter().find(|m| m.name == "ghps");
+        assert!(!ghps.unwrap().enabled);
+
+        let origin = ConfigOrigin::File(file);
+        assert_eq!(
+            loaded.origin("url_mapping_rules[GitHub Raw Files].enabled"),
+            &origin
+        );
+        assert_eq!(
+            loaded.origin("url_mapping_rules[GitHub Raw Files].pattern"),
+            &ConfigOrigin::BuiltIn
+        );
+        // Defaults filled in by serde inherit the origin of their entry
+        assert_eq!(
+            loaded.origin("url_mapping_rules[Team mirror].examples"),
+            &origin
+        );
+
+        let entries = loaded.entries().unwrap();
+        let entry = entries
+            .iter()
+            .find(|e| e.key == "url_mapping_rules[Team mirror].priority")
+            .unwrap();
+        assert_eq!(entry.value, Value::Integer(5));
+        assert_eq!(entry.origin, origin);
+    }
+
+    #[test]
+    fn test_invalid_layers_are_errors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let broken = write_file(dir.path(), "broken.toml", "[performance\n");
+
+        assert!(ConfigLoader::new().with_file(&broken).load().is_err());
+        assert!(ConfigLoader::new()
+            .with_file(dir.path().join("missing.toml"))
+            .load()
+            .is_err());
+        assert!(ConfigLoader::new()
+            .with_override("performance.timeout", "soon")
+            .load()
+            .is_err());
+        assert!(ConfigLoader::new()
+            .with_override("performance..timeout", "1")
+            .load()
+            .is_err());
+    }
+}
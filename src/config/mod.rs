@@ -8,6 +8,13 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+mod layers;
+
+pub use layers::{
+    system_config_file, user_config_file, ConfigEntry, ConfigLoader, ConfigOrigin, LoadedConfig,
+    ENV_PREFIX, PROJECT_CONFIG_FILE,
+};
+
 /// Main configuration for TurboCdn
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TurboCdnConfig {
@@ -21,6 +28,30 @@ pub struct TurboCdnConfig {
     pub geo_detection: GeoDetectionConfig,
     /// Testing configuration
     pub testing: TestingConfig,
+    /// Local download cache settings
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// Network settings (proxies)
+    #[serde(default)]
+    pub network: NetworkConfig,
+    /// Mirror rewriting policy
+    #[serde(default)]
+    pub policy: PolicyConfig,
+    /// Per-host credentials
+    #[serde(default)]
+    pub credentials: CredentialsConfig,
+    /// GitHub API settings
+    #[serde(default)]
+    pub github: GitHubConfig,
+    /// How URL mapping rules are combined
+    #[serde(default)]
+    pub mapping: MappingConfig,
+    /// Named mirrors that rule replacements can reference
+    #[serde(default)]
+    pub mirrors: Vec<MirrorConfig>,
+    /// Signed remote rule sets merged into the mapping rules
+    #[serde(default)]
+    pub subscriptions: Vec<SubscriptionConfig>,
     /// URL mapping rules
     pub url_mapping_rules: Vec<UrlMappingRuleConfig>,
 }
@@ -33,13 +64,223 @@ pub struct UrlMappingRuleConfig {
     /// Regex pattern to match URLs
     pub pattern: String,
     /// Replacement URL templates (in priority order)
-    pub replacements: Vec<String>,
+    pub replacements: Vec<ReplacementConfig>,
     /// Applicable regions for this rule
     pub regions: Vec<Region>,
     /// Priority (lower = higher priority)
     pub priority: u32,
     /// Whether this rule is enabled
     pub enabled: bool,
+    /// Self-tests run by `turbo-cdn rules check`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<RuleExampleConfig>,
+}
+
+/// Self-test of a URL mapping rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleExampleConfig {
+    /// URL the rule is applied to
+    pub input: String,
+    /// URLs the rule must generate, in order; empty if the rule must not match
+    #[serde(default)]
+    pub expected: Vec<String>,
+    /// Region the templates are rendered for (defaults to Global)
+    #[serde(default)]
+    pub region: Option<Region>,
+}
+
+/// Replacement URL template of a mapping rule
+///
+/// Either a plain template string, a table with an explicit weight
+/// (`{ url = "https://mirror.example.com/$1", weight = 200 }`) or a
+/// reference to a catalog mirror (`{ mirror = "ghproxy", weight = 200 }`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ReplacementConfig {
+    /// Template with the default weight
+    Url(String),
+    /// Template with an explicit weight
+    Weighted {
+        /// URL template
+        url: String,
+        /// Static preference, higher is tried earlier
+        #[serde(default = "default_replacement_weight")]
+        weight: u32,
+    },
+    /// Mirror from the `[[mirrors]]` catalog
+    Mirror {
+        /// Mirror name
+        mirror: String,
+        /// Static preference, higher is tried earlier
+        #[serde(default = "default_replacement_weight")]
+        weight: u32,
+    },
+}
+
+impl ReplacementConfig {
+    /// URL template, `None` for mirror references
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            Self::Url(url) | Self::Weighted { url, .. } => Some(url),
+            Self::Mirror { .. } => None,
+        }
+    }
+
+    /// Referenced catalog mirror, if any
+    pub fn mirror(&self) -> Option<&str> {
+        match self {
+            Self::Mirror { mirror, .. } => Some(mirror),
+            _ => None,
+        }
+    }
+
+    /// Static preference, higher is tried earlier
+    pub fn weight(&self) -> u32 {
+        match self {
+            Self::Url(_) => default_replacement_weight(),
+            Self::Weighted { weight, .. } | Self::Mirror { weight, .. } => *weight,
+        }
+    }
+}
+
+impl From<&str> for ReplacementConfig {
+    fn from(url: &str) -> Self {
+        Self::Url(url.to_string())
+    }
+}
+
+impl From<String> for ReplacementConfig {
+    fn from(url: String) -> Self {
+        Self::Url(url)
+    }
+}
+
+/// Default weight of a replacement template
+pub const DEFAULT_REPLACEMENT_WEIGHT: u32 = 100;
+
+fn default_replacement_weight() -> u32 {
+    DEFAULT_REPLACEMENT_WEIGHT
+}
+
+/// How matching URL mapping rules are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleMode {
+    /// Use only the highest-priority matching rule
+    #[default]
+    First,
+    /// Merge the candidates of every matching rule
+    Merge,
+}
+
+/// URL mapping configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MappingConfig {
+    /// Whether the first or all matching rules are used
+    pub mode: RuleMode,
+    /// Share of the candidate ranking taken from observed server performance
+    /// (0.0 = static weights only, 1.0 = performance only)
+    pub performance_weight: f64,
+    /// Catalog mirrors to skip regardless of their `enabled` flag
+    pub disabled_mirrors: Vec<String>,
+}
+
+impl Default for MappingConfig {
+    fn default() -> Self {
+        Self {
+            mode: RuleMode::First,
+            performance_weight: 0.5,
+            disabled_mirrors: Vec::new(),
+        }
+    }
+}
+
+/// How a mirror addresses the original resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MirrorStyle {
+    /// The full original URL is appended to the mirror URL,
+    /// e.g. `https://ghproxy.net/https://github.com/...`
+    #[default]
+    PrefixProxy,
+    /// The original host is replaced and the path kept,
+    /// e.g. `https://bgithub.xyz/owner/repo/...`
+    PathRewrite,
+}
+
+/// Constraints of a mirror
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MirrorCapabilities {
+    /// Whether the mirror honors `Range` requests
+    pub range_requests: bool,
+    /// Whether the mirror can serve `releases/latest` URLs
+    pub latest_release: bool,
+    /// Largest file the mirror serves, in bytes
+    pub max_file_size: Option<u64>,
+}
+
+impl Default for MirrorCapabilities {
+    fn default() -> Self {
+        Self {
+            range_requests: true,
+            latest_release: true,
+            max_file_size: None,
+        }
+    }
+}
+
+/// Named mirror in the `[[mirrors]]` catalog
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MirrorConfig {
+    /// Name rules use to reference the mirror
+    pub name: String,
+    /// Mirror base URL
+    pub url: String,
+    /// How the original URL is mapped onto the mirror
+    #[serde(default)]
+    pub style: MirrorStyle,
+    /// Regions the mirror serves (empty = all regions)
+    #[serde(default)]
+    pub regions: Vec<Region>,
+    /// Constraints of the mirror
+    #[serde(default)]
+    pub capabilities: MirrorCapabilities,
+    /// URL probed by `turbo-cdn mirrors check` (defaults to `url`)
+    #[serde(default)]
+    pub health_check_url: Option<String>,
+    /// Whether the mirror is used
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Subscription to a remote, signed rule set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubscriptionConfig {
+    /// Name of the subscription, also names its cache file
+    pub name: String,
+    /// URL of the rule set
+    pub url: String,
+    /// URL of the detached signature (defaults to `url` + `.sig`)
+    #[serde(default)]
+    pub signature_url: Option<String>,
+    /// Base64 ed25519 public key the rule set must be signed with
+    pub public_key: String,
+    /// Seconds between update checks, 0 to only update on demand
+    #[serde(default = "default_refresh_interval")]
+    pub refresh_interval: u64,
+    /// Whether the subscription is used
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_refresh_interval() -> u64 {
+    6 * 60 * 60
 }
 
 /// General configuration
@@ -57,6 +298,9 @@ pub struct GeneralConfig {
     pub url_cache_ttl: u64,
     /// Maximum cache entries
     pub max_cache_entries: usize,
+    /// Serve only from local state and never access the network
+    #[serde(default)]
+    pub offline: bool,
 }
 
 /// Performance configuration
@@ -104,6 +348,38 @@ pub struct PerformanceConfig {
     pub smart_chunking_enabled: Option<bool>,
     /// Chunk performance history size
     pub chunk_performance_history_size: Option<usize>,
+    /// How downloaded data is written to disk
+    #[serde(default)]
+    pub write_backend: WriteBackendMode,
+    /// Files larger than this many bytes are memory-mapped in `auto` mode
+    #[serde(default = "default_mmap_threshold")]
+    pub mmap_threshold: u64,
+    /// Size of the pooled buffers chunk bodies are staged in, in bytes
+    #[serde(default = "default_write_buffer_size")]
+    pub write_buffer_size: usize,
+}
+
+/// Strategy for writing downloaded data to disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WriteBackendMode {
+    /// Pick from the file size: mmap, positional writes or buffered streaming
+    #[default]
+    Auto,
+    /// Memory-map the output file
+    Mmap,
+    /// Positional writes (`pwrite`) without a shared file cursor
+    Pwrite,
+    /// Buffered sequential writes through a single file handle
+    Buffered,
+}
+
+fn default_mmap_threshold() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_write_buffer_size() -> usize {
+    256 * 1024
 }
 
 /// Security configuration
@@ -113,6 +389,18 @@ pub struct SecurityConfig {
     pub verify_ssl: bool,
     /// Allowed protocols
     pub allowed_protocols: Vec<String>,
+    /// PEM bundle of additional trusted root certificates
+    #[serde(default)]
+    pub ca_bundle: Option<PathBuf>,
+    /// PEM client certificate for mutual TLS (may also contain the key)
+    #[serde(default)]
+    pub client_cert: Option<PathBuf>,
+    /// PEM private key for the client certificate (PKCS#8, required with native-tls)
+    #[serde(default)]
+    pub client_key: Option<PathBuf>,
+    /// Minimum TLS version ("1.0", "1.1", "1.2" or "1.3")
+    #[serde(default)]
+    pub min_tls_version: Option<String>,
 }
 
 /// Geographic detection configuration
@@ -135,6 +423,160 @@ pub struct TestingConfig {
     pub speed_test_sizes: Vec<u64>,
 }
 
+/// Local download cache configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Enable the on-disk download cache (off for library use, turned on by
+    /// the command line tool unless configured)
+    pub enabled: bool,
+    /// Cache directory (defaults to the platform cache directory)
+    pub directory: Option<PathBuf>,
+    /// Maximum total size of cached files in bytes
+    pub max_size: u64,
+    /// Revalidate cached files with the origin server before reuse
+    pub revalidate: bool,
+    /// Hard-link cached files into place instead of copying them; linked
+    /// files are made read-only since they share storage with the cache
+    pub link_files: bool,
+}
+
+/// Policy controlling which URLs may be rewritten to mirrors
+///
+/// Host entries match the host itself and all of its subdomains.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PolicyConfig {
+    /// Only rewrite URLs on these hosts (empty = any host)
+    pub allow_hosts: Vec<String>,
+    /// Never rewrite URLs on these hosts
+    pub deny_hosts: Vec<String>,
+    /// Only apply these mapping rules, by name (empty = any rule)
+    pub allow_rules: Vec<String>,
+    /// Never apply these mapping rules, by name
+    pub deny_rules: Vec<String>,
+    /// Never rewrite URLs carrying credentials or sensitive query parameters,
+    /// or URLs on hosts with configured credentials
+    pub protect_credentials: bool,
+    /// Query parameter names treated as credentials (case-insensitive)
+    pub sensitive_query_params: Vec<String>,
+    /// Only use mirrors on these hosts (empty = any mirror)
+    pub trusted_mirrors: Vec<String>,
+}
+
+/// Credential store configuration
+///
+/// Credentials are sent only to the exact host they are configured for, so
+/// they never reach rewritten mirror hosts or cross-host redirect targets.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CredentialsConfig {
+    /// Read credentials from a `.netrc` file
+    pub netrc: bool,
+    /// `.netrc` location (defaults to `$NETRC`, then `~/.netrc`)
+    pub netrc_file: Option<PathBuf>,
+    /// Per-host credentials, taking precedence over `.netrc`
+    pub hosts: Vec<HostCredentialConfig>,
+}
+
+/// Credentials for a single host
+///
+/// Values may reference environment variables as `${NAME}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostCredentialConfig {
+    /// Host name, optionally with a port (`nexus.example.com:8443`)
+    pub host: String,
+    /// Bearer token
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Basic auth username
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Basic auth password
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// GitHub API configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitHubConfig {
+    /// API base URL (change for GitHub Enterprise Server)
+    pub api_base: String,
+}
+
+impl Default for GitHubConfig {
+    fn default() -> Self {
+        Self {
+            api_base: "https://api.github.com".to_string(),
+        }
+    }
+}
+
+/// Network configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Outbound proxy settings
+    pub proxy: ProxyConfig,
+}
+
+/// Outbound proxy configuration
+///
+/// When no proxy is configured, the standard `HTTP_PROXY`/`HTTPS_PROXY`/
+/// `NO_PROXY` environment variables are honored.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxyConfig {
+    /// Proxy for `http://` URLs
+    pub http: Option<String>,
+    /// Proxy for `https://` URLs (falls back to `http`)
+    pub https: Option<String>,
+    /// SOCKS5 proxy for URLs without an HTTP proxy
+    pub socks5: Option<String>,
+    /// Hosts or domains (`.example.com`) that bypass the proxy, `*` for all
+    pub no_proxy: Vec<String>,
+    /// Proxy username
+    pub username: Option<String>,
+    /// Proxy password
+    pub password: Option<String>,
+    /// Per-origin overrides, the first matching rule wins
+    pub rules: Vec<ProxyRuleConfig>,
+}
+
+/// Proxy override for origins matching a pattern
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyRuleConfig {
+    /// Regex pattern matched against the request origin (`scheme://host[:port]/`)
+    pub pattern: String,
+    /// Proxy URL to use, or `"direct"` to bypass all proxies
+    pub proxy: String,
+}
+
+impl ProxyConfig {
+    /// Check if any proxy setting is configured
+    pub fn is_configured(&self) -> bool {
+        self.http.is_some()
+            || self.https.is_some()
+            || self.socks5.is_some()
+            || !self.rules.is_empty()
+    }
+
+    /// Route all traffic through a single proxy URL
+    pub fn set_all<S: Into<String>>(&mut self, proxy: S) {
+        let proxy = proxy.into();
+        if proxy.starts_with("socks") {
+            self.http = None;
+            self.https = None;
+            self.socks5 = Some(proxy);
+        } else {
+            self.http = Some(proxy.clone());
+            self.https = Some(proxy);
+            self.socks5 = None;
+        }
+    }
+}
+
 #[allow(clippy::derivable_impls)]
 impl Default for TurboCdnConfig {
     fn default() -> Self {
@@ -144,6 +586,14 @@ impl Default for TurboCdnConfig {
             security: SecurityConfig::default(),
             geo_detection: GeoDetectionConfig::default(),
             testing: TestingConfig::default(),
+            cache: CacheConfig::default(),
+            network: NetworkConfig::default(),
+            policy: PolicyConfig::default(),
+            credentials: CredentialsConfig::default(),
+            github: GitHubConfig::default(),
+            mapping: MappingConfig::default(),
+            mirrors: Vec::new(),
+            subscriptions: Vec::new(),
             url_mapping_rules: Vec::new(), // Will be loaded from config file
         }
     }
@@ -158,6 +608,7 @@ impl Default for GeneralConfig {
             enable_url_cache: true,
             url_cache_ttl: 3600, // 1 hour
             max_cache_entries: 1000,
+            offline: false,
         }
     }
 }
@@ -186,6 +637,9 @@ impl Default for PerformanceConfig {
             dns_cache_max_entries: Some(1000),
             smart_chunking_enabled: Some(true),
             chunk_performance_history_size: Some(100),
+            write_backend: WriteBackendMode::Auto,
+            mmap_threshold: default_mmap_threshold(),
+            write_buffer_size: default_write_buffer_size(),
         }
     }
 }
@@ -195,6 +649,32 @@ impl Default for SecurityConfig {
         Self {
             verify_ssl: true,
             allowed_protocols: vec!["https".to_string(), "http".to_string()],
+            ca_bundle: None,
+            client_cert: None,
+            client_key: None,
+            min_tls_version: None,
+        }
+    }
+}
+
+impl SecurityConfig {
+    /// Check if a URL scheme is allowed
+    pub fn is_protocol_allowed(&self, scheme: &str) -> bool {
+        self.allowed_protocols
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+    }
+
+    /// Reject URLs whose scheme is not in `allowed_protocols`
+    pub fn check_url(&self, url: &str) -> crate::error::Result<()> {
+        let parsed = url::Url::parse(url)?;
+        if self.is_protocol_allowed(parsed.scheme()) {
+            Ok(())
+        } else {
+            Err(crate::error::TurboCdnError::compliance(format!(
+                "Protocol '{}' is not allowed for {url}",
+                parsed.scheme()
+            )))
         }
     }
 }
@@ -226,6 +706,64 @@ impl Default for TestingConfig {
     }
 }
 
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            allow_hosts: Vec::new(),
+            deny_hosts: Vec::new(),
+            allow_rules: Vec::new(),
+            deny_rules: Vec::new(),
+            protect_credentials: true,
+            sensitive_query_params: [
+                "token",
+                "access_token",
+                "auth",
+                "key",
+                "api_key",
+                "sig",
+                "signature",
+                "x-amz-signature",
+                "x-amz-credential",
+                "x-goog-signature",
+            ]
+            .iter()
+            .map(|param| param.to_string())
+            .collect(),
+            trusted_mirrors: Vec::new(),
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: None,
+            max_size: 5 * 1024 * 1024 * 1024, // 5GB
+            revalidate: true,
+            link_files: false,
+        }
+    }
+}
+
+impl CacheConfig {
+    /// Resolve the cache directory, falling back to the platform default
+    pub fn resolve_directory(&self) -> PathBuf {
+        if let Some(dir) = &self.directory {
+            return dir.clone();
+        }
+
+        #[cfg(windows)]
+        let base = std::env::var_os("LOCALAPPDATA").map(PathBuf::from);
+        #[cfg(not(windows))]
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")));
+
+        base.unwrap_or_else(std::env::temp_dir).join("turbo-cdn")
+    }
+}
+
 impl TurboCdnConfig {
     /// Load configuration from embedded default TOML.
     pub fn load() -> Result<Self, toml::de::Error> {
@@ -233,16 +771,17 @@ impl TurboCdnConfig {
         toml::from_str(config_content)
     }
 
-    /// Load configuration from a custom TOML file, falling back to defaults.
+    /// Load configuration from a custom TOML file merged over the defaults.
+    ///
+    /// A missing file yields the defaults.
     pub fn load_from_file<P: Into<PathBuf>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
-        let path = path.into();
-        if path.exists() {
-            let file_content = std::fs::read_to_string(&path)?;
-            Ok(toml::from_str(&file_content)?)
-        } else {
-            let config_content = include_str!("default.toml");
-            Ok(toml::from_str(config_content)?)
-        }
+        Ok(ConfigLoader::new().with_optional_file(path).load()?.config)
+    }
+
+    /// Load the layered configuration: built-in defaults, the system, user
+    /// and project files, then `TURBO_CDN_*` environment variables.
+    pub fn load_layered() -> crate::error::Result<LoadedConfig> {
+        ConfigLoader::standard().load()
     }
 }
 
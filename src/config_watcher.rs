@@ -0,0 +1,129 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Configuration file watcher
+//!
+//! Polls the files of a [`ConfigLoader`] and reloads the configuration when
+//! one is modified, created or removed. Polling needs no platform file
+//! notification support and also catches editors that replace a file
+//! instead of writing it in place.
+
+use crate::config::{ConfigLoader, TurboCdnConfig};
+use crate::error::Result;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// What the watcher has done so far
+#[derive(Debug, Clone, Default)]
+pub struct WatchStatus {
+    /// Number of configurations applied
+    pub reloads: u64,
+    /// Why the last change was rejected, cleared by the next successful reload
+    pub last_error: Option<String>,
+}
+
+/// Background task reloading the configuration when its files change
+///
+/// The task stops when the watcher or the client it reloads is dropped.
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    task: JoinHandle<()>,
+    status: Arc<Mutex<WatchStatus>>,
+}
+
+impl ConfigWatcher {
+    /// Start polling the loader's files every `interval`
+    ///
+    /// `reload` applies a freshly loaded configuration and returns `None`
+    /// once there is nothing left to reload, which stops the watcher.
+    pub(crate) fn spawn<F, Fut>(loader: ConfigLoader, interval: Duration, reload: F) -> Self
+    where
+        F: Fn(TurboCdnConfig) -> Fut + Send + 'static,
+        Fut: Future<Output = Option<Result<()>>> + Send + 'static,
+    {
+        // Taken now so that changes made before the task first runs count
+        let files: Vec<PathBuf> = loader.files().map(PathBuf::from).collect();
+        let last = fingerprint(&files);
+        let status = Arc::new(Mutex::new(WatchStatus::default()));
+        let task = tokio::spawn(watch(loader, files, last, interval, reload, status.clone()));
+        Self { task, status }
+    }
+
+    /// Reloads so far and the last rejection
+    pub fn status(&self) -> WatchStatus {
+        self.status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Stop watching
+    pub fn stop(self) {}
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Modification time and size of a file, `None` if it doesn't exist
+type Fingerprint = Option<(SystemTime, u64)>;
+
+fn fingerprint(files: &[PathBuf]) -> Vec<Fingerprint> {
+    files
+        .iter()
+        .map(|path| {
+            let metadata = std::fs::metadata(path).ok()?;
+            Some((metadata.modified().ok()?, metadata.len()))
+        })
+        .collect()
+}
+
+async fn watch<F, Fut>(
+    loader: ConfigLoader,
+    files: Vec<PathBuf>,
+    mut last: Vec<Fingerprint>,
+    interval: Duration,
+    reload: F,
+    status: Arc<Mutex<WatchStatus>>,
+) where
+    F: Fn(TurboCdnConfig) -> Fut,
+    Fut: Future<Output = Option<Result<()>>>,
+{
+    loop {
+        tokio::time::sleep(interval).await;
+        let current = fingerprint(&files);
+        if current == last {
+            continue;
+        }
+        last = current;
+
+        let result = match loader.load() {
+            Ok(loaded) => match reload(loaded.config).await {
+                Some(result) => result,
+                None => break,
+            },
+            Err(e) => Err(e),
+        };
+
+        let mut status = status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match result {
+            Ok(()) => {
+                info!("Reloaded configuration");
+                status.reloads += 1;
+                status.last_error = None;
+            }
+            Err(e) => {
+                warn!("Keeping previous configuration: {}", e);
+                status.last_error = Some(e.to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,381 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Per-host credential store
+//!
+//! Resolves bearer tokens and basic auth credentials from configuration,
+//! environment variable references and `.netrc`. Credentials are keyed by the
+//! exact request host, so a rewritten mirror URL never matches the credentials
+//! of the original host. Cross-host redirects are left to the
+//! [`HttpBackend`](crate::http_backend::HttpBackend): reqwest drops
+//! `Authorization` whenever the host or port changes, and custom backends
+//! that follow redirects are required to do the same.
+
+use crate::config::{CredentialsConfig, HostCredentialConfig};
+use crate::error::{Result, TurboCdnError};
+use reqwest::RequestBuilder;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+use url::Url;
+
+/// Credential for a single host
+#[derive(Clone, PartialEq, Eq)]
+pub enum Credential {
+    /// `Authorization: Bearer <token>`
+    Bearer(String),
+    /// `Authorization: Basic <base64(username:password)>`
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+}
+
+impl Credential {
+    /// Attach the credential to a request
+    pub fn apply(&self, request: RequestBuilder) -> RequestBuilder {
+        match self {
+            Credential::Bearer(token) => request.bearer_auth(token),
+            Credential::Basic { username, password } => {
+                request.basic_auth(username, password.as_ref())
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Credential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Credential::Bearer(_) => f.write_str("Bearer(<redacted>)"),
+            Credential::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &"<redacted>")
+                .finish(),
+        }
+    }
+}
+
+/// Credentials keyed by host
+#[derive(Debug, Clone, Default)]
+pub struct CredentialStore {
+    hosts: HashMap<String, Credential>,
+}
+
+impl CredentialStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a store from configuration
+    ///
+    /// Host entries referencing unset environment variables are skipped with
+    /// a warning, so shared configs work on machines without every secret.
+    pub fn from_config(config: &CredentialsConfig) -> Result<Self> {
+        let mut store = Self::new();
+
+        if config.netrc {
+            if let Some(path) = config.netrc_file.clone().or_else(default_netrc_path) {
+                if path.exists() {
+                    store.load_netrc(&path)?;
+                } else if config.netrc_file.is_some() {
+                    return Err(TurboCdnError::config(format!(
+                        "netrc file not found: {}",
+                        path.display()
+                    )));
+                }
+            }
+        }
+
+        // Explicit host entries override .netrc
+        for entry in &config.hosts {
+            if let Some(credential) = Self::credential_from_config(entry)? {
+                store.insert(&entry.host, credential);
+            }
+        }
+
+        Ok(store)
+    }
+
+    /// Add or replace the credential for a host
+    pub fn insert(&mut self, host: &str, credential: Credential) {
+        self.hosts
+            .insert(host.trim().to_ascii_lowercase(), credential);
+    }
+
+    /// Hosts with credentials, optionally with a port
+    pub fn hosts(&self) -> impl Iterator<Item = &str> {
+        self.hosts.keys().map(String::as_str)
+    }
+
+    /// Check whether the store holds no credentials
+    pub fn is_empty(&self) -> bool {
+        self.hosts.is_empty()
+    }
+
+    /// Get the credential for a URL
+    ///
+    /// `host:port` entries are preferred over plain host entries.
+    pub fn for_url(&self, url: &str) -> Option<&Credential> {
+        let url = Url::parse(url).ok()?;
+        let host = url.host_str()?.to_ascii_lowercase();
+
+        url.port()
+            .and_then(|port| self.hosts.get(&format!("{host}:{port}")))
+            .or_else(|| self.hosts.get(&host))
+    }
+
+    /// Attach the credential for `url`, if any, to a request
+    pub fn authorize(&self, request: RequestBuilder, url: &str) -> RequestBuilder {
+        match self.for_url(url) {
+            Some(credential) => {
+                debug!("Applying configured credentials for {}", url);
+                credential.apply(request)
+            }
+            None => request,
+        }
+    }
+
+    /// Resolve a configured host entry
+    fn credential_from_config(entry: &HostCredentialConfig) -> Result<Option<Credential>> {
+        let resolve = |value: &Option<String>| value.as_deref().map(expand_env).transpose();
+
+        let credential = match (&entry.token, &entry.username) {
+            (Some(_), Some(_)) => {
+                return Err(TurboCdnError::config(format!(
+                    "Credentials for {} set both token and username",
+                    entry.host
+                )))
+            }
+            (Some(_), None) => resolve(&entry.token).map(|token| token.map(Credential::Bearer)),
+            (None, Some(_)) => resolve(&entry.username).and_then(|username| {
+                let password = resolve(&entry.password)?;
+                Ok(username.map(|username| Credential::Basic { username, password }))
+            }),
+            (None, None) => {
+                return Err(TurboCdnError::config(format!(
+                    "Credentials for {} need a token or a username",
+                    entry.host
+                )))
+            }
+        };
+
+        credential.or_else(|missing| {
+            warn!(
+                "Skipping credentials for {}: environment variable {} is not set",
+                entry.host, missing
+            );
+            Ok(None)
+        })
+    }
+
+    /// Load `machine` entries from a `.netrc` file
+    fn load_netrc(&mut self, path: &Path) -> Result<()> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            TurboCdnError::config(format!("Failed to read netrc {}: {e}", path.display()))
+        })?;
+
+        for (host, credential) in parse_netrc(&content) {
+            self.insert(&host, credential);
+        }
+        debug!("Loaded credentials from {}", path.display());
+        Ok(())
+    }
+}
+
+/// Parse `machine`/`login`/`password` entries of a `.netrc` file
+///
+/// `default` entries are ignored: sending credentials to arbitrary hosts is
+/// exactly what the store must prevent.
+fn parse_netrc(content: &str) -> Vec<(String, Credential)> {
+    let mut entries = Vec::new();
+    let mut machine: Option<String> = None;
+    let mut login = None;
+    let mut password = None;
+
+    let mut flush = |machine: &mut Option<String>,
+                     login: &mut Option<String>,
+                     password: &mut Option<String>| {
+        if let (Some(host), Some(username)) = (machine.take(), login.take()) {
+            entries.push((
+                host,
+                Credential::Basic {
+                    username,
+                    password: password.take(),
+                },
+            ));
+        }
+        *password = None;
+    };
+
+    let mut tokens = content.split_whitespace();
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" => {
+                flush(&mut machine, &mut login, &mut password);
+                machine = tokens.next().map(str::to_string);
+            }
+            "default" => {
+                flush(&mut machine, &mut login, &mut password);
+            }
+            "login" => login = tokens.next().map(str::to_string),
+            "password" => password = tokens.next().map(str::to_string),
+            "account" => {
+                tokens.next();
+            }
+            // Macro definitions run to the end of the file in practice
+            "macdef" => break,
+            _ => {}
+        }
+    }
+    flush(&mut machine, &mut login, &mut password);
+
+    entries
+}
+
+/// Default `.netrc` location
+fn default_netrc_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("NETRC") {
+        return Some(PathBuf::from(path));
+    }
+
+    #[cfg(windows)]
+    let (home, name) = (std::env::var_os("USERPROFILE"), "_netrc");
+    #[cfg(not(windows))]
+    let (home, name) = (std::env::var_os("HOME"), ".netrc");
+
+    home.map(|home| PathBuf::from(home).join(name))
+}
+
+/// Expand `${NAME}` environment variable references
+///
+/// Returns the name of the first unset variable as the error.
+fn expand_env(value: &str) -> std::result::Result<String, String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + end];
+        let replacement = std::env::var(name).map_err(|_| name.to_string())?;
+
+        result.push_str(&rest[..start]);
+        result.push_str(&replacement);
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(host: &str, token: Option<&str>, username: Option<&str>) -> HostCredentialConfig {
+        HostCredentialConfig {
+            host: host.to_string(),
+            token: token.map(str::to_string),
+            username: username.map(str::to_string),
+            password: Some("secret".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_credentials_keyed_by_exact_host() {
+        let config = CredentialsConfig {
+            hosts: vec![
+                host("GitHub.com", Some("ghp_token"), None),
+                host("nexus.example.com:8443", None, Some("ci")),
+            ],
+            ..Default::default()
+        };
+        let store = CredentialStore::from_config(&config).unwrap();
+
+        assert_eq!(
+            store.for_url("https://github.com/owner/repo/releases/download/v1/a.zip"),
+            Some(&Credential::Bearer("ghp_token".to_string()))
+        );
+        assert!(store
+            .for_url("https://ghproxy.net/https://github.com/owner/repo")
+            .is_none());
+        assert!(store.for_url("https://api.github.com/repos").is_none());
+        assert!(matches!(
+            store.for_url("https://nexus.example.com:8443/repo/a.jar"),
+            Some(Credential::Basic { .. })
+        ));
+        assert!(store
+            .for_url("https://nexus.example.com/repo/a.jar")
+            .is_none());
+    }
+
+    #[test]
+    fn test_invalid_host_entries() {
+        let both = CredentialsConfig {
+            hosts: vec![host("github.com", Some("t"), Some("u"))],
+            ..Default::default()
+        };
+        assert!(CredentialStore::from_config(&both).is_err());
+
+        let neither = CredentialsConfig {
+            hosts: vec![host("github.com", None, None)],
+            ..Default::default()
+        };
+        assert!(CredentialStore::from_config(&neither).is_err());
+    }
+
+    #[test]
+    fn test_env_references() {
+        std::env::set_var("TURBO_CDN_TEST_CREDENTIAL", "from-env");
+        assert_eq!(
+            expand_env("Bearer ${TURBO_CDN_TEST_CREDENTIAL}!").unwrap(),
+            "Bearer from-env!"
+        );
+        assert_eq!(
+            expand_env("${TURBO_CDN_TEST_UNSET_CREDENTIAL}").unwrap_err(),
+            "TURBO_CDN_TEST_UNSET_CREDENTIAL"
+        );
+
+        let config = CredentialsConfig {
+            hosts: vec![host(
+                "github.com",
+                Some("${TURBO_CDN_TEST_UNSET_CREDENTIAL}"),
+                None,
+            )],
+            ..Default::default()
+        };
+        assert!(CredentialStore::from_config(&config).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_netrc() {
+        let entries = parse_netrc(
+            "machine nexus.example.com login ci password s3cret\n\
+             default login anonymous password guest\n\
+             machine other.example.com\n  login bob\n  account x\n  password pw\n",
+        );
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, "nexus.example.com");
+        assert_eq!(
+            entries[1].1,
+            Credential::Basic {
+                username: "bob".to_string(),
+                password: Some("pw".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let credential = Credential::Basic {
+            username: "ci".to_string(),
+            password: Some("s3cret".to_string()),
+        };
+        assert!(!format!("{credential:?}").contains("s3cret"));
+        assert!(!format!("{:?}", Credential::Bearer("tok".to_string())).contains("tok"));
+    }
+}
@@ -0,0 +1,794 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Content-addressed local download cache
+//!
+//! This module keeps completed downloads on disk so repeated requests for the
+//! same artifact can be served locally. Entries are keyed by the original URL
+//! together with the HTTP validators (`ETag` / `Last-Modified`) returned by the
+//! server, while file contents are stored once per SHA-256 hash.
+//!
+//! Layout:
+//! - `<dir>/objects/<hash[..2]>/<hash>` - cached file contents
+//! - `<dir>/index.json` - URL entries with validators and access times
+
+use crate::config::CacheConfig;
+use crate::error::{Result, TurboCdnError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// Index file name inside the cache directory
+const INDEX_FILE: &str = "index.json";
+
+/// Directory holding content-addressed objects
+const OBJECTS_DIR: &str = "objects";
+
+/// Counter making temporary object names unique within the process
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Read buffer size used while hashing files
+const HASH_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// HTTP validators used to revalidate a cached entry
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheValidators {
+    /// `ETag` response header
+    pub etag: Option<String>,
+    /// `Last-Modified` response header
+    pub last_modified: Option<String>,
+}
+
+impl CacheValidators {
+    /// Check if any validator is available
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// A cached download keyed by its original URL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// Original URL requested by the caller
+    pub url: String,
+    /// URL the content was actually fetched from (may be a mirror)
+    pub source_url: String,
+    /// SHA-256 hash of the content (hex)
+    pub sha256: String,
+    /// Content size in bytes
+    pub size: u64,
+    /// Validators returned by the source server
+    pub validators: CacheValidators,
+    /// When the entry was stored
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// When the entry was last served or revalidated
+    pub last_accessed: chrono::DateTime<chrono::Utc>,
+}
+
+/// Cache usage statistics
+#[derive(Debug, Clone, Default)]
+pub struct DownloadCacheStats {
+    /// Cache directory
+    pub directory: PathBuf,
+    /// Number of URL entries
+    pub entries: usize,
+    /// Number of unique stored objects
+    pub objects: usize,
+    /// Total bytes used by stored objects
+    pub total_size: u64,
+    /// Configured size limit in bytes
+    pub max_size: u64,
+}
+
+/// Result of a prune operation
+#[derive(Debug, Clone, Default)]
+pub struct PruneReport {
+    /// Number of URL entries removed
+    pub removed_entries: usize,
+    /// Number of objects deleted from disk
+    pub removed_objects: usize,
+    /// Bytes reclaimed
+    pub freed_bytes: u64,
+}
+
+/// Persistent index of cached entries
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheIndex {
+    entries: HashMap<String, CacheEntry>,
+}
+
+/// On-disk download cache with LRU eviction
+#[derive(Debug)]
+pub struct DownloadCache {
+    directory: PathBuf,
+    max_size: u64,
+    revalidate: bool,
+    link_files: bool,
+    index: Mutex<CacheIndex>,
+}
+
+impl DownloadCache {
+    /// Open (or create) the cache described by the configuration
+    pub fn open(config: &CacheConfig) -> Result<Self> {
+        Ok(Self::open_at(
+            config.resolve_directory(),
+            config.max_size,
+            config.revalidate,
+        )?
+        .with_link_files(config.link_files))
+    }
+
+    /// Open (or create) a cache in a specific directory
+    pub fn open_at<P: Into<PathBuf>>(
+        directory: P,
+        max_size: u64,
+        revalidate: bool,
+    ) -> Result<Self> {
+        let directory = directory.into();
+        std::fs::create_dir_all(directory.join(OBJECTS_DIR)).map_err(|e| {
+            TurboCdnError::cache(format!(
+                "Failed to create cache directory {}: {e}",
+                directory.display()
+            ))
+        })?;
+
+        let index = Self::read_index(&directory.join(INDEX_FILE));
+        debug!(
+            "Opened download cache at {} ({} entries)",
+            directory.display(),
+            index.entries.len()
+        );
+
+        Ok(Self {
+            directory,
+            max_size,
+            revalidate,
+            link_files: false,
+            index: Mutex::new(index),
+        })
+    }
+
+    /// Hard-link cached objects into place instead of copying them
+    ///
+    /// Linked files are made read-only: they share their inode with the
+    /// cached object, so writing to them would corrupt the cache.
+    pub fn with_link_files(mut self, link_files: bool) -> Self {
+        self.link_files = link_files;
+        self
+    }
+
+    /// Cache directory
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Whether cached entries should be revalidated before reuse
+    pub fn revalidate_enabled(&self) -> bool {
+        self.revalidate
+    }
+
+    /// Look up a cached entry for a URL
+    ///
+    /// Entries whose object file has disappeared are dropped from the index.
+    pub fn lookup(&self, url: &str) -> Option<CacheEntry> {
+        let mut index = self.index.lock().unwrap();
+        let entry = index.entries.get(url)?.clone();
+
+        if !self.object_path(&entry.sha256).exists() {
+            warn!("Cached object for {} is missing, dropping entry", url);
+            index.entries.remove(url);
+            self.write_index(&index);
+            return None;
+        }
+
+        Some(entry)
+    }
+
+    /// Mark an entry as recently used
+    pub fn touch(&self, url: &str) {
+        let mut index = self.index.lock().unwrap();
+        if let Some(entry) = index.entries.get_mut(url) {
+            entry.last_accessed = chrono::Utc::now();
+            self.write_index(&index);
+        }
+    }
+
+    /// Store a downloaded file in the cache
+    ///
+    /// The file is hashed and linked (or copied) into the object store. If an
+    /// object with the same hash already exists it is reused.
+    pub fn insert(
+        &self,
+        url: &str,
+        source_url: &str,
+        file: &Path,
+        validators: CacheValidators,
+    ) -> Result<CacheEntry> {
+        let (sha256, size) = hash_file(file)?;
+        let object_path = self.object_path(&sha256);
+
+        if !object_path.exists() {
+            if let Some(parent) = object_path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| {
+                    TurboCdnError::cache(format!("Failed to create object directory: {e}"))
+                })?;
+            }
+            // Write to a temporary name first so readers never see partial
+            // objects; the name is unique so concurrent inserts of the same
+            // content don't write to the same file
+            let tmp_path = object_path.with_extension(format!(
+                "{}.{}.tmp",
+                std::process::id(),
+                TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            std::fs::copy(file, &tmp_path)
+                .and_then(|_| std::fs::rename(&tmp_path, &object_path))
+                .map_err(|e| {
+                    let _ = std::fs::remove_file(&tmp_path);
+                    TurboCdnError::cache(format!("Failed to store cached object: {e}"))
+                })?;
+        }
+
+        let now = chrono::Utc::now();
+        let entry = CacheEntry {
+            url: url.to_string(),
+            source_url: source_url.to_string(),
+            sha256,
+            size,
+            validators,
+            created_at: now,
+            last_accessed: now,
+        };
+
+        {
+            let mut index = self.index.lock().unwrap();
+            index.entries.insert(url.to_string(), entry.clone());
+            self.write_index(&index);
+        }
+
+        debug!("Cached {} ({} bytes, sha256 {})", url, size, entry.sha256);
+
+        // Keep the cache within its configured size, never evicting the new entry
+        self.prune_except(self.max_size, Some(url))?;
+
+        Ok(entry)
+    }
+
+    /// Place a cached object at the destination path
+    ///
+    /// The object is checked against its hash first; a corrupt object is
+    /// dropped from the cache and reported as an error. The file is copied,
+    /// or hard-linked and made read-only when linking is enabled and the
+    /// cache and the destination share a filesystem.
+    pub fn materialize(&self, entry: &CacheEntry, destination: &Path) -> Result<()> {
+        let object_path = self.verified_object(entry)?;
+
+        if let Some(parent) = destination.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent).map_err(|e| {
+                    TurboCdnError::io(format!("Failed to create output directory: {e}"))
+                })?;
+            }
+        }
+
+        if destination.exists() {
+            // Windows refuses to delete read-only files, such as earlier links
+            #[cfg(windows)]
+            set_writable(destination)?;
+            std::fs::remove_file(destination).map_err(|e| {
+                TurboCdnError::io(format!("Failed to replace {}: {e}", destination.display()))
+            })?;
+        }
+
+        let linked = self.link_files && std::fs::hard_link(&object_path, destination).is_ok();
+        if linked {
+            set_read_only(destination)?;
+        } else {
+            std::fs::copy(&object_path, destination).map_err(|e| {
+                TurboCdnError::cache(format!(
+                    "Failed to copy cached object to {}: {e}",
+                    destination.display()
+                ))
+            })?;
+            // Copies of read-only objects must not stay read-only
+            set_writable(destination)?;
+        }
+        Ok(())
+    }
+
+    /// Path of the cached object of an entry, after checking it against its hash
+    ///
+    /// A corrupt object is dropped from the cache and reported as an error.
+    /// The object must only be read, it may be shared by several entries.
+    pub fn verified_object(&self, entry: &CacheEntry) -> Result<PathBuf> {
+        let object_path = self.object_path(&entry.sha256);
+        let (sha256, _) = hash_file(&object_path)?;
+        if sha256 != entry.sha256 {
+            warn!("Cached object for {} is corrupt, dropping it", entry.url);
+            self.discard_object(&entry.sha256);
+            return Err(TurboCdnError::cache(format!(
+                "Cached object for {} does not match its hash",
+                entry.url
+            )));
+        }
+        self.touch(&entry.url);
+        Ok(object_path)
+    }
+
+    /// Remove a single URL entry
+    pub fn remove(&self, url: &str) -> Result<bool> {
+        let mut index = self.index.lock().unwrap();
+        let removed = index.entries.remove(url);
+        if let Some(entry) = &removed {
+            self.remove_orphaned_object(&index, &entry.sha256);
+            self.write_index(&index);
+        }
+        Ok(removed.is_some())
+    }
+
+    /// List all cached entries, most recently used first
+    pub fn list(&self) -> Vec<CacheEntry> {
+        let index = self.index.lock().unwrap();
+        let mut entries: Vec<_> = index.entries.values().cloned().collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.last_accessed));
+        entries
+    }
+
+    /// Get cache statistics
+    pub fn stats(&self) -> DownloadCacheStats {
+        let index = self.index.lock().unwrap();
+        let objects = Self::unique_objects(&index);
+        DownloadCacheStats {
+            directory: self.directory.clone(),
+            entries: index.entries.len(),
+            objects: objects.len(),
+            total_size: objects.values().sum(),
+            max_size: self.max_size,
+        }
+    }
+
+    /// Evict least recently used entries until the cache fits in `max_size`
+    pub fn prune(&self, max_size: u64) -> Result<PruneReport> {
+        self.prune_except(max_size, None)
+    }
+
+    /// Evict least recently used entries other than `keep`
+    fn prune_except(&self, max_size: u64, keep: Option<&str>) -> Result<PruneReport> {
+        let mut index = self.index.lock().unwrap();
+        let mut report = PruneReport::default();
+
+        let mut objects = Self::unique_objects(&index);
+        let mut total_size: u64 = objects.values().sum();
+        if total_size <= max_size {
+            return Ok(report);
+        }
+
+        let mut by_age: Vec<_> = index
+            .entries
+            .values()
+            .filter(|e| Some(e.url.as_str()) != keep)
+            .map(|e| (e.last_accessed, e.url.clone()))
+            .collect();
+        by_age.sort();
+
+        for (_, url) in by_age {
+            if total_size <= max_size {
+                break;
+            }
+            if let Some(entry) = index.entries.remove(&url) {
+                report.removed_entries += 1;
+                if self.remove_orphaned_object(&index, &entry.sha256) {
+                    let size = objects.remove(&entry.sha256).unwrap_or(entry.size);
+                    total_size = total_size.saturating_sub(size);
+                    report.removed_objects += 1;
+                    report.freed_bytes += size;
+                }
+            }
+        }
+
+        self.write_index(&index);
+        info!(
+            "Pruned download cache: {} entries, {} bytes freed",
+            report.removed_entries, report.freed_bytes
+        );
+        Ok(report)
+    }
+
+    /// Remove every cached entry and object
+    pub fn clear(&self) -> Result<PruneReport> {
+        let mut index = self.index.lock().unwrap();
+        let objects = Self::unique_objects(&index);
+        let report = PruneReport {
+            removed_entries: index.entries.len(),
+            removed_objects: objects.len(),
+            freed_bytes: objects.values().sum(),
+        };
+
+        index.entries.clear();
+        let objects_dir = self.directory.join(OBJECTS_DIR);
+        if objects_dir.exists() {
+            std::fs::remove_dir_all(&objects_dir)
+                .map_err(|e| TurboCdnError::cache(format!("Failed to clear cache: {e}")))?;
+        }
+        std::fs::create_dir_all(&objects_dir)
+            .map_err(|e| TurboCdnError::cache(format!("Failed to recreate cache: {e}")))?;
+        self.write_index(&index);
+
+        Ok(report)
+    }
+
+    /// Drop every entry pointing at an object and delete the object
+    fn discard_object(&self, sha256: &str) {
+        let mut index = self.index.lock().unwrap();
+        index.entries.retain(|_, entry| entry.sha256 != sha256);
+        self.remove_orphaned_object(&index, sha256);
+        self.write_index(&index);
+    }
+
+    /// Path of an object inside the store
+    fn object_path(&self, sha256: &str) -> PathBuf {
+        let prefix = &sha256[..2.min(sha256.len())];
+        self.directory.join(OBJECTS_DIR).join(prefix).join(sha256)
+    }
+
+    /// Delete an object if no entry references it anymore
+    fn remove_orphaned_object(&self, index: &CacheIndex, sha256: &str) -> bool {
+        if index.entries.values().any(|e| e.sha256 == sha256) {
+            return false;
+        }
+        let path = self.object_path(sha256);
+        if let Err(e) = std::fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("Failed to remove cached object {}: {}", path.display(), e);
+            }
+        }
+        true
+    }
+
+    /// Unique objects referenced by the index with their sizes
+    fn unique_objects(index: &CacheIndex) -> HashMap<String, u64> {
+        index
+            .entries
+            .values()
+            .map(|e| (e.sha256.clone(), e.size))
+            .collect()
+    }
+
+    fn read_index(path: &Path) -> CacheIndex {
+        match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring corrupt cache index {}: {}", path.display(), e);
+                CacheIndex::default()
+            }),
+            Err(_) => CacheIndex::default(),
+        }
+    }
+
+    fn write_index(&self, index: &CacheIndex) {
+        let path = self.directory.join(INDEX_FILE);
+        let tmp_path = path.with_extension("json.tmp");
+        let result = serde_json::to_vec_pretty(index)
+            .map_err(std::io::Error::other)
+            .and_then(|data| std::fs::write(&tmp_path, data))
+            .and_then(|_| std::fs::rename(&tmp_path, &path));
+
+        if let Err(e) = result {
+            warn!("Failed to write cache index {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Mark a file read-only
+fn set_read_only(path: &Path) -> Result<()> {
+    set_permissions(path, true)
+}
+
+/// Make a file writable by its owner
+fn set_writable(path: &Path) -> Result<()> {
+    set_permissions(path, false)
+}
+
+fn set_permissions(path: &Path, readonly: bool) -> Result<()> {
+    let mut permissions = std::fs::metadata(path)
+        .map_err(|e| TurboCdnError::io(format!("Failed to stat {}: {e}", path.display())))?
+        .permissions();
+    if permissions.readonly() == readonly {
+        return Ok(());
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = permissions.mode();
+        permissions.set_mode(if readonly {
+            mode & !0o222
+        } else {
+            mode | 0o200
+        });
+    }
+    #[cfg(not(unix))]
+    permissions.set_readonly(readonly);
+    std::fs::set_permissions(path, permissions).map_err(|e| {
+        TurboCdnError::io(format!(
+            "Failed to change permissions of {}: {e}",
+            path.display()
+        ))
+    })
+}
+
+/// Compute the SHA-256 hash (hex) and size of a file
+pub fn hash_file(path: &Path) -> Result<(String, u64)> {
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| TurboCdnError::io(format!("Failed to open {}: {e}", path.display())))?;
+
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
+    let mut size = 0u64;
+
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| TurboCdnError::io(format!("Failed to read {}: {e}", path.display())))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+
+    Ok((format!("{:x}", hasher.finalize()), size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_file(dir: &Path, name: &str, content: &[u8]) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_insert_and_lookup() {
+        let dir = tempdir().unwrap();
+        let cache = DownloadCache::open_at(dir.path().join("cache"), 1024 * 1024, true).unwrap();
+        let file = write_file(dir.path(), "a.bin", b"hello world");
+
+        let validators = CacheValidators {
+            etag: Some("\"abc\"".to_string()),
+            last_modified: None,
+        };
+        let entry = cache
+            .insert(
+                "https://example.com/a.bin",
+                "https://mirror.example.com/a.bin",
+                &file,
+                validators.clone(),
+            )
+            .unwrap();
+
+        assert_eq!(entry.size, 11);
+        let found = cache.lookup("https://example.com/a.bin").unwrap();
+        assert_eq!(found.sha256, entry.sha256);
+        assert_eq!(found.validators, validators);
+        assert_eq!(found.source_url, "https://mirror.example.com/a.bin");
+        assert!(cache.lookup("https://example.com/missing.bin").is_none());
+    }
+
+    #[test]
+    fn test_identical_content_is_stored_once() {
+        let dir = tempdir().unwrap();
+        let cache = DownloadCache::open_at(dir.path().join("cache"), 1024 * 1024, true).unwrap();
+        let file = write_file(dir.path(), "a.bin", b"same bytes");
+
+        cache
+            .insert(
+                "https://a.example.com/f",
+                "https://a.example.com/f",
+                &file,
+                CacheValidators::default(),
+            )
+            .unwrap();
+        cache
+            .insert(
+                "https://b.example.com/f",
+                "https://b.example.com/f",
+                &file,
+                CacheValidators::default(),
+            )
+            .unwrap();
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.objects, 1);
+        assert_eq!(stats.total_size, 10);
+    }
+
+    #[test]
+    fn test_materialize_and_index_persistence() {
+        let dir = tempdir().unwrap();
+        let cache_dir = dir.path().join("cache");
+        let file = write_file(dir.path(), "a.bin", b"payload");
+
+        {
+            let cache = DownloadCache::open_at(&cache_dir, 1024, true).unwrap();
+            cache
+                .insert(
+                    "https://example.com/a.bin",
+                    "https://example.com/a.bin",
+                    &file,
+                    CacheValidators::default(),
+                )
+                .unwrap();
+        }
+
+        let cache = DownloadCache::open_at(&cache_dir, 1024, true).unwrap();
+        let entry = cache.lookup("https://example.com/a.bin").unwrap();
+        let output = dir.path().join("out").join("a.bin");
+        cache.materialize(&entry, &output).unwrap();
+        assert_eq!(std::fs::read(&output).unwrap(), b"payload");
+    }
+
+    #[test]
+    fn test_materialized_copy_does_not_touch_cache() {
+        let dir = tempdir().unwrap();
+        let cache = DownloadCache::open_at(dir.path().join("cache"), 1024, true).unwrap();
+        let file = write_file(dir.path(), "a.bin", b"payload");
+        let entry = cache
+            .insert(
+                "https://example.com/a.bin",
+                "https://example.com/a.bin",
+                &file,
+                CacheValidators::default(),
+            )
+            .unwrap();
+
+        let output = dir.path().join("out.bin");
+        cache.materialize(&entry, &output).unwrap();
+        std::fs::write(&output, b"edited").unwrap();
+
+        let again = dir.path().join("again.bin");
+        cache.materialize(&entry, &again).unwrap();
+        assert_eq!(std::fs::read(&again).unwrap(), b"payload");
+    }
+
+    #[test]
+    fn test_linked_files_are_read_only() {
+        let dir = tempdir().unwrap();
+        let cache = DownloadCache::open_at(dir.path().join("cache"), 1024, true)
+            .unwrap()
+            .with_link_files(true);
+        let file = write_file(dir.path(), "a.bin", b"payload");
+        let entry = cache
+            .insert(
+                "https://example.com/a.bin",
+                "https://example.com/a.bin",
+                &file,
+                CacheValidators::default(),
+            )
+            .unwrap();
+
+        let output = dir.path().join("out.bin");
+        cache.materialize(&entry, &output).unwrap();
+        assert!(std::fs::metadata(&output).unwrap().permissions().readonly());
+
+        // Materializing again replaces the read-only link
+        cache.materialize(&entry, &output).unwrap();
+        assert_eq!(std::fs::read(&output).unwrap(), b"payload");
+    }
+
+    #[test]
+    fn test_corrupt_object_is_not_served() {
+        let dir = tempdir().unwrap();
+        let cache = DownloadCache::open_at(dir.path().join("cache"), 1024, true).unwrap();
+        let file = write_file(dir.path(), "a.bin", b"payload");
+        let entry = cache
+            .insert(
+                "https://example.com/a.bin",
+                "https://example.com/a.bin",
+                &file,
+                CacheValidators::default(),
+            )
+            .unwrap();
+        std::fs::write(cache.object_path(&entry.sha256), b"tampered").unwrap();
+
+        let output = dir.path().join("out.bin");
+        assert!(cache.materialize(&entry, &output).is_err());
+        assert!(!output.exists());
+        assert!(cache.lookup("https://example.com/a.bin").is_none());
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let dir = tempdir().unwrap();
+        let cache = DownloadCache::open_at(dir.path().join("cache"), 20, true).unwrap();
+
+        let first = write_file(dir.path(), "1.bin", &[1u8; 10]);
+        let second = write_file(dir.path(), "2.bin", &[2u8; 10]);
+        let third = write_file(dir.path(), "3.bin", &[3u8; 10]);
+
+        cache
+            .insert(
+                "https://example.com/1",
+                "https://example.com/1",
+                &first,
+                CacheValidators::default(),
+            )
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        cache
+            .insert(
+                "https://example.com/2",
+                "https://example.com/2",
+                &second,
+                CacheValidators::default(),
+            )
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        cache.touch("https://example.com/1");
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        cache
+            .insert(
+                "https://example.com/3",
+                "https://example.com/3",
+                &third,
+                CacheValidators::default(),
+            )
+            .unwrap();
+
+        assert!(cache.lookup("https://example.com/1").is_some());
+        assert!(cache.lookup("https://example.com/2").is_none());
+        assert!(cache.lookup("https://example.com/3").is_some());
+        assert!(cache.stats().total_size <= 20);
+    }
+
+    #[test]
+    fn test_oversized_entry_is_kept() {
+        let dir = tempdir().unwrap();
+        let cache = DownloadCache::open_at(dir.path().join("cache"), 15, true).unwrap();
+        let small = write_file(dir.path(), "small.bin", &[1u8; 10]);
+        let large = write_file(dir.path(), "large.bin", &[2u8; 20]);
+
+        for (url, file) in [
+            ("https://example.com/s", &small),
+            ("https://example.com/l", &large),
+        ] {
+            cache
+                .insert(url, url, file, CacheValidators::default())
+                .unwrap();
+        }
+
+        // Older entries make room, but the new one stays usable
+        assert!(cache.lookup("https://example.com/s").is_none());
+        let entry = cache.lookup("https://example.com/l").unwrap();
+        cache
+            .materialize(&entry, &dir.path().join("out.bin"))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_clear() {
+        let dir = tempdir().unwrap();
+        let cache = DownloadCache::open_at(dir.path().join("cache"), 1024, true).unwrap();
+        let file = write_file(dir.path(), "a.bin", b"data");
+        cache
+            .insert(
+                "https://example.com/a",
+                "https://example.com/a",
+                &file,
+                CacheValidators::default(),
+            )
+            .unwrap();
+
+        let report = cache.clear().unwrap();
+        assert_eq!(report.removed_entries, 1);
+        assert_eq!(report.freed_bytes, 4);
+        assert!(cache.list().is_empty());
+    }
+}
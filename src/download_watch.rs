@@ -0,0 +1,175 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Following a download while it is written
+//!
+//! Concurrent chunks complete out of order, but every chunk is written front
+//! to back. The output file is therefore complete from its start up to the
+//! first missing byte of the first unfinished chunk. [`DownloadWatch`] tracks
+//! that readable prefix, so the file can be served in order while the rest
+//! of it still downloads.
+
+use crate::concurrent_downloader::ChunkInfo;
+use std::sync::Mutex;
+use tokio::sync::watch;
+
+/// Mirror response an attempt writes the download from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedAttempt {
+    /// URL being downloaded
+    pub url: String,
+    /// Total size, 0 when the server did not report one
+    pub total_size: u64,
+    /// `ETag` validator returned by the server
+    pub etag: Option<String>,
+    /// `Last-Modified` validator returned by the server
+    pub last_modified: Option<String>,
+}
+
+/// How much of a download can be read
+#[derive(Debug, Clone, Default)]
+pub struct WatchState {
+    /// Counts attempts; bytes read during an earlier attempt may be gone
+    pub generation: u64,
+    /// Current attempt, once a mirror answered
+    pub attempt: Option<WatchedAttempt>,
+    /// Bytes readable from the start of the output file
+    pub readable: u64,
+}
+
+/// Reports the readable prefix of a download to its followers
+#[derive(Debug)]
+pub struct DownloadWatch {
+    /// Next byte to write and end (exclusive) of each chunk
+    chunks: Mutex<Vec<(u64, u64)>>,
+    state: watch::Sender<WatchState>,
+}
+
+impl DownloadWatch {
+    /// Create a watch for a download that has not started yet
+    pub fn new() -> Self {
+        Self {
+            chunks: Mutex::new(Vec::new()),
+            state: watch::Sender::new(WatchState::default()),
+        }
+    }
+
+    /// Follow the readable state of the download
+    pub fn subscribe(&self) -> watch::Receiver<WatchState> {
+        self.state.subscribe()
+    }
+
+    /// Whether anyone follows the download
+    ///
+    /// Writes are handed on right away only when it is followed.
+    pub fn is_followed(&self) -> bool {
+        self.state.receiver_count() > 0
+    }
+
+    /// Start an attempt whose output already holds `existing_size` bytes
+    pub(crate) fn begin(&self, attempt: WatchedAttempt, existing_size: u64) {
+        *self.chunks.lock().unwrap() = vec![(existing_size, u64::MAX)];
+        self.state.send_modify(|state| {
+            state.generation += 1;
+            state.attempt = Some(attempt);
+            state.readable = existing_size;
+        });
+    }
+
+    /// Split the current attempt into `chunks`
+    pub(crate) fn set_chunks(&self, chunks: &[ChunkInfo]) {
+        *self.chunks.lock().unwrap() = chunks
+            .iter()
+            .map(|chunk| (chunk.start, chunk.end + 1))
+            .collect();
+    }
+
+    /// Record that chunk `index` is written up to `position`
+    pub(crate) fn written(&self, index: usize, position: u64) {
+        let mut chunks = self.chunks.lock().unwrap();
+        if let Some(chunk) = chunks.get_mut(index) {
+            chunk.0 = position;
+        }
+
+        let readable = chunks
+            .iter()
+            .find(|(next, end)| next < end)
+            .or(chunks.last())
+            .map_or(0, |&(next, end)| next.min(end));
+        self.state.send_if_modified(|state| {
+            let advanced = state.readable != readable;
+            state.readable = readable;
+            advanced
+        });
+    }
+
+    /// Drop the current attempt before its partial output is discarded
+    pub(crate) fn reset(&self) {
+        self.chunks.lock().unwrap().clear();
+        self.state.send_modify(|state| {
+            state.generation += 1;
+            state.attempt = None;
+            state.readable = 0;
+        });
+    }
+}
+
+impl Default for DownloadWatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(index: usize, start: u64, end: u64) -> ChunkInfo {
+        ChunkInfo { start, end, index }
+    }
+
+    fn attempt() -> WatchedAttempt {
+        WatchedAttempt {
+            url: "https://example.com/file.bin".to_string(),
+            total_size: 300,
+            etag: None,
+            last_modified: None,
+        }
+    }
+
+    #[test]
+    fn test_readable_prefix_stops_at_first_unfinished_chunk() {
+        let watch = DownloadWatch::new();
+        let state = watch.subscribe();
+        watch.begin(attempt(), 0);
+        watch.set_chunks(&[chunk(0, 0, 99), chunk(1, 100, 199), chunk(2, 200, 299)]);
+
+        watch.written(1, 200);
+        watch.written(2, 250);
+        assert_eq!(state.borrow().readable, 0);
+
+        watch.written(0, 60);
+        assert_eq!(state.borrow().readable, 60);
+
+        watch.written(0, 100);
+        assert_eq!(state.borrow().readable, 250);
+
+        watch.written(2, 300);
+        assert_eq!(state.borrow().readable, 300);
+    }
+
+    #[test]
+    fn test_reset_starts_a_new_generation() {
+        let watch = DownloadWatch::new();
+        let state = watch.subscribe();
+        watch.begin(attempt(), 0);
+        watch.written(0, 42);
+        assert_eq!(state.borrow().readable, 42);
+
+        watch.reset();
+        let current = state.borrow().clone();
+        assert_eq!(current.generation, 2);
+        assert_eq!(current.readable, 0);
+        assert!(current.attempt.is_none());
+    }
+}
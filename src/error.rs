@@ -83,6 +83,18 @@ pub enum TurboCdnError {
         url: String,
     },
 
+    /// Offline mode errors (the resource is not available locally)
+    #[error("Offline mode: {resource} is not available locally")]
+    Offline { resource: String },
+
+    /// Not enough free disk space for a download
+    #[error("Insufficient disk space for {path}: {required} bytes required, {available} bytes available")]
+    InsufficientSpace {
+        path: String,
+        required: u64,
+        available: u64,
+    },
+
     /// Unsupported operation errors
     #[error("Unsupported operation: {message}")]
     Unsupported { message: String },
@@ -224,6 +236,22 @@ impl TurboCdnError {
         }
     }
 
+    /// Create a new offline error for a resource missing from local state
+    pub fn offline<S: Into<String>>(resource: S) -> Self {
+        Self::Offline {
+            resource: resource.into(),
+        }
+    }
+
+    /// Create a new insufficient disk space error
+    pub fn insufficient_space<S: Into<String>>(path: S, required: u64, available: u64) -> Self {
+        Self::InsufficientSpace {
+            path: path.into(),
+            required,
+            available,
+        }
+    }
+
     /// Create a new unsupported operation error
     pub fn unsupported<S: Into<String>>(message: S) -> Self {
         Self::Unsupported {
@@ -319,6 +347,8 @@ impl TurboCdnError {
             TurboCdnError::FileNotFound { .. } => "file_not_found",
             TurboCdnError::HttpStatus { .. } => "http_status",
             TurboCdnError::ServerError { .. } => "server_error",
+            TurboCdnError::Offline { .. } => "offline",
+            TurboCdnError::InsufficientSpace { .. } => "insufficient_space",
             TurboCdnError::Unsupported { .. } => "unsupported",
             TurboCdnError::Internal { .. } => "internal",
         }
@@ -7,20 +7,22 @@
 //! Uses multiple detection methods with fallback strategies.
 
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::timeout;
 use tracing::{debug, info, warn};
 
 use crate::config::{Region, TurboCdnConfig};
 use crate::error::{Result, TurboCdnError};
+use crate::http_backend::{HttpBackend, ReqwestBackend};
 
 /// Geographic location detector with configuration support
 #[derive(Debug)]
 pub struct GeoDetector {
     client: reqwest::Client,
+    backend: Arc<dyn HttpBackend>,
     cache: Option<DetectionResult>,
     cache_ttl: Duration,
-    #[allow(dead_code)]
     config: TurboCdnConfig,
 }
 
@@ -43,6 +45,63 @@ pub enum DetectionMethod {
     Manual,
 }
 
+/// How the region in use was determined
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RegionSource {
+    /// `general.default_region`, auto-detection disabled
+    #[default]
+    Configured,
+    /// Auto-detected
+    Detected {
+        method: DetectionMethod,
+        country_code: String,
+        confidence: f64,
+    },
+    /// Auto-detection failed, `general.default_region` is used
+    DetectionFailed { reason: String },
+    /// Auto-detection skipped in offline mode, `general.default_region` is used
+    Offline,
+    /// Set explicitly after startup
+    Manual,
+}
+
+impl RegionSource {
+    /// Source for a completed detection
+    pub fn detected(result: &DetectionResult) -> Self {
+        Self::Detected {
+            method: result.method.clone(),
+            country_code: result.country_code.clone(),
+            confidence: result.confidence,
+        }
+    }
+}
+
+impl std::fmt::Display for RegionSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Configured => write!(f, "configured default region"),
+            Self::Detected {
+                method,
+                country_code,
+                confidence,
+            } => write!(
+                f,
+                "auto-detected via {method:?} (country {country_code}, confidence {:.0}%)",
+                confidence * 100.0
+            ),
+            Self::DetectionFailed { reason } => {
+                write!(
+                    f,
+                    "auto-detection failed ({reason}), using configured default"
+                )
+            }
+            Self::Offline => write!(f, "offline, using configured default"),
+            Self::Manual => write!(f, "set manually"),
+        }
+    }
+}
+
 /// IP geolocation response from ip-api.com
 #[derive(Debug, Deserialize)]
 struct IpApiResponse {
@@ -62,23 +121,30 @@ struct IpApiResponse {
 
 impl GeoDetector {
     /// Create a new geo detector with configuration
-    pub fn new(config: TurboCdnConfig) -> Self {
-        // Initialize rustls provider before creating reqwest client
-        crate::init_rustls_provider();
-
+    ///
+    /// Fails when the proxy or TLS settings can't be applied, rather than
+    /// detecting the region over a connection that ignores them.
+    pub fn new(config: TurboCdnConfig) -> Result<Self> {
         let timeout = Duration::from_secs(config.geo_detection.ip_detection_timeout);
-        let client = reqwest::Client::builder()
+        let client = crate::client_builder::client_builder(&config)?
             .timeout(timeout)
             .user_agent(&config.general.user_agent)
             .build()
-            .unwrap_or_else(|_| reqwest::Client::new());
+            .map_err(|e| TurboCdnError::network(format!("Failed to create HTTP client: {e}")))?;
 
-        Self {
+        Ok(Self {
+            backend: Arc::new(ReqwestBackend::new(client.clone())),
             client,
             cache: None,
             cache_ttl: Duration::from_secs(config.general.url_cache_ttl),
             config,
-        }
+        })
+    }
+
+    /// Send detection requests with a shared backend
+    pub fn with_http_backend(mut self, backend: Arc<dyn HttpBackend>) -> Self {
+        self.backend = backend;
+        self
     }
 
     /// Detect user's geographic region
@@ -92,6 +158,10 @@ impl GeoDetector {
             }
         }
 
+        if self.config.general.offline {
+            return Err(TurboCdnError::offline("geographic region detection"));
+        }
+
         info!("Detecting geographic location...");
 
         // Try multiple detection methods
@@ -139,11 +209,11 @@ impl GeoDetector {
     /// Detect region using IP geolocation API
     async fn detect_via_ip_api(&self) -> Result<DetectionResult> {
         let url = "http://ip-api.com/json/?fields=status,country,countryCode,region,regionName,city,timezone";
+        self.config.security.check_url(url)?;
 
-        let response = timeout(Duration::from_secs(5), self.client.get(url).send())
+        let response = timeout(Duration::from_secs(5), self.send(self.client.get(url)))
             .await
-            .map_err(|_| TurboCdnError::network("IP geolocation request timeout"))?
-            .map_err(TurboCdnError::Network)?;
+            .map_err(|_| TurboCdnError::network("IP geolocation request timeout"))??;
 
         let ip_info: IpApiResponse = response.json().await.map_err(TurboCdnError::Network)?;
 
@@ -205,14 +275,22 @@ impl GeoDetector {
         })
     }
 
+    /// Send a request with the detector's backend
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let timeout = Duration::from_secs(self.config.geo_detection.ip_detection_timeout);
+        self.backend
+            .execute(request.timeout(timeout).build()?)
+            .await
+    }
+
     /// Test latency to a URL
     async fn test_latency(&self, url: &str) -> Result<f64> {
+        self.config.security.check_url(url)?;
         let start = std::time::Instant::now();
 
-        let _response = timeout(Duration::from_secs(3), self.client.head(url).send())
+        let _response = timeout(Duration::from_secs(3), self.send(self.client.head(url)))
             .await
-            .map_err(|_| TurboCdnError::network("Latency test timeout"))?
-            .map_err(TurboCdnError::Network)?;
+            .map_err(|_| TurboCdnError::network("Latency test timeout"))??;
 
         let latency = start.elapsed().as_millis() as f64;
         Ok(latency)
@@ -285,6 +363,6 @@ impl GeoDetector {
 
 impl Default for GeoDetector {
     fn default() -> Self {
-        Self::new(TurboCdnConfig::default())
+        Self::new(TurboCdnConfig::default()).expect("Failed to create default GeoDetector")
     }
 }
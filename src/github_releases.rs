@@ -12,6 +12,7 @@
 //! - **GitHub API**: Direct access to GitHub releases (requires token for higher rate limits)
 //! - **jsDelivr Fallback**: Automatic fallback to jsDelivr data API (no rate limits)
 //! - **Version Filtering**: Filter pre-releases, drafts, and specific patterns
+//! - **Offline Mode**: Serve previously fetched release metadata without network access
 //!
 //! # Example
 //!
@@ -38,15 +39,21 @@
 //! }
 //! ```
 
+use crate::config::TurboCdnConfig;
+use crate::credentials::{Credential, CredentialStore};
 use crate::error::{Result, TurboCdnError};
+use crate::http_backend::{HttpBackend, ReqwestBackend};
+use crate::middleware::{MiddlewareStack, RequestKind};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, info, warn};
 
 /// Default timeout for API requests
 const API_TIMEOUT: Duration = Duration::from_secs(30);
 
-/// GitHub API base URL
+/// Default GitHub API base URL
 const GITHUB_API_BASE: &str = "https://api.github.com";
 
 /// jsDelivr data API base URL (fallback, no rate limits)
@@ -75,6 +82,9 @@ pub struct ReleaseInfo {
 /// Asset information from a GitHub release
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AssetInfo {
+    /// Asset ID, used to download private assets through the API
+    #[serde(default)]
+    pub id: u64,
     /// Asset file name
     pub name: String,
     /// Asset size in bytes
@@ -119,6 +129,19 @@ pub struct FetchOptions {
     pub github_token: Option<String>,
     /// Request timeout
     pub timeout: Duration,
+    /// Never touch the network, answer only from persisted metadata
+    pub offline: bool,
+    /// Directory where fetched release metadata is persisted
+    pub metadata_dir: Option<PathBuf>,
+    /// Configuration supplying network settings such as proxies
+    pub client_config: Option<Arc<TurboCdnConfig>>,
+    /// GitHub API base URL
+    pub api_base: String,
+    /// Middleware API requests are sent through
+    pub middleware: MiddlewareStack,
+    /// Backend API requests are sent with, a client built from the network
+    /// settings when unset
+    pub http_backend: Option<Arc<dyn HttpBackend>>,
 }
 
 impl Default for FetchOptions {
@@ -129,6 +152,12 @@ impl Default for FetchOptions {
             max_versions: None,
             github_token: None,
             timeout: API_TIMEOUT,
+            offline: false,
+            metadata_dir: None,
+            client_config: None,
+            api_base: GITHUB_API_BASE.to_string(),
+            middleware: MiddlewareStack::new(),
+            http_backend: None,
         }
     }
 }
@@ -168,6 +197,74 @@ impl FetchOptions {
         self.timeout = timeout;
         self
     }
+
+    /// Enable or disable offline mode
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Send API requests through middleware
+    pub fn with_middleware(mut self, middleware: MiddlewareStack) -> Self {
+        self.middleware = middleware;
+        self
+    }
+
+    /// Send API requests with a backend instead of a client of their own
+    pub fn with_http_backend(mut self, backend: Arc<dyn HttpBackend>) -> Self {
+        self.http_backend = Some(backend);
+        self
+    }
+
+    /// Persist fetched release metadata in a directory
+    pub fn with_metadata_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.metadata_dir = Some(dir.into());
+        self
+    }
+
+    /// Set the GitHub API base URL (e.g. for GitHub Enterprise Server)
+    pub fn with_api_base<S: Into<String>>(mut self, api_base: S) -> Self {
+        self.api_base = api_base.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Use the network settings of a configuration for API requests
+    pub fn with_client_config(mut self, config: Arc<TurboCdnConfig>) -> Self {
+        self.client_config = Some(config);
+        self
+    }
+
+    /// Create options following the offline, cache and network settings of a configuration
+    ///
+    /// Release metadata is persisted next to the download cache when the
+    /// cache is enabled.
+    pub fn from_config(config: &TurboCdnConfig) -> Self {
+        let api_base = config.github.api_base.trim_end_matches('/').to_string();
+
+        // Environment tokens win over a credential configured for the API host
+        let github_token = std::env::var("GITHUB_TOKEN")
+            .or_else(|_| std::env::var("GH_TOKEN"))
+            .ok()
+            .or_else(|| {
+                let store = CredentialStore::from_config(&config.credentials).ok()?;
+                match store.for_url(&api_base)? {
+                    Credential::Bearer(token) => Some(token.clone()),
+                    Credential::Basic { .. } => None,
+                }
+            });
+
+        Self {
+            github_token,
+            api_base,
+            offline: config.general.offline,
+            metadata_dir: config
+                .cache
+                .enabled
+                .then(|| config.cache.resolve_directory().join("metadata")),
+            client_config: Some(Arc::new(config.clone())),
+            ..Self::default()
+        }
+    }
 }
 
 /// Source of version data
@@ -177,6 +274,8 @@ pub enum DataSource {
     GitHub,
     /// Data fetched from jsDelivr CDN (fallback)
     JsDelivr,
+    /// Data loaded from previously persisted metadata (offline mode)
+    LocalCache,
 }
 
 impl std::fmt::Display for DataSource {
@@ -184,6 +283,7 @@ impl std::fmt::Display for DataSource {
         match self {
             DataSource::GitHub => write!(f, "GitHub API"),
             DataSource::JsDelivr => write!(f, "jsDelivr CDN"),
+            DataSource::LocalCache => write!(f, "local metadata cache"),
         }
     }
 }
@@ -206,6 +306,52 @@ pub struct ReleasesResult {
     pub source: DataSource,
 }
 
+/// A `https://github.com/<owner>/<repo>/releases/latest/download/<asset>` URL
+///
+/// GitHub redirects these to the newest release, but mirrors serve them
+/// inconsistently and a cache keyed by the URL goes stale with the next
+/// release, so they are resolved to a concrete tag before use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatestReleaseUrl {
+    /// Repository owner
+    pub owner: String,
+    /// Repository name
+    pub repo: String,
+    /// Asset path after `download/`, including any query
+    pub asset: String,
+}
+
+impl LatestReleaseUrl {
+    /// Parse a latest release download URL
+    pub fn parse(url: &str) -> Option<Self> {
+        let path = url.strip_prefix("https://github.com/")?;
+        let mut parts = path.splitn(6, '/');
+        let owner = parts.next()?;
+        let repo = parts.next()?;
+        if (parts.next()?, parts.next()?, parts.next()?) != ("releases", "latest", "download") {
+            return None;
+        }
+        let asset = parts.next().filter(|asset| !asset.is_empty())?;
+        if owner.is_empty() || repo.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            asset: asset.to_string(),
+        })
+    }
+
+    /// Download URL of the asset in a concrete release
+    pub fn with_tag(&self, tag: &str) -> String {
+        format!(
+            "https://github.com/{}/{}/releases/download/{}/{}",
+            self.owner, self.repo, tag, self.asset
+        )
+    }
+}
+
 /// Fetcher for GitHub releases with CDN fallback
 ///
 /// Provides methods to fetch version lists and release information
@@ -252,6 +398,14 @@ impl GitHubReleasesFetcher {
         owner: &str,
         repo: &str,
     ) -> Result<VersionsResult> {
+        if self.options.offline {
+            let releases = self.load_persisted_releases(owner, repo)?;
+            return Ok(VersionsResult {
+                versions: releases.into_iter().map(|r| r.tag_name).collect(),
+                source: DataSource::LocalCache,
+            });
+        }
+
         // Try GitHub API first
         match self.fetch_versions_from_github(owner, repo).await {
             Ok(versions) => {
@@ -326,6 +480,13 @@ impl GitHubReleasesFetcher {
         owner: &str,
         repo: &str,
     ) -> Result<ReleasesResult> {
+        if self.options.offline {
+            return Ok(ReleasesResult {
+                releases: self.load_persisted_releases(owner, repo)?,
+                source: DataSource::LocalCache,
+            });
+        }
+
         // Try GitHub API first
         match self.list_releases_from_github(owner, repo).await {
             Ok(releases) => {
@@ -393,6 +554,128 @@ impl GitHubReleasesFetcher {
         })
     }
 
+    /// API URL of a release asset
+    pub fn asset_api_url(&self, owner: &str, repo: &str, asset_id: u64) -> String {
+        format!(
+            "{}/repos/{}/{}/releases/assets/{}",
+            self.options.api_base, owner, repo, asset_id
+        )
+    }
+
+    /// Resolve a release asset to its short-lived signed download URL
+    ///
+    /// Works for private repositories: the API is queried with
+    /// `Accept: application/octet-stream` and the configured token, and the
+    /// redirect target is returned without being followed, so the token is
+    /// never sent to the storage host. With a shared [`HttpBackend`] that
+    /// follows redirects, the URL the response came from is returned.
+    pub async fn resolve_asset_url(
+        &self,
+        owner: &str,
+        repo: &str,
+        asset_id: u64,
+    ) -> Result<String> {
+        let url = self.asset_api_url(owner, repo, asset_id);
+        if self.options.offline {
+            return Err(TurboCdnError::offline(url));
+        }
+
+        debug!("Resolving GitHub release asset: {}", url);
+
+        let client = self
+            .client_builder()?
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| TurboCdnError::network(format!("Failed to create HTTP client: {e}")))?;
+
+        let mut builder = client
+            .get(&url)
+            .timeout(self.options.timeout)
+            .header("User-Agent", "turbo-cdn")
+            .header("Accept", "application/octet-stream");
+        if let Some(ref token) = self.options.github_token {
+            builder = builder.header("Authorization", format!("Bearer {token}"));
+        }
+
+        let response = self
+            .options
+            .middleware
+            .send(
+                self.backend(&client).as_ref(),
+                builder,
+                RequestKind::GitHubApi,
+                |e| TurboCdnError::network(format!("GitHub API request failed: {e}")),
+            )
+            .await?;
+        let status = response.status();
+
+        // A shared backend follows the redirect itself, dropping the token
+        // on the way to the storage host; the final URL is the signed one
+        if status.is_success() && reqwest::Url::parse(&url).ok().as_ref() != Some(response.url()) {
+            return Ok(response.url().to_string());
+        }
+
+        if status.is_redirection() {
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| {
+                    TurboCdnError::download(format!("GitHub API redirect without location: {url}"))
+                })?;
+            let signed = response
+                .url()
+                .join(location)
+                .map_err(|e| TurboCdnError::download(format!("Invalid asset redirect: {e}")))?;
+            return Ok(signed.to_string());
+        }
+
+        match status.as_u16() {
+            200..=299 => Err(TurboCdnError::unsupported(format!(
+                "GitHub API returned asset {asset_id} inline instead of redirecting"
+            ))),
+            401 | 403 if self.options.github_token.is_none() => {
+                Err(TurboCdnError::authentication(format!(
+                    "Access to {url} denied (HTTP {status}); set GITHUB_TOKEN for private repositories"
+                )))
+            }
+            401 => Err(TurboCdnError::authentication(format!(
+                "GitHub token rejected for {url}"
+            ))),
+            code => Err(TurboCdnError::from_status_code(code, url)),
+        }
+    }
+
+    /// Backend sending API requests: the configured one, or the client that
+    /// built them
+    fn backend(&self, client: &reqwest::Client) -> Arc<dyn HttpBackend> {
+        match &self.options.http_backend {
+            Some(backend) => backend.clone(),
+            None => Arc::new(ReqwestBackend::new(client.clone())),
+        }
+    }
+
+    /// Build an HTTP client honoring the configured network settings
+    fn http_client(&self) -> Result<reqwest::Client> {
+        self.client_builder()?
+            .build()
+            .map_err(|e| TurboCdnError::network(format!("Failed to create HTTP client: {e}")))
+    }
+
+    /// Create a client builder honoring the configured network settings
+    fn client_builder(&self) -> Result<reqwest::ClientBuilder> {
+        let default_config;
+        let config = match &self.options.client_config {
+            Some(config) => config.as_ref(),
+            None => {
+                default_config = TurboCdnConfig::default();
+                &default_config
+            }
+        };
+
+        Ok(crate::client_builder::client_builder(config)?.timeout(self.options.timeout))
+    }
+
     /// Fetch versions from GitHub API
     async fn fetch_versions_from_github(&self, owner: &str, repo: &str) -> Result<Vec<String>> {
         let releases = self.list_releases_from_github(owner, repo).await?;
@@ -401,20 +684,17 @@ impl GitHubReleasesFetcher {
 
     /// Fetch detailed releases from GitHub API
     async fn list_releases_from_github(&self, owner: &str, repo: &str) -> Result<Vec<ReleaseInfo>> {
-        crate::init_rustls_provider();
-
         let url = format!(
             "{}/repos/{}/{}/releases?per_page={}",
-            GITHUB_API_BASE, owner, repo, GITHUB_PER_PAGE
+            self.options.api_base, owner, repo, GITHUB_PER_PAGE
         );
 
         debug!("Fetching releases from GitHub: {}", url);
 
-        let mut builder = reqwest::Client::builder()
-            .timeout(self.options.timeout)
-            .build()
-            .map_err(|e| TurboCdnError::network(format!("Failed to create HTTP client: {e}")))?
+        let client = self.http_client()?;
+        let mut builder = client
             .get(&url)
+            .timeout(self.options.timeout)
             .header("User-Agent", "turbo-cdn")
             .header("Accept", "application/vnd.github.v3+json");
 
@@ -423,10 +703,16 @@ impl GitHubReleasesFetcher {
             builder = builder.header("Authorization", format!("Bearer {token}"));
         }
 
-        let response = builder
-            .send()
-            .await
-            .map_err(|e| TurboCdnError::network(format!("GitHub API request failed: {e}")))?;
+        let response = self
+            .options
+            .middleware
+            .send(
+                self.backend(&client).as_ref(),
+                builder,
+                RequestKind::GitHubApi,
+                |e| TurboCdnError::network(format!("GitHub API request failed: {e}")),
+            )
+            .await?;
 
         let status = response.status();
 
@@ -445,7 +731,7 @@ impl GitHubReleasesFetcher {
         if !status.is_success() {
             return Err(TurboCdnError::from_status_code(
                 status.as_u16(),
-                format!("{GITHUB_API_BASE}/repos/{owner}/{repo}/releases"),
+                format!("{}/repos/{owner}/{repo}/releases", self.options.api_base),
             ));
         }
 
@@ -470,6 +756,7 @@ impl GitHubReleasesFetcher {
                     .assets
                     .into_iter()
                     .map(|a| AssetInfo {
+                        id: a.id,
                         name: a.name,
                         size: a.size,
                         browser_download_url: a.browser_download_url,
@@ -487,26 +774,84 @@ impl GitHubReleasesFetcher {
             releases
         };
 
+        self.persist_releases(owner, repo, &releases);
+
+        Ok(releases)
+    }
+
+    /// Path of the persisted metadata file for a repository
+    fn metadata_path(&self, owner: &str, repo: &str) -> Option<PathBuf> {
+        self.options
+            .metadata_dir
+            .as_ref()
+            .map(|dir| dir.join("github").join(owner).join(format!("{repo}.json")))
+    }
+
+    /// Persist fetched releases for offline use (best effort)
+    fn persist_releases(&self, owner: &str, repo: &str, releases: &[ReleaseInfo]) {
+        let Some(path) = self.metadata_path(owner, repo) else {
+            return;
+        };
+
+        let result = serde_json::to_vec(releases)
+            .map_err(std::io::Error::other)
+            .and_then(|data| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&path, data)
+            });
+
+        if let Err(e) = result {
+            warn!(
+                "Failed to persist release metadata for {}/{}: {}",
+                owner, repo, e
+            );
+        }
+    }
+
+    /// Load releases persisted by an earlier online fetch
+    fn load_persisted_releases(&self, owner: &str, repo: &str) -> Result<Vec<ReleaseInfo>> {
+        let missing = || TurboCdnError::offline(format!("release metadata for {owner}/{repo}"));
+
+        let path = self.metadata_path(owner, repo).ok_or_else(missing)?;
+        let content = std::fs::read_to_string(&path).map_err(|_| missing())?;
+        let releases: Vec<ReleaseInfo> = serde_json::from_str(&content)?;
+
+        let releases = releases
+            .into_iter()
+            .filter(|r| {
+                (self.options.include_drafts || !r.draft)
+                    && (self.options.include_prereleases || !r.prerelease)
+            })
+            .take(self.options.max_versions.unwrap_or(usize::MAX))
+            .collect();
+
+        debug!("Loaded persisted release metadata from {}", path.display());
         Ok(releases)
     }
 
     /// Fetch versions from jsDelivr data API (fallback, no rate limits)
     async fn fetch_versions_from_jsdelivr(&self, owner: &str, repo: &str) -> Result<Vec<String>> {
-        crate::init_rustls_provider();
-
         let url = format!("{}/package/gh/{}/{}", JSDELIVR_DATA_API_BASE, owner, repo);
 
         debug!("Fetching versions from jsDelivr: {}", url);
 
-        let response = reqwest::Client::builder()
-            .timeout(self.options.timeout)
-            .build()
-            .map_err(|e| TurboCdnError::network(format!("Failed to create HTTP client: {e}")))?
+        let client = self.http_client()?;
+        let request = client
             .get(&url)
-            .header("User-Agent", "turbo-cdn")
-            .send()
-            .await
-            .map_err(|e| TurboCdnError::network(format!("jsDelivr API request failed: {e}")))?;
+            .timeout(self.options.timeout)
+            .header("User-Agent", "turbo-cdn");
+        let response = self
+            .options
+            .middleware
+            .send(
+                self.backend(&client).as_ref(),
+                request,
+                RequestKind::GitHubApi,
+                |e| TurboCdnError::network(format!("jsDelivr API request failed: {e}")),
+            )
+            .await?;
 
         let status = response.status();
 
@@ -564,6 +909,7 @@ struct GitHubApiRelease {
 /// GitHub API asset response (internal deserialization structure)
 #[derive(Debug, Deserialize)]
 struct GitHubApiAsset {
+    id: u64,
     name: String,
     size: u64,
     browser_download_url: String,
@@ -621,6 +967,29 @@ pub async fn fetch_versions_with_source(owner: &str, repo: &str) -> Result<Versi
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_latest_release_url() {
+        let latest = LatestReleaseUrl::parse(
+            "https://github.com/owner/repo/releases/latest/download/tool.zip",
+        )
+        .unwrap();
+        assert_eq!(latest.owner, "owner");
+        assert_eq!(latest.repo, "repo");
+        assert_eq!(
+            latest.with_tag("v1.2.0"),
+            "https://github.com/owner/repo/releases/download/v1.2.0/tool.zip"
+        );
+
+        for url in [
+            "https://github.com/owner/repo/releases/download/latest/tool.zip",
+            "https://github.com/owner/repo/releases/latest",
+            "https://github.com/owner/repo/releases/latest/download/",
+            "https://example.com/owner/repo/releases/latest/download/tool.zip",
+        ] {
+            assert!(LatestReleaseUrl::parse(url).is_none(), "{url}");
+        }
+    }
+
     #[test]
     fn test_fetch_options_default() {
         let options = FetchOptions::default();
@@ -661,6 +1030,7 @@ mod tests {
             draft: false,
             published_at: Some("2024-01-01T00:00:00Z".to_string()),
             assets: vec![AssetInfo {
+                id: 42,
                 name: "app-linux-x64.tar.gz".to_string(),
                 size: 1024,
                 browser_download_url:
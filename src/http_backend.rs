@@ -0,0 +1,134 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Pluggable HTTP transport
+//!
+//! An [`HttpBackend`] sends the requests of every subsystem of a client:
+//! downloads, GitHub API calls, region detection, rule set subscriptions,
+//! mirror health checks and CDN quality probes. [`ReqwestBackend`] is used unless another backend is
+//! given to [`crate::TurboCdnBuilder::with_http_backend`], so one
+//! connection pool serves the whole client. Custom backends can route
+//! requests through a shared client of the host application, or answer
+//! them from memory in tests.
+
+use crate::config::TurboCdnConfig;
+use crate::constants::HTTP2_FRAME_SIZE;
+use crate::error::{Result, TurboCdnError};
+use async_trait::async_trait;
+use std::time::Duration;
+
+/// Sends HTTP requests
+///
+/// Requests arrive fully built, with credentials and middleware applied.
+/// Return transport failures as [`TurboCdnError::Network`] so callers can
+/// report them with their own context; other errors are passed on as is.
+///
+/// Credentials are attached for the request's own host only. A backend that
+/// follows redirects must drop `Authorization`, `Cookie` and
+/// `Proxy-Authorization` when a redirect leaves that host or port, as
+/// reqwest does; otherwise private tokens leak to the redirect target.
+///
+/// # Example
+/// ```rust
+/// use async_trait::async_trait;
+/// use turbo_cdn::http_backend::HttpBackend;
+///
+/// /// Sends requests with the application's own client
+/// struct SharedClient(reqwest::Client);
+///
+/// #[async_trait]
+/// impl HttpBackend for SharedClient {
+///     fn name(&self) -> &str {
+///         "shared"
+///     }
+///
+///     async fn execute(&self, request: reqwest::Request) -> turbo_cdn::Result<reqwest::Response> {
+///         Ok(self.0.execute(request).await?)
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait HttpBackend: Send + Sync {
+    /// Name shown in logs and debug output
+    fn name(&self) -> &str {
+        "custom"
+    }
+
+    /// Send a request and return its response once the headers arrived
+    async fn execute(&self, request: reqwest::Request) -> Result<reqwest::Response>;
+}
+
+impl std::fmt::Debug for dyn HttpBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpBackend")
+            .field("name", &self.name())
+            .finish()
+    }
+}
+
+/// The default backend, sending requests with a `reqwest::Client`
+#[derive(Debug, Clone)]
+pub struct ReqwestBackend {
+    client: reqwest::Client,
+}
+
+impl ReqwestBackend {
+    /// Send requests with an existing client
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    /// Build a client from the network and performance settings of a configuration
+    pub fn from_config(config: &TurboCdnConfig) -> Result<Self> {
+        let mut client_builder = crate::client_builder::client_builder(config)?
+            .timeout(Duration::from_secs(config.performance.timeout))
+            .user_agent(&config.general.user_agent)
+            .pool_max_idle_per_host(config.performance.pool_max_idle_per_host)
+            .pool_idle_timeout(Duration::from_secs(config.performance.pool_idle_timeout))
+            .tcp_keepalive(Duration::from_secs(config.performance.tcp_keepalive))
+            .tcp_nodelay(true) // Disable Nagle's algorithm for lower latency
+            .connection_verbose(config.general.debug); // Enable connection debugging if debug mode
+
+        if config.performance.http2_prior_knowledge {
+            client_builder = client_builder.http2_prior_knowledge();
+        }
+
+        // Configure TLS settings for better performance
+        client_builder = client_builder
+            .http2_adaptive_window(true)
+            .http2_max_frame_size(Some(HTTP2_FRAME_SIZE));
+
+        let client = client_builder
+            .build()
+            .map_err(|e| TurboCdnError::network(format!("Failed to create HTTP client: {e}")))?;
+        Ok(Self::new(client))
+    }
+
+    /// Get the client requests are sent with
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+}
+
+#[async_trait]
+impl HttpBackend for ReqwestBackend {
+    fn name(&self) -> &str {
+        "reqwest"
+    }
+
+    async fn execute(&self, request: reqwest::Request) -> Result<reqwest::Response> {
+        Ok(self.client.execute(request).await?)
+    }
+}
+
+/// Send a request through a backend, converting transport errors with `map_err`
+pub(crate) async fn execute(
+    backend: &dyn HttpBackend,
+    request: reqwest::Request,
+    map_err: impl FnOnce(reqwest::Error) -> TurboCdnError,
+) -> Result<reqwest::Response> {
+    match backend.execute(request).await {
+        Err(TurboCdnError::Network(e)) => Err(map_err(e)),
+        result => result,
+    }
+}
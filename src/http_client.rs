@@ -6,8 +6,11 @@
 //! This module provides a simple, reliable HTTP client implementation
 //! using reqwest with rustls for better cross-platform compatibility.
 
+use crate::config::{SecurityConfig, TurboCdnConfig};
 use crate::error::{Result, TurboCdnError};
+use crate::http_backend::{HttpBackend, ReqwestBackend};
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// HTTP response abstraction
@@ -21,16 +24,20 @@ pub struct HttpResponse {
 /// HTTP client implementation using reqwest
 #[derive(Debug)]
 pub struct HttpClient {
-    client: reqwest::Client,
+    backend: Arc<dyn HttpBackend>,
+    timeout: Duration,
+    security: SecurityConfig,
 }
 
 impl HttpClient {
     /// Create a new HTTP client with the specified timeout
     pub fn new(timeout: Duration) -> Result<Self> {
-        // Initialize rustls provider before creating reqwest client
-        crate::init_rustls_provider();
+        Self::with_config(timeout, &TurboCdnConfig::default())
+    }
 
-        let client = reqwest::Client::builder()
+    /// Create a new HTTP client using the network settings from `config`
+    pub fn with_config(timeout: Duration, config: &TurboCdnConfig) -> Result<Self> {
+        let client = crate::client_builder::client_builder(config)?
             .timeout(timeout)
             .tcp_keepalive(Duration::from_secs(60))
             .tcp_nodelay(true)
@@ -39,36 +46,37 @@ impl HttpClient {
             .build()
             .map_err(|e| TurboCdnError::network(format!("Failed to create HTTP client: {e}")))?;
 
-        Ok(Self { client })
+        Ok(Self::with_backend(
+            Arc::new(ReqwestBackend::new(client)),
+            timeout,
+            config,
+        ))
+    }
+
+    /// Create an HTTP client sending its requests with a shared backend
+    pub fn with_backend(
+        backend: Arc<dyn HttpBackend>,
+        timeout: Duration,
+        config: &TurboCdnConfig,
+    ) -> Self {
+        Self {
+            backend,
+            timeout,
+            security: config.security.clone(),
+        }
     }
 
     /// Perform a GET request
     pub async fn get(&self, url: &str) -> Result<HttpResponse> {
         let response = self
-            .client
-            .get(url)
-            .send()
-            .await
-            .map_err(|e| TurboCdnError::network(format!("GET request failed: {e}")))?;
-
-        let status = response.status().as_u16();
-        let headers = response
-            .headers()
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
-            .collect();
-
-        let body = response
-            .bytes()
-            .await
-            .map_err(|e| TurboCdnError::network(format!("Failed to read response body: {e}")))?
-            .to_vec();
-
-        Ok(HttpResponse {
-            status,
-            headers,
-            body,
-        })
+            .send(
+                reqwest::Method::GET,
+                url,
+                &HashMap::new(),
+                "GET request failed",
+            )
+            .await?;
+        Self::read_response(response, true).await
     }
 
     /// Perform a GET request with custom headers
@@ -77,46 +85,65 @@ impl HttpClient {
         url: &str,
         request_headers: &HashMap<String, String>,
     ) -> Result<HttpResponse> {
-        let mut request = self.client.get(url);
-
-        for (key, value) in request_headers {
-            request = request.header(key, value);
-        }
+        let response = self
+            .send(
+                reqwest::Method::GET,
+                url,
+                request_headers,
+                "GET request with headers failed",
+            )
+            .await?;
+        Self::read_response(response, true).await
+    }
 
-        let response = request
-            .send()
-            .await
-            .map_err(|e| TurboCdnError::network(format!("GET request with headers failed: {e}")))?;
+    /// Perform a HEAD request
+    pub async fn head(&self, url: &str) -> Result<HttpResponse> {
+        let response = self
+            .send(
+                reqwest::Method::HEAD,
+                url,
+                &HashMap::new(),
+                "HEAD request failed",
+            )
+            .await?;
+        Self::read_response(response, false).await
+    }
 
-        let status = response.status().as_u16();
-        let headers = response
-            .headers()
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
-            .collect();
+    /// Get client name for debugging
+    pub fn name(&self) -> &str {
+        self.backend.name()
+    }
 
-        let body = response
-            .bytes()
-            .await
-            .map_err(|e| TurboCdnError::network(format!("Failed to read response body: {e}")))?
-            .to_vec();
+    /// Build a request and send it with the backend
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        request_headers: &HashMap<String, String>,
+        context: &str,
+    ) -> Result<reqwest::Response> {
+        self.security.check_url(url)?;
+        let parsed = reqwest::Url::parse(url)
+            .map_err(|e| TurboCdnError::network(format!("{context}: invalid URL {url}: {e}")))?;
+
+        let mut request = reqwest::Request::new(method, parsed);
+        *request.timeout_mut() = Some(self.timeout);
+        for (key, value) in request_headers {
+            let name = reqwest::header::HeaderName::from_bytes(key.as_bytes())
+                .map_err(|e| TurboCdnError::network(format!("{context}: {e}")))?;
+            let value = reqwest::header::HeaderValue::from_str(value)
+                .map_err(|e| TurboCdnError::network(format!("{context}: {e}")))?;
+            request.headers_mut().append(name, value);
+        }
 
-        Ok(HttpResponse {
-            status,
-            headers,
-            body,
+        crate::http_backend::execute(self.backend.as_ref(), request, |e| {
+            TurboCdnError::network(format!("{context}: {e}"))
         })
+        .await
     }
 
-    /// Perform a HEAD request
-    pub async fn head(&self, url: &str) -> Result<HttpResponse> {
-        let response = self
-            .client
-            .head(url)
-            .send()
-            .await
-            .map_err(|e| TurboCdnError::network(format!("HEAD request failed: {e}")))?;
-
+    /// Collect the status, headers and, for `GET`, the body of a response
+    async fn read_response(response: reqwest::Response, with_body: bool) -> Result<HttpResponse> {
         let status = response.status().as_u16();
         let headers = response
             .headers()
@@ -124,15 +151,20 @@ impl HttpClient {
             .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
             .collect();
 
+        let body = if with_body {
+            response
+                .bytes()
+                .await
+                .map_err(|e| TurboCdnError::network(format!("Failed to read response body: {e}")))?
+                .to_vec()
+        } else {
+            Vec::new()
+        };
+
         Ok(HttpResponse {
             status,
             headers,
-            body: Vec::new(),
+            body,
         })
     }
-
-    /// Get client name for debugging
-    pub fn name(&self) -> &'static str {
-        "reqwest"
-    }
 }
@@ -8,6 +8,7 @@
 
 use crate::config::TurboCdnConfig;
 use crate::error::Result;
+use crate::http_backend::HttpBackend;
 use crate::http_client::HttpClient;
 use std::sync::Arc;
 use std::time::Duration;
@@ -74,8 +75,18 @@ impl HttpClientManager {
     /// Create a new HTTP client manager
     pub fn new(config: Arc<TurboCdnConfig>) -> Result<Self> {
         let timeout = Duration::from_secs(config.performance.timeout);
-        let client = HttpClient::new(timeout)?;
+        let client = HttpClient::with_config(timeout, &config)?;
+        Ok(Self::with_client(config, client))
+    }
+
+    /// Create an HTTP client manager sending its requests with a shared backend
+    pub fn with_backend(config: Arc<TurboCdnConfig>, backend: Arc<dyn HttpBackend>) -> Self {
+        let timeout = Duration::from_secs(config.performance.timeout);
+        let client = HttpClient::with_backend(backend, timeout, &config);
+        Self::with_client(config, client)
+    }
 
+    fn with_client(config: Arc<TurboCdnConfig>, client: HttpClient) -> Self {
         let metrics = ClientMetrics {
             avg_response_time: Duration::from_millis(0),
             success_rate: 1.0,
@@ -83,13 +94,16 @@ impl HttpClientManager {
             last_updated: std::time::Instant::now(),
         };
 
-        info!("HTTP client manager initialized with reqwest client");
+        info!(
+            "HTTP client manager initialized with {} backend",
+            client.name()
+        );
 
-        Ok(Self {
+        Self {
             config,
             client,
             metrics: Arc::new(std::sync::Mutex::new(metrics)),
-        })
+        }
     }
 
     /// Perform a GET request
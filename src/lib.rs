@@ -65,47 +65,77 @@ pub fn init_rustls_provider() {
 
 pub mod adaptive_concurrency;
 pub mod adaptive_speed_controller;
+pub mod buffer_pool;
 pub mod cdn_quality;
 pub mod cli_progress;
+pub mod client_builder;
 pub mod concurrent_downloader;
 pub mod config;
+pub mod config_watcher;
 pub mod constants;
+pub mod credentials;
 pub mod dns_cache;
+pub mod download_cache;
+pub mod download_watch;
 pub mod error;
 pub mod geo_detection;
 pub mod github_releases;
+pub mod http_backend;
 pub mod http_client;
 pub mod http_client_manager;
 pub mod load_balancer;
 pub mod logging;
 pub mod memory_tracker;
+pub mod middleware;
+pub mod mirror;
 pub mod mmap_writer;
 pub mod progress;
+pub mod rule_check;
+#[cfg(feature = "server")]
+pub mod server;
 pub mod server_quality_scorer;
 pub mod server_tracker;
 pub mod smart_chunking;
 pub mod smart_downloader;
 pub mod string_interner;
+pub mod subscription;
 pub mod url_mapper;
+pub mod url_policy;
+pub mod url_resolver;
+pub mod url_template;
+pub mod write_backend;
 
 // Note: Imports will be added as needed
 
 // Re-export commonly used types
 pub use concurrent_downloader::{ConcurrentDownloader, DownloadResult};
 pub use config::{Region, TurboCdnConfig};
+pub use config_watcher::{ConfigWatcher, WatchStatus};
 pub use constants::*;
+pub use download_cache::{CacheEntry, DownloadCache};
 pub use error::{Result, TurboCdnError};
+pub use geo_detection::RegionSource;
 pub use github_releases::{
-    AssetInfo, DataSource, FetchOptions, GitHubReleasesFetcher, ReleaseInfo, ReleasesResult,
-    VersionsResult,
+    AssetInfo, DataSource, FetchOptions, GitHubReleasesFetcher, LatestReleaseUrl, ReleaseInfo,
+    ReleasesResult, VersionsResult,
 };
+pub use http_backend::{HttpBackend, ReqwestBackend};
+pub use middleware::{RequestKind, RequestMiddleware};
+pub use mirror::MirrorCatalog;
 pub use progress::{ConsoleProgressReporter, ProgressCallback, ProgressInfo, ProgressTracker};
 pub use server_tracker::{PerformanceSummary, ServerStats};
-pub use url_mapper::UrlMapper;
+pub use subscription::{RefreshOutcome, SubscriptionSet, SubscriptionStatus};
+pub use url_mapper::{
+    ExplainedCandidate, ResolvedCandidate, RuleOutcome, RuleTrace, UrlExplanation, UrlMapper,
+    UrlResolution,
+};
+pub use url_policy::{PolicyReport, UrlPolicy};
+pub use url_resolver::{ResolverCandidate, UrlResolver};
+pub use url_template::UrlTemplate;
 
 // Internal imports
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use tracing::{info, warn};
 
@@ -247,48 +277,147 @@ pub struct TurboCdn {
     #[allow(dead_code)]
     progress_tracker: Option<Arc<ProgressTracker>>,
     stats: Arc<RwLock<TurboCdnStats>>,
+    download_cache: Option<Arc<DownloadCache>>,
+    offline: bool,
+    config: Arc<TurboCdnConfig>,
+    /// `owner/repo` to the resolved latest release tag and when it was resolved
+    latest_releases: dashmap::DashMap<String, (String, Instant)>,
+    /// Remote rule sets merged into the mapping rules
+    subscriptions: Arc<SubscriptionSet>,
+    /// Configuration and added rules the URL mapper is built from before
+    /// the rule sets are applied
+    rules_config: Arc<RwLock<RuleSources>>,
+    /// Resolvers whose candidates are merged with the rule candidates
+    resolvers: url_resolver::ResolverSet,
     created_at: Instant,
 }
 
+/// Where [`TurboCdn::lookup_stream`] found a download
+#[cfg(feature = "server")]
+pub(crate) enum DownloadStream {
+    /// A cached object that matched its hash
+    Cached {
+        entry: CacheEntry,
+        path: std::path::PathBuf,
+    },
+    /// Nothing usable is cached, the download has to come from a mirror
+    Uncached {
+        /// Cache key of the download
+        key: String,
+    },
+}
+
 impl TurboCdn {
     /// Create a new builder for TurboCdn
     pub fn builder() -> TurboCdnBuilder {
         TurboCdnBuilder::new()
     }
 
-    /// Create a TurboCdn client with default configuration
+    /// Create a TurboCdn client with the layered configuration
+    ///
+    /// See [`config::ConfigLoader`] for the layers and their order.
     pub async fn new() -> Result<Self> {
-        let config = TurboCdnConfig::load().unwrap_or_default();
+        let config = TurboCdnConfig::load_layered()?.config;
         Self::with_config(config).await
     }
 
     /// Create a TurboCdn client with custom configuration
     pub async fn with_config(config: TurboCdnConfig) -> Result<Self> {
-        // Auto-detect region if enabled
-        let region = if config.geo_detection.auto_detect_region {
-            let mut geo_detector = crate::geo_detection::GeoDetector::new(config.clone());
+        Self::from_parts(config, middleware::MiddlewareStack::new(), None).await
+    }
+
+    /// Create a client whose requests go through `middleware` and are sent
+    /// with `http_backend`, or a reqwest client built from `config`
+    async fn from_parts(
+        config: TurboCdnConfig,
+        middleware: middleware::MiddlewareStack,
+        http_backend: Option<Arc<dyn HttpBackend>>,
+    ) -> Result<Self> {
+        let downloader = match http_backend {
+            Some(backend) => ConcurrentDownloader::with_backend(&config, backend)?,
+            None => ConcurrentDownloader::with_config(&config)?,
+        }
+        .with_middleware(middleware);
+
+        // Auto-detect region if enabled (never offline)
+        let (region, region_source) = if !config.geo_detection.auto_detect_region {
+            (
+                config.general.default_region.clone(),
+                RegionSource::Configured,
+            )
+        } else if config.general.offline {
+            (config.general.default_region.clone(), RegionSource::Offline)
+        } else {
+            let mut geo_detector = crate::geo_detection::GeoDetector::new(config.clone())?
+                .with_http_backend(downloader.http_backend().clone());
             match geo_detector.detect_region().await {
                 Ok(detected_region) => {
                     info!("Auto-detected region: {:?}", detected_region);
-                    detected_region
+                    let source = geo_detector
+                        .get_cached_result()
+                        .map_or(RegionSource::Configured, RegionSource::detected);
+                    (detected_region, source)
                 }
                 Err(e) => {
                     warn!("Failed to auto-detect region: {}, using default", e);
-                    config.general.default_region.clone()
+                    let source = RegionSource::DetectionFailed {
+                        reason: e.to_string(),
+                    };
+                    (config.general.default_region.clone(), source)
                 }
             }
-        } else {
-            config.general.default_region.clone()
         };
 
-        let url_mapper = UrlMapper::new(&config, region)?;
-        let downloader = ConcurrentDownloader::with_config(&config)?;
+        let subscriptions = Arc::new(SubscriptionSet::new(&config)?);
+        let mut mapping_config = config.clone();
+        subscriptions.apply_to(&mut mapping_config);
+        let url_mapper = UrlMapper::new(&mapping_config, region)?
+            .with_server_tracker(downloader.server_tracker())
+            .with_shared_mirrors(downloader.shared_mirrors())
+            .with_region_source(region_source);
+        let url_mapper = Arc::new(RwLock::new(url_mapper));
+        let rules_config = Arc::new(RwLock::new(RuleSources {
+            config: config.clone(),
+            added: Vec::new(),
+        }));
+        let config = Arc::new(config);
+
+        if !config.general.offline {
+            if let Some(interval) = subscriptions.refresh_interval() {
+                tokio::spawn(refresh_subscriptions_periodically(
+                    downloader.http_backend().clone(),
+                    Arc::downgrade(&url_mapper),
+                    subscriptions.clone(),
+                    rules_config.clone(),
+                    interval,
+                ));
+            }
+        }
+
+        let download_cache = if config.cache.enabled {
+            match DownloadCache::open(&config.cache) {
+                Ok(cache) => Some(Arc::new(cache)),
+                Err(e) => {
+                    warn!("Download cache unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
 
         Ok(Self {
-            url_mapper: Arc::new(RwLock::new(url_mapper)),
+            url_mapper,
             downloader,
             progress_tracker: None,
             stats: Arc::new(RwLock::new(TurboCdnStats::default())),
+            download_cache,
+            offline: config.general.offline,
+            config,
+            latest_releases: dashmap::DashMap::new(),
+            subscriptions,
+            rules_config,
+            resolvers: url_resolver::ResolverSet::new(),
             created_at: Instant::now(),
         })
     }
@@ -321,15 +450,20 @@ impl TurboCdn {
     /// }
     /// ```
     pub async fn download_from_url(&self, url: &str) -> Result<DownloadResult> {
+        let (url, resolved_tag) = self.resolve_latest_release(url).await;
+
         // Map URL to optimal CDN alternatives
-        let urls = self.url_mapper.read().await.map_url(url)?;
+        let urls = self.map_url(&url).await?;
 
         // Generate output filename from URL
-        let filename = self.extract_filename_from_url(url)?;
+        let filename = self.extract_filename_from_url(&url)?;
         let output_path = std::env::temp_dir().join(&filename);
 
         // Download with concurrent downloader
-        let result = self.downloader.download(&urls, &output_path, None).await?;
+        let mut result = self
+            .download_cached(&url, &urls, &output_path, None)
+            .await?;
+        result.resolved_tag = resolved_tag;
 
         // Update stats
         self.update_stats(&result).await;
@@ -343,8 +477,43 @@ impl TurboCdn {
         url: &str,
         output_path: P,
     ) -> Result<DownloadResult> {
-        let urls = self.url_mapper.read().await.map_url(url)?;
-        let result = self.downloader.download(&urls, output_path, None).await?;
+        let (url, resolved_tag) = self.resolve_latest_release(url).await;
+        let urls = self.map_url(&url).await?;
+        let mut result = self
+            .download_cached(&url, &urls, output_path.as_ref(), None)
+            .await?;
+        result.resolved_tag = resolved_tag;
+        self.update_stats(&result).await;
+        Ok(result)
+    }
+
+    /// Download a GitHub release asset by ID, including from private repositories
+    ///
+    /// The asset is resolved through the GitHub API to a signed URL, which is
+    /// downloaded with the chunked downloader. Public mirrors are never used.
+    /// Asset IDs are immutable, so cached copies are reused without
+    /// revalidation.
+    pub async fn download_github_asset<P: AsRef<std::path::Path>>(
+        &self,
+        owner: &str,
+        repo: &str,
+        asset_id: u64,
+        output_path: P,
+    ) -> Result<DownloadResult> {
+        let output_path = output_path.as_ref();
+        let fetcher = GitHubReleasesFetcher::with_options(self.fetch_options());
+
+        // Signed URLs expire, so the asset is cached under its API URL
+        let cache_key = fetcher.asset_api_url(owner, repo, asset_id);
+        if let Some(result) = self.cached_copy(&cache_key, output_path, false).await {
+            self.update_stats(&result).await;
+            return Ok(result);
+        }
+
+        let signed_url = fetcher.resolve_asset_url(owner, repo, asset_id).await?;
+        let result = self
+            .download_and_cache(&cache_key, &[signed_url], output_path, None)
+            .await?;
         self.update_stats(&result).await;
         Ok(result)
     }
@@ -352,14 +521,18 @@ impl TurboCdn {
     /// Download directly from original URL without CDN optimization
     pub async fn download_direct_from_url(&self, url: &str) -> Result<DownloadResult> {
         // Use only the original URL, no CDN mapping
-        let urls = vec![url.to_string()];
+        let (url, resolved_tag) = self.resolve_latest_release(url).await;
+        let urls = vec![url.clone()];
 
         // Generate output filename from URL
-        let filename = self.extract_filename_from_url(url)?;
+        let filename = self.extract_filename_from_url(&url)?;
         let output_path = std::env::temp_dir().join(&filename);
 
         // Download with concurrent downloader
-        let result = self.downloader.download(&urls, &output_path, None).await?;
+        let mut result = self
+            .download_cached(&url, &urls, &output_path, None)
+            .await?;
+        result.resolved_tag = resolved_tag;
         self.update_stats(&result).await;
         Ok(result)
     }
@@ -371,8 +544,12 @@ impl TurboCdn {
         output_path: P,
     ) -> Result<DownloadResult> {
         // Use only the original URL, no CDN mapping
-        let urls = vec![url.to_string()];
-        let result = self.downloader.download(&urls, output_path, None).await?;
+        let (url, resolved_tag) = self.resolve_latest_release(url).await;
+        let urls = vec![url.clone()];
+        let mut result = self
+            .download_cached(&url, &urls, output_path.as_ref(), None)
+            .await?;
+        result.resolved_tag = resolved_tag;
         self.update_stats(&result).await;
         Ok(result)
     }
@@ -388,10 +565,14 @@ impl TurboCdn {
         url: &str,
         verbose: bool,
     ) -> Result<DownloadResult> {
-        use crate::smart_downloader::SmartDownloader;
+        // Method selection needs the network, offline requests go to the cache
+        if self.offline {
+            return self.download_from_url(url).await;
+        }
 
-        let smart_downloader = SmartDownloader::new_with_verbose(verbose).await?;
-        smart_downloader.download_smart(url).await
+        self.smart_session(&smart_downloader::SmartDownloadConfig::default(), verbose)
+            .download_smart(url)
+            .await
     }
 
     /// Smart download to specific path with automatic method selection
@@ -411,14 +592,28 @@ impl TurboCdn {
         output_path: P,
         verbose: bool,
     ) -> Result<DownloadResult> {
-        use crate::smart_downloader::SmartDownloader;
+        if self.offline {
+            return self.download_to_path(url, output_path).await;
+        }
 
-        let smart_downloader = SmartDownloader::new_with_verbose(verbose).await?;
-        smart_downloader
+        self.smart_session(&smart_downloader::SmartDownloadConfig::default(), verbose)
             .download_smart_to_path(url, output_path)
             .await
     }
 
+    /// Speed-test and download with this client
+    fn smart_session<'a>(
+        &'a self,
+        config: &'a smart_downloader::SmartDownloadConfig,
+        verbose: bool,
+    ) -> smart_downloader::SmartSession<'a> {
+        smart_downloader::SmartSession {
+            config,
+            turbo_cdn: self,
+            verbose,
+        }
+    }
+
     /// Download with custom options
     pub async fn download_with_options<P: AsRef<std::path::Path>>(
         &self,
@@ -426,7 +621,8 @@ impl TurboCdn {
         output_path: P,
         options: DownloadOptions,
     ) -> Result<DownloadResult> {
-        let urls = self.url_mapper.read().await.map_url(url)?;
+        let (url, resolved_tag) = self.resolve_latest_release(url).await;
+        let urls = self.map_url(&url).await?;
 
         // Create progress tracker if callback is provided
         let progress_tracker = if options.progress_callback.is_some() {
@@ -436,35 +632,626 @@ impl TurboCdn {
             None
         };
 
-        let result = self
-            .downloader
-            .download(&urls, output_path, progress_tracker)
+        let mut result = self
+            .download_cached(&url, &urls, output_path.as_ref(), progress_tracker)
             .await?;
+        result.resolved_tag = resolved_tag;
         self.update_stats(&result).await;
         Ok(result)
     }
 
     /// Get optimal CDN URL without downloading
     pub async fn get_optimal_url(&self, url: &str) -> Result<String> {
-        let urls = self.url_mapper.read().await.map_url(url)?;
-        Ok(urls.into_iter().next().unwrap_or_else(|| url.to_string()))
+        let (url, _) = self.resolve_latest_release(url).await;
+        let urls = self.map_url(&url).await?;
+        Ok(urls.into_iter().next().unwrap_or(url))
     }
 
     /// Get all available CDN URLs for a given URL
     pub async fn get_all_cdn_urls(&self, url: &str) -> Result<Vec<String>> {
-        self.url_mapper.read().await.map_url(url)
+        let (url, _) = self.resolve_latest_release(url).await;
+        self.map_url(&url).await
+    }
+
+    /// Resolve a URL into ranked candidates without downloading
+    ///
+    /// Reports the matched rule, the region in use and the performance
+    /// observed for each candidate server.
+    pub async fn resolve_url(&self, url: &str) -> Result<UrlResolution> {
+        let (resolved_url, resolved_tag) = self.resolve_latest_release(url).await;
+        let urls = self.map_url(&resolved_url).await?;
+        let (matched_rules, region, policy) = {
+            let mapper = self.url_mapper.read().await;
+            (
+                mapper
+                    .matching_rules(&resolved_url)
+                    .into_iter()
+                    .map(|rule| rule.name.clone())
+                    .collect::<Vec<_>>(),
+                mapper.region().to_string(),
+                mapper.evaluate_policy(&resolved_url),
+            )
+        };
+
+        let candidates = urls
+            .into_iter()
+            .map(|candidate| {
+                let stats = self.downloader.get_server_detail(&candidate);
+                ResolvedCandidate {
+                    score: stats.as_ref().map(|s| s.performance_score()),
+                    success_rate: stats.as_ref().map(|s| s.success_rate),
+                    average_speed: stats.as_ref().map(|s| s.average_speed),
+                    attempts: stats.as_ref().map_or(0, |s| s.total_attempts),
+                    url: candidate,
+                }
+            })
+            .collect();
+
+        Ok(UrlResolution {
+            url: url.to_string(),
+            region,
+            matched_rule: matched_rules.first().cloned(),
+            matched_rules,
+            resolved_tag,
+            candidates,
+            policy,
+        })
+    }
+
+    /// Explain how a URL is mapped
+    ///
+    /// Reports the region and how it was determined, what happened to every
+    /// rule, and the candidates with the weights and server scores that
+    /// ordered them.
+    pub async fn explain_url(&self, url: &str) -> UrlExplanation {
+        let (resolved_url, resolved_tag) = self.resolve_latest_release(url).await;
+        let resolved = if self.offline {
+            Vec::new()
+        } else {
+            let region = self.url_mapper.read().await.region().clone();
+            self.resolvers.resolve(&resolved_url, &region).await
+        };
+        let mut explanation = self
+            .url_mapper
+            .read()
+            .await
+            .explain_with(&resolved_url, &resolved);
+        explanation.resolved_tag = resolved_tag;
+        explanation
+    }
+
+    /// Map a URL with the rules and the registered resolvers
+    ///
+    /// Resolvers are only consulted when the mapping isn't cached.
+    async fn map_url(&self, url: &str) -> Result<Vec<String>> {
+        if self.offline || self.resolvers.is_empty() {
+            return self.url_mapper.read().await.map_url(url);
+        }
+
+        let region = {
+            let mapper = self.url_mapper.read().await;
+            if let Some(urls) = mapper.cached_urls(url) {
+                return Ok(urls);
+            }
+            mapper.region().clone()
+        };
+        let resolved = self.resolvers.resolve(url, &region).await;
+        self.url_mapper.read().await.map_url_with(url, &resolved)
+    }
+
+    /// Resolve a `releases/latest/download` URL to the current release
+    ///
+    /// Returns the URL to map and the resolved tag. Other URLs, and latest
+    /// URLs whose release can't be determined, are returned unchanged.
+    async fn resolve_latest_release(&self, url: &str) -> (String, Option<String>) {
+        let Some(latest) = LatestReleaseUrl::parse(url) else {
+            return (url.to_string(), None);
+        };
+
+        let key = format!("{}/{}", latest.owner, latest.repo);
+        let ttl = std::time::Duration::from_secs(self.config.general.url_cache_ttl);
+        if let Some(entry) = self.latest_releases.get(&key) {
+            if entry.1.elapsed() < ttl {
+                return (latest.with_tag(&entry.0), Some(entry.0.clone()));
+            }
+        }
+
+        let fetcher = GitHubReleasesFetcher::with_options(self.fetch_options());
+        match fetcher
+            .fetch_latest_version(&latest.owner, &latest.repo)
+            .await
+        {
+            Ok(tag) => {
+                info!("Resolved latest release of {} to {}", key, tag);
+                self.latest_releases
+                    .insert(key, (tag.clone(), Instant::now()));
+                (latest.with_tag(&tag), Some(tag))
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to resolve latest release of {}: {}, using {} as is",
+                    key, e, url
+                );
+                (url.to_string(), None)
+            }
+        }
     }
 
     /// Check if a URL can be optimized
     pub async fn can_optimize_url(&self, url: &str) -> bool {
-        self.url_mapper
-            .read()
+        self.map_url(url)
             .await
-            .map_url(url)
             .map(|urls| urls.len() > 1)
             .unwrap_or(false)
     }
 
+    /// Check whether the client runs in offline mode
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Get the HTTP client requests are built with
+    ///
+    /// Send requests with [`Self::http_backend`]: with a custom backend this
+    /// client only assembles them.
+    pub fn http_client(&self) -> &reqwest::Client {
+        self.downloader.http_client()
+    }
+
+    /// Get the backend every subsystem sends its requests with
+    pub fn http_backend(&self) -> &Arc<dyn HttpBackend> {
+        self.downloader.http_backend()
+    }
+
+    /// Get the middleware requests are sent through
+    pub fn middleware(&self) -> &middleware::MiddlewareStack {
+        self.downloader.middleware()
+    }
+
+    /// Create an HTTP client manager sending its requests with this client's backend
+    pub fn http_client_manager(&self) -> http_client_manager::HttpClientManager {
+        http_client_manager::HttpClientManager::with_backend(
+            self.config.clone(),
+            self.http_backend().clone(),
+        )
+    }
+
+    /// Create a CDN quality assessor probing with this client's backend
+    pub fn quality_assessor(&self) -> cdn_quality::CdnQualityAssessor {
+        cdn_quality::CdnQualityAssessor::with_backend(
+            (*self.config).clone(),
+            self.http_backend().clone(),
+        )
+    }
+
+    /// Probe every enabled mirror of the current catalog
+    ///
+    /// Mirrors from subscriptions and reloaded configuration are included.
+    pub async fn check_mirror_health(&self) -> Result<Vec<mirror::MirrorHealth>> {
+        if self.offline {
+            return Err(TurboCdnError::offline("mirror health checks"));
+        }
+
+        let catalog = self.downloader.shared_mirrors().read().unwrap().clone();
+        let timeout = Duration::from_secs(self.config.performance.timeout);
+        let checks = catalog
+            .iter()
+            .filter(|mirror| catalog.is_enabled(&mirror.name))
+            .map(|mirror| mirror::check_health(self.http_backend().as_ref(), timeout, mirror));
+        Ok(futures::future::join_all(checks).await)
+    }
+
+    /// GitHub API options with the client's configuration, middleware and backend
+    fn fetch_options(&self) -> FetchOptions {
+        FetchOptions::from_config(&self.config)
+            .with_middleware(self.middleware().clone())
+            .with_http_backend(self.http_backend().clone())
+    }
+
+    /// Get the configuration this client was created with
+    pub fn config(&self) -> &TurboCdnConfig {
+        &self.config
+    }
+
+    /// Get the rule-set subscriptions
+    pub fn subscriptions(&self) -> &SubscriptionSet {
+        &self.subscriptions
+    }
+
+    /// Fetch every subscribed rule set now and apply the updates
+    ///
+    /// Rule sets that fail verification are reported and the last good set
+    /// stays in use.
+    pub async fn refresh_subscriptions(&self) -> Result<Vec<SubscriptionStatus>> {
+        if self.offline {
+            return Err(TurboCdnError::offline("rule set subscriptions"));
+        }
+
+        let base = self.rules_config.read().await.config.clone();
+        let statuses = self
+            .subscriptions
+            .refresh(self.http_backend().as_ref(), &base, true)
+            .await;
+        if statuses
+            .iter()
+            .any(|s| s.outcome == RefreshOutcome::Updated)
+        {
+            apply_subscriptions(&self.url_mapper, &self.subscriptions, &self.rules_config).await?;
+        }
+        Ok(statuses)
+    }
+
+    /// Rebuild the URL mapping from a new configuration
+    ///
+    /// Rules, mirrors and the `[mapping]` and `[url_policy]` settings are
+    /// swapped atomically and the mapping cache is cleared; server
+    /// performance history and the region are kept. Download, network and
+    /// subscription settings keep the values the client was created with.
+    /// Rules added with [`TurboCdn::add_rule`] are kept on top of the new
+    /// configuration.
+    ///
+    /// A configuration with rule errors (see [`rule_check`]) is rejected
+    /// and the previous rules stay in use.
+    pub async fn reload_config(&self, config: TurboCdnConfig) -> Result<()> {
+        reload_rules(
+            &self.url_mapper,
+            &self.subscriptions,
+            &self.rules_config,
+            config,
+        )
+        .await
+    }
+
+    /// Add a URL mapping rule
+    ///
+    /// The rule is checked like the rules of a configuration file and takes
+    /// part in priority ordering like them; the mapping cache is cleared.
+    /// Names must be unique. Added rules survive configuration reloads.
+    pub async fn add_rule(&self, rule: config::UrlMappingRuleConfig) -> Result<()> {
+        self.update_rules(|sources| {
+            let exists = sources
+                .config
+                .url_mapping_rules
+                .iter()
+                .chain(&sources.added)
+                .any(|r| r.name == rule.name);
+            if exists {
+                return Err(TurboCdnError::config(format!(
+                    "Rule '{}' already exists",
+                    rule.name
+                )));
+            }
+            sources.added.push(rule);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Remove a URL mapping rule by name
+    ///
+    /// Rules of the configuration come back when it is reloaded.
+    pub async fn remove_rule(&self, name: &str) -> Result<()> {
+        self.update_rules(|sources| {
+            sources.find_rule(name)?;
+            sources
+                .config
+                .url_mapping_rules
+                .retain(|rule| rule.name != name);
+            sources.added.retain(|rule| rule.name != name);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Enable or disable a URL mapping rule by name
+    ///
+    /// Rules of the configuration get their configured state back when it
+    /// is reloaded.
+    pub async fn set_rule_enabled(&self, name: &str, enabled: bool) -> Result<()> {
+        self.update_rules(|sources| {
+            sources.find_rule(name)?.enabled = enabled;
+            Ok(())
+        })
+        .await
+    }
+
+    /// List the URL mapping rules of the configuration and those added at
+    /// runtime, in the order they were defined
+    ///
+    /// Rules from subscribed rule sets are not included.
+    pub async fn list_rules(&self) -> Vec<config::UrlMappingRuleConfig> {
+        self.rules_config.read().await.merged().url_mapping_rules
+    }
+
+    async fn update_rules(&self, edit: impl FnOnce(&mut RuleSources) -> Result<()>) -> Result<()> {
+        update_rules(
+            &self.url_mapper,
+            &self.subscriptions,
+            &self.rules_config,
+            edit,
+        )
+        .await
+    }
+
+    /// Register a resolver, replacing one with the same name
+    ///
+    /// Its candidates are merged with those of the rules and ranked with
+    /// them. The mapping cache is cleared.
+    pub async fn add_resolver(&self, resolver: Arc<dyn UrlResolver>) {
+        self.resolvers.register(resolver);
+        self.url_mapper.read().await.clear_cache();
+    }
+
+    /// Remove a resolver by name, returning whether it was registered
+    pub async fn remove_resolver(&self, name: &str) -> bool {
+        let removed = self.resolvers.remove(name);
+        if removed {
+            self.url_mapper.read().await.clear_cache();
+        }
+        removed
+    }
+
+    /// Names of the registered resolvers, in the order they are consulted
+    pub fn resolvers(&self) -> Vec<String> {
+        self.resolvers.names()
+    }
+
+    /// Reload the configuration whenever one of the loader's files changes
+    ///
+    /// The files are polled every `interval` and each change is applied
+    /// like [`TurboCdn::reload_config`]; rejected changes are logged and
+    /// reported by [`ConfigWatcher::status`]. Watching stops when the
+    /// watcher is dropped.
+    pub fn watch_config(&self, loader: config::ConfigLoader, interval: Duration) -> ConfigWatcher {
+        let url_mapper = Arc::downgrade(&self.url_mapper);
+        let subscriptions = self.subscriptions.clone();
+        let rules_config = self.rules_config.clone();
+
+        ConfigWatcher::spawn(loader, interval, move |config| {
+            let url_mapper = url_mapper.upgrade();
+            let subscriptions = subscriptions.clone();
+            let rules_config = rules_config.clone();
+            async move {
+                let url_mapper = url_mapper?;
+                Some(reload_rules(&url_mapper, &subscriptions, &rules_config, config).await)
+            }
+        })
+    }
+
+    /// Get the local download cache, if enabled
+    pub fn download_cache(&self) -> Option<&Arc<DownloadCache>> {
+        self.download_cache.as_ref()
+    }
+
+    /// Download through the local cache
+    ///
+    /// Serves a fresh cache entry for `url` without touching the network and
+    /// stores new downloads in the cache. Cache failures never fail the
+    /// download itself.
+    async fn download_cached(
+        &self,
+        url: &str,
+        urls: &[String],
+        output_path: &std::path::Path,
+        progress_tracker: Option<Arc<ProgressTracker>>,
+    ) -> Result<DownloadResult> {
+        if let Some(result) = self.cached_copy(url, output_path, true).await {
+            return Ok(result);
+        }
+        self.download_and_cache(url, urls, output_path, progress_tracker)
+            .await
+    }
+
+    /// Materialize a cached copy of `url` at `output_path`, if one can be used
+    ///
+    /// Without `revalidate` the entry is trusted as-is, for resources that
+    /// never change under the same key.
+    async fn cached_copy(
+        &self,
+        url: &str,
+        output_path: &std::path::Path,
+        revalidate: bool,
+    ) -> Option<DownloadResult> {
+        let (cache, entry) = self
+            .usable_cache_entry(url, revalidate, Some(output_path))
+            .await?;
+
+        let start = Instant::now();
+        // Hashing and copying the object would stall the runtime
+        let materialized = {
+            let entry = entry.clone();
+            let destination = output_path.to_path_buf();
+            tokio::task::spawn_blocking(move || cache.materialize(&entry, &destination))
+                .await
+                .unwrap_or_else(|e| Err(TurboCdnError::internal(e.to_string())))
+        };
+        match materialized {
+            Ok(()) => {
+                info!("Serving {} from download cache", url);
+                Some(DownloadResult {
+                    path: output_path.to_path_buf(),
+                    size: entry.size,
+                    duration: start.elapsed(),
+                    speed: 0.0,
+                    url: entry.source_url,
+                    resumed: false,
+                    etag: entry.validators.etag,
+                    last_modified: entry.validators.last_modified,
+                    from_cache: true,
+                    resolved_tag: None,
+                })
+            }
+            Err(e) => {
+                warn!("Failed to use cached copy of {}: {}", url, e);
+                None
+            }
+        }
+    }
+
+    /// Look up the cache entry of `url`, dropping it when it went stale
+    ///
+    /// A stale entry also removes the copy at `destination`, if any.
+    async fn usable_cache_entry(
+        &self,
+        url: &str,
+        revalidate: bool,
+        destination: Option<&std::path::Path>,
+    ) -> Option<(Arc<DownloadCache>, CacheEntry)> {
+        let cache = self.download_cache.clone()?;
+        let entry = cache.lookup(url)?;
+        if revalidate && !self.is_cache_entry_fresh(&cache, &entry).await {
+            let _ = cache.remove(url);
+            // A stale copy at the destination must not be resumed from
+            if let Some(path) = destination.filter(|path| path.exists()) {
+                let _ = tokio::fs::remove_file(path).await;
+            }
+            return None;
+        }
+        Some((cache, entry))
+    }
+
+    /// Look up `url` in the download cache for streaming
+    #[cfg(feature = "server")]
+    pub(crate) async fn lookup_stream(&self, url: &str) -> DownloadStream {
+        let (url, _) = self.resolve_latest_release(url).await;
+
+        if let Some((cache, entry)) = self.usable_cache_entry(&url, true, None).await {
+            // Hashing the object would stall the runtime
+            let verified = {
+                let entry = entry.clone();
+                tokio::task::spawn_blocking(move || cache.verified_object(&entry))
+                    .await
+                    .unwrap_or_else(|e| Err(TurboCdnError::internal(e.to_string())))
+            };
+            match verified {
+                Ok(path) => {
+                    info!("Serving {} from download cache", url);
+                    return DownloadStream::Cached { entry, path };
+                }
+                Err(e) => warn!("Failed to use cached copy of {}: {}", url, e),
+            }
+        }
+        DownloadStream::Uncached { key: url }
+    }
+
+    /// Open a response for the uncached `key` on the first mirror that answers
+    ///
+    /// `range` is forwarded to the mirror. Returns the URL that answered
+    /// with its response.
+    #[cfg(feature = "server")]
+    pub(crate) async fn open_stream(
+        &self,
+        key: &str,
+        range: Option<&str>,
+    ) -> Result<(String, reqwest::Response)> {
+        let urls = self.map_url(key).await?;
+        self.downloader.open_stream(&urls, range).await
+    }
+
+    /// Download the uncached `key` with the chunked downloader, reporting written bytes to `watch`
+    ///
+    /// The finished download is stored in the cache.
+    #[cfg(feature = "server")]
+    pub(crate) async fn download_watched(
+        &self,
+        key: &str,
+        output_path: &std::path::Path,
+        watch: Arc<download_watch::DownloadWatch>,
+    ) -> Result<DownloadResult> {
+        let urls = self.map_url(key).await?;
+        let result = self
+            .downloader
+            .download_watched(&urls, output_path, watch)
+            .await?;
+
+        let validators = download_cache::CacheValidators {
+            etag: result.etag.clone(),
+            last_modified: result.last_modified.clone(),
+        };
+        self.store_in_cache(key, &result.url, &result.path, validators)
+            .await;
+        self.update_stats(&result).await;
+        Ok(result)
+    }
+
+    /// Store a completely downloaded file in the cache under `url`, if caching is enabled
+    pub(crate) async fn store_in_cache(
+        &self,
+        url: &str,
+        source_url: &str,
+        path: &std::path::Path,
+        validators: download_cache::CacheValidators,
+    ) {
+        let Some(cache) = self.download_cache.clone() else {
+            return;
+        };
+
+        let key = url.to_string();
+        let source_url = source_url.to_string();
+        let path = path.to_path_buf();
+        let stored =
+            tokio::task::spawn_blocking(move || cache.insert(&key, &source_url, &path, validators))
+                .await;
+        match stored {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => warn!("Failed to cache {}: {}", url, e),
+            Err(e) => warn!("Failed to cache {}: {}", url, e),
+        }
+    }
+
+    /// Download from `urls` and store the result in the cache under `url`
+    async fn download_and_cache(
+        &self,
+        url: &str,
+        urls: &[String],
+        output_path: &std::path::Path,
+        progress_tracker: Option<Arc<ProgressTracker>>,
+    ) -> Result<DownloadResult> {
+        // The mapper already ranked the candidates with the shared tracker
+        let result = self
+            .downloader
+            .download_in_order(urls, output_path, progress_tracker)
+            .await?;
+
+        let validators = download_cache::CacheValidators {
+            etag: result.etag.clone(),
+            last_modified: result.last_modified.clone(),
+        };
+        self.store_in_cache(url, &result.url, &result.path, validators)
+            .await;
+
+        Ok(result)
+    }
+
+    /// Check whether a cache entry can be served without downloading again
+    async fn is_cache_entry_fresh(&self, cache: &DownloadCache, entry: &CacheEntry) -> bool {
+        if self.offline || !cache.revalidate_enabled() {
+            return true;
+        }
+        if entry.validators.is_empty() {
+            return false;
+        }
+
+        match self
+            .downloader
+            .revalidate(
+                &entry.source_url,
+                entry.validators.etag.as_deref(),
+                entry.validators.last_modified.as_deref(),
+            )
+            .await
+        {
+            Ok(fresh) => fresh,
+            Err(e) => {
+                warn!(
+                    "Failed to revalidate {}: {}, serving cached copy",
+                    entry.url, e
+                );
+                true
+            }
+        }
+    }
+
     /// Get download statistics
     pub async fn get_stats(&self) -> TurboCdnStats {
         let mut stats = self.stats.read().await.clone();
@@ -492,12 +1279,16 @@ impl TurboCdn {
     async fn update_stats(&self, result: &DownloadResult) {
         let mut stats = self.stats.write().await;
         stats.total_downloads += 1;
+        let hit = if result.from_cache { 1.0 } else { 0.0 };
+        stats.cache_hit_rate += (hit - stats.cache_hit_rate) / stats.total_downloads as f64;
         if result.size > 0 {
             stats.successful_downloads += 1;
             stats.total_bytes += result.size;
-            // Update moving average speed
-            let alpha = 0.3; // Smoothing factor
-            stats.average_speed = alpha * result.speed + (1.0 - alpha) * stats.average_speed;
+            // Update moving average speed (cache hits say nothing about the network)
+            if !result.from_cache {
+                let alpha = 0.3; // Smoothing factor
+                stats.average_speed = alpha * result.speed + (1.0 - alpha) * stats.average_speed;
+            }
         } else {
             stats.failed_downloads += 1;
         }
@@ -519,6 +1310,128 @@ impl TurboCdn {
     }
 }
 
+/// Where the mapping rules come from, before rule sets are applied
+#[derive(Debug, Clone)]
+struct RuleSources {
+    /// Configuration replaced by [`TurboCdn::reload_config`]
+    config: TurboCdnConfig,
+    /// Rules added with [`TurboCdn::add_rule`], kept across reloads
+    added: Vec<config::UrlMappingRuleConfig>,
+}
+
+impl RuleSources {
+    /// The configuration with the added rules after its own
+    fn merged(&self) -> TurboCdnConfig {
+        let mut config = self.config.clone();
+        config.url_mapping_rules.extend(self.added.iter().cloned());
+        config
+    }
+
+    /// Find a configured or added rule by name
+    fn find_rule(&mut self, name: &str) -> Result<&mut config::UrlMappingRuleConfig> {
+        self.config
+            .url_mapping_rules
+            .iter_mut()
+            .chain(self.added.iter_mut())
+            .find(|rule| rule.name == name)
+            .ok_or_else(|| TurboCdnError::config(format!("Unknown rule '{name}'")))
+    }
+}
+
+/// Rebuild the URL mapper from the base configuration and the rule sets in use
+async fn apply_subscriptions(
+    url_mapper: &RwLock<UrlMapper>,
+    subscriptions: &SubscriptionSet,
+    base: &RwLock<RuleSources>,
+) -> Result<()> {
+    // Holding the base keeps a concurrent reload from being overwritten
+    let base = base.read().await;
+    let mut config = base.merged();
+    subscriptions.apply_to(&mut config);
+    url_mapper.write().await.reconfigure(&config)
+}
+
+/// Replace the base configuration and rebuild the URL mapper, unless the
+/// rules have errors
+async fn reload_rules(
+    url_mapper: &RwLock<UrlMapper>,
+    subscriptions: &SubscriptionSet,
+    base: &RwLock<RuleSources>,
+    config: TurboCdnConfig,
+) -> Result<()> {
+    update_rules(url_mapper, subscriptions, base, |base| {
+        base.config = config;
+        Ok(())
+    })
+    .await
+}
+
+/// Edit the base configuration and rebuild the URL mapper, unless the edit
+/// fails or leaves the rules with errors
+async fn update_rules(
+    url_mapper: &RwLock<UrlMapper>,
+    subscriptions: &SubscriptionSet,
+    base: &RwLock<RuleSources>,
+    edit: impl FnOnce(&mut RuleSources) -> Result<()>,
+) -> Result<()> {
+    let mut base = base.write().await;
+    let mut sources = base.clone();
+    edit(&mut sources)?;
+    let mut merged = sources.merged();
+    subscriptions.apply_to(&mut merged);
+
+    let report = rule_check::check_rules(&merged);
+    if report.has_errors() {
+        let errors: Vec<String> = report
+            .issues
+            .iter()
+            .filter(|issue| issue.severity == rule_check::Severity::Error)
+            .map(|issue| format!("{}: {}", issue.rule, issue.message))
+            .collect();
+        return Err(TurboCdnError::config(format!(
+            "Rejected configuration: {}",
+            errors.join("; ")
+        )));
+    }
+
+    url_mapper.write().await.reconfigure(&merged)?;
+    *base = sources;
+    Ok(())
+}
+
+/// Check the subscriptions for updates until the client is dropped
+async fn refresh_subscriptions_periodically(
+    backend: Arc<dyn HttpBackend>,
+    url_mapper: Weak<RwLock<UrlMapper>>,
+    subscriptions: Arc<SubscriptionSet>,
+    base: Arc<RwLock<RuleSources>>,
+    interval: Duration,
+) {
+    loop {
+        let snapshot = base.read().await.config.clone();
+        let statuses = subscriptions
+            .refresh(backend.as_ref(), &snapshot, false)
+            .await;
+        let Some(mapper) = url_mapper.upgrade() else {
+            break;
+        };
+        if statuses
+            .iter()
+            .any(|s| s.outcome == RefreshOutcome::Updated)
+        {
+            if let Err(e) = apply_subscriptions(&mapper, &subscriptions, &base).await {
+                warn!("Failed to apply rule set update: {}", e);
+            }
+        }
+        drop(mapper);
+
+        tokio::time::sleep(interval).await;
+        if url_mapper.strong_count() == 0 {
+            break;
+        }
+    }
+}
+
 /// Statistics for TurboCdn
 #[derive(Debug, Clone, Default)]
 pub struct TurboCdnStats {
@@ -580,13 +1493,38 @@ impl TurboCdnStats {
 #[derive(Debug, Clone)]
 pub struct TurboCdnBuilder {
     config: TurboCdnConfig,
+    /// Why the layered configuration could not be loaded, reported by `build`
+    config_error: Option<String>,
+    rules: Vec<config::UrlMappingRuleConfig>,
+    resolvers: Vec<Arc<dyn UrlResolver>>,
+    middleware: middleware::MiddlewareStack,
+    http_backend: Option<Arc<dyn HttpBackend>>,
 }
 
 impl TurboCdnBuilder {
-    /// Create a new builder with default configuration
+    /// Create a new builder with the layered configuration
+    ///
+    /// See [`config::ConfigLoader`] for the layers and their order. A layer
+    /// that fails to load is reported by [`Self::build`], unless a
+    /// configuration is given with [`Self::with_config`].
     pub fn new() -> Self {
+        let (config, config_error) = match TurboCdnConfig::load_layered() {
+            Ok(loaded) => (loaded.config, None),
+            Err(TurboCdnError::Config { message }) => {
+                (TurboCdnConfig::load().unwrap_or_default(), Some(message))
+            }
+            Err(e) => (
+                TurboCdnConfig::load().unwrap_or_default(),
+                Some(e.to_string()),
+            ),
+        };
         Self {
-            config: TurboCdnConfig::load().unwrap_or_default(),
+            config,
+            config_error,
+            rules: Vec::new(),
+            resolvers: Vec::new(),
+            middleware: middleware::MiddlewareStack::new(),
+            http_backend: None,
         }
     }
 
@@ -639,6 +1577,12 @@ impl TurboCdnBuilder {
         self
     }
 
+    /// Enable offline mode (serve only from the local download cache)
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.config.general.offline = offline;
+        self
+    }
+
     /// Set custom user agent
     pub fn with_user_agent<S: Into<String>>(mut self, user_agent: S) -> Self {
         self.config.general.user_agent = user_agent.into();
@@ -648,12 +1592,58 @@ impl TurboCdnBuilder {
     /// Use a custom configuration
     pub fn with_config(mut self, config: TurboCdnConfig) -> Self {
         self.config = config;
+        self.config_error = None;
+        self
+    }
+
+    /// Add a URL mapping rule on top of the configured ones
+    ///
+    /// See [`TurboCdn::add_rule`].
+    pub fn with_rule(mut self, rule: config::UrlMappingRuleConfig) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Register a resolver
+    ///
+    /// See [`TurboCdn::add_resolver`].
+    pub fn with_resolver(mut self, resolver: Arc<dyn UrlResolver>) -> Self {
+        self.resolvers.push(resolver);
+        self
+    }
+
+    /// Send requests through middleware, after the middleware added before
+    ///
+    /// Applies to size probes, chunk and stream requests, revalidation,
+    /// speed tests and GitHub API calls.
+    pub fn with_middleware(mut self, middleware: Arc<dyn RequestMiddleware>) -> Self {
+        self.middleware.push(middleware);
+        self
+    }
+
+    /// Send every request with a custom backend instead of a reqwest client
+    ///
+    /// The backend is shared by downloads, speed tests, GitHub API calls,
+    /// region detection and rule set subscriptions.
+    pub fn with_http_backend(mut self, backend: Arc<dyn HttpBackend>) -> Self {
+        self.http_backend = Some(backend);
         self
     }
 
     /// Build the TurboCdn client
     pub async fn build(self) -> Result<TurboCdn> {
-        TurboCdn::with_config(self.config).await
+        if let Some(message) = self.config_error {
+            return Err(TurboCdnError::config(message));
+        }
+        let turbo_cdn =
+            TurboCdn::from_parts(self.config, self.middleware, self.http_backend).await?;
+        for rule in self.rules {
+            turbo_cdn.add_rule(rule).await?;
+        }
+        for resolver in self.resolvers {
+            turbo_cdn.resolvers.register(resolver);
+        }
+        Ok(turbo_cdn)
     }
 }
 
@@ -917,6 +1907,10 @@ mod tests {
             speed: 1024.0,
             url: "https://github.com/owner/repo/releases/download/v1.0.0/file.zip".to_string(),
             resumed: false,
+            etag: None,
+            last_modified: None,
+            from_cache: false,
+            resolved_tag: None,
         };
 
         assert_eq!(result.path, PathBuf::from("/tmp/file.zip"));
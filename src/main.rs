@@ -5,7 +5,7 @@
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use std::io::{self, Write};
 use std::path::PathBuf;
 use turbo_cdn::*;
@@ -21,10 +21,44 @@ struct Cli {
     #[arg(short, long)]
     verbose: bool,
 
+    #[command(flatten)]
+    global: GlobalOptions,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Options shared by every subcommand
+#[derive(Args, Clone)]
+struct GlobalOptions {
+    /// Serve only from the local download cache, never access the network
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Route all requests through a proxy (http://, https:// or socks5://)
+    #[arg(long, global = true, value_name = "URL")]
+    proxy: Option<String>,
+
+    /// Never use this catalog mirror (repeatable)
+    #[arg(long, global = true, value_name = "NAME")]
+    disable_mirror: Vec<String>,
+
+    /// Merge this configuration file over the standard layers (repeatable)
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Vec<PathBuf>,
+
+    /// Override a configuration value, e.g. performance.timeout=60 (repeatable)
+    #[arg(long = "set", global = true, value_name = "KEY=VALUE", value_parser = parse_override)]
+    overrides: Vec<(String, String)>,
+}
+
+/// Parse a `KEY=VALUE` configuration override
+fn parse_override(arg: &str) -> std::result::Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{arg}'"))
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Download file with intelligent method selection (default: smart mode)
@@ -50,8 +84,51 @@ enum Commands {
         /// URL to optimize
         url: String,
     },
+    /// Explain why a URL is mapped to its CDN candidates
+    Explain {
+        /// URL to explain
+        url: String,
+        /// Print the explanation as JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Show performance statistics
     Stats,
+    /// Run a local caching proxy server for other tools
+    #[cfg(feature = "server")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = turbo_cdn::server::DEFAULT_LISTEN_ADDR)]
+        listen: std::net::SocketAddr,
+        /// Only answer /resolve requests (mirror lists), never download
+        #[arg(long)]
+        resolve_only: bool,
+    },
+    /// Manage the local download cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    /// Inspect the mirror catalog
+    Mirrors {
+        #[command(subcommand)]
+        command: MirrorCommands,
+    },
+    /// Inspect the merged configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// Validate the URL mapping rules
+    Rules {
+        #[command(subcommand)]
+        command: RuleCommands,
+    },
+    /// Manage remote rule-set subscriptions
+    Subscriptions {
+        #[command(subcommand)]
+        command: SubscriptionCommands,
+    },
     /// Update turbo-cdn to the latest version
     #[cfg(feature = "self-update")]
     #[command(alias = "upgrade")]
@@ -62,6 +139,55 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// List cached downloads
+    List,
+    /// Evict least recently used entries until the cache fits the size limit
+    Prune {
+        /// Size limit in bytes (defaults to the configured limit)
+        #[arg(long)]
+        max_size: Option<u64>,
+    },
+    /// Remove all cached downloads
+    Clear,
+    /// Show cache usage statistics
+    Stats,
+}
+
+#[derive(Subcommand)]
+enum MirrorCommands {
+    /// List catalog mirrors and whether they are used
+    List,
+    /// Probe the health check URL of every enabled mirror
+    Check,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the effective configuration
+    Show {
+        /// Show which layer each value comes from
+        #[arg(long)]
+        origin: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum RuleCommands {
+    /// Compile every rule, run its examples and flag shadowed rules
+    #[command(alias = "lint")]
+    Check,
+}
+
+#[derive(Subcommand)]
+enum SubscriptionCommands {
+    /// List subscriptions and the rule set version in use
+    List,
+    /// Fetch every subscribed rule set now
+    Update,
+}
+
 #[tokio::main]
 async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
@@ -74,7 +200,7 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
 
     match cli.command {
         Commands::GetOptimalUrl { url } => {
-            handle_optimize_command(&url, cli.verbose).await?;
+            handle_optimize_command(&url, cli.verbose, &cli.global).await?;
         }
         Commands::Download {
             url,
@@ -85,12 +211,45 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
         } => {
             // Determine download mode: smart is default unless explicitly disabled
             let smart_mode = !no_smart && !no_cdn && !force_cdn;
-            handle_download_command(&url, output, cli.verbose, no_cdn, force_cdn, smart_mode)
-                .await?;
+            handle_download_command(
+                &url,
+                output,
+                cli.verbose,
+                &cli.global,
+                no_cdn,
+                force_cdn,
+                smart_mode,
+            )
+            .await?;
+        }
+        Commands::Explain { url, json } => {
+            handle_explain_command(&url, json, &cli.global).await?;
         }
         Commands::Stats => {
             handle_stats_command().await?;
         }
+        #[cfg(feature = "server")]
+        Commands::Serve {
+            listen,
+            resolve_only,
+        } => {
+            handle_serve_command(listen, resolve_only, &cli.global).await?;
+        }
+        Commands::Cache { command } => {
+            handle_cache_command(command, &cli.global)?;
+        }
+        Commands::Mirrors { command } => {
+            handle_mirrors_command(command, &cli.global).await?;
+        }
+        Commands::Config { command } => {
+            handle_config_command(command, &cli.global)?;
+        }
+        Commands::Rules { command } => {
+            handle_rules_command(command, &cli.global)?;
+        }
+        Commands::Subscriptions { command } => {
+            handle_subscriptions_command(command, &cli.global).await?;
+        }
         #[cfg(feature = "self-update")]
         Commands::SelfUpdate { check } => {
             handle_self_update_command(check).await?;
@@ -100,9 +259,59 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Load the layered configuration and apply the global command line flags
+fn load_layered_config(global: &GlobalOptions) -> turbo_cdn::Result<config::LoadedConfig> {
+    let mut loader = config::ConfigLoader::standard();
+    for path in &global.config {
+        loader = loader.with_file(path);
+    }
+    for (key, value) in &global.overrides {
+        loader = loader.with_override(key, value);
+    }
+    let mut loaded = loader.load()?;
+
+    let config = &mut loaded.config;
+    let offline = global.offline && !config.general.offline;
+    config.general.offline |= global.offline;
+    if let Some(proxy) = &global.proxy {
+        config.network.proxy.set_all(proxy);
+    }
+    config
+        .mapping
+        .disabled_mirrors
+        .extend(global.disable_mirror.iter().cloned());
+
+    // The command line tool caches downloads unless a layer decides otherwise
+    if *loaded.origin("cache.enabled") == config::ConfigOrigin::BuiltIn {
+        loaded.config.cache.enabled = true;
+        loaded.set_origin("cache.enabled", config::ConfigOrigin::Cli);
+    }
+    if offline {
+        loaded.set_origin("general.offline", config::ConfigOrigin::Cli);
+    }
+    if global.proxy.is_some() {
+        loaded.set_origin("network.proxy", config::ConfigOrigin::Cli);
+    }
+    if !global.disable_mirror.is_empty() {
+        loaded.set_origin("mapping.disabled_mirrors", config::ConfigOrigin::Cli);
+    }
+    Ok(loaded)
+}
+
+/// Load the configuration with the global command line flags applied
+fn load_config(global: &GlobalOptions) -> turbo_cdn::Result<TurboCdnConfig> {
+    load_layered_config(global).map(|loaded| loaded.config)
+}
+
+/// Create a TurboCdn client honoring the global command line flags
+async fn create_turbo_cdn(global: &GlobalOptions) -> turbo_cdn::Result<TurboCdn> {
+    TurboCdn::with_config(load_config(global)?).await
+}
+
 async fn handle_optimize_command(
     url: &str,
     verbose: bool,
+    global: &GlobalOptions,
 ) -> std::result::Result<(), Box<dyn std::error::Error>> {
     if verbose {
         println!("🔍 Turbo CDN - Finding optimal URL");
@@ -116,7 +325,7 @@ async fn handle_optimize_command(
     }
 
     // Create a TurboCdn instance
-    let turbo_cdn = TurboCdn::new().await?;
+    let turbo_cdn = create_turbo_cdn(global).await?;
 
     // Get optimal URL
     match turbo_cdn.get_optimal_url(url).await {
@@ -138,6 +347,12 @@ async fn handle_optimize_command(
                     println!("   🚀 This should provide faster download speeds");
                 }
             }
+
+            if verbose {
+                if let Ok(resolution) = turbo_cdn.resolve_url(url).await {
+                    print_policy_report(&resolution.policy);
+                }
+            }
         }
         Err(e) => {
             if !verbose {
@@ -152,10 +367,98 @@ async fn handle_optimize_command(
     Ok(())
 }
 
+async fn handle_explain_command(
+    url: &str,
+    json: bool,
+    global: &GlobalOptions,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let turbo_cdn = create_turbo_cdn(global).await?;
+    let explanation = turbo_cdn.explain_url(url).await;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&explanation)?);
+        return Ok(());
+    }
+
+    println!("🔎 {url}");
+    if let Some(tag) = &explanation.resolved_tag {
+        println!("🏷️  Latest release: {tag}");
+    }
+    println!(
+        "🌍 Region: {} ({})",
+        explanation.region, explanation.region_source
+    );
+    println!("📐 Rule mode: {:?}", explanation.rule_mode);
+
+    println!();
+    if explanation.offline {
+        println!("📜 Rules: not applied, offline mode uses the original URL");
+    } else {
+        println!("📜 Rules:");
+        for rule in &explanation.rules {
+            let icon = match rule.outcome {
+                RuleOutcome::Used => "✅",
+                RuleOutcome::PolicyDenied { .. } => "🛡️ ",
+                _ => "  ",
+            };
+            println!(
+                "   {icon} [{}] {}: {}",
+                rule.priority, rule.name, rule.outcome
+            );
+        }
+    }
+
+    println!();
+    let cache = if explanation.cache_hit { "hit" } else { "miss" };
+    println!("🔗 Candidates (mapping cache {cache}):");
+    for (position, candidate) in explanation.candidates.iter().enumerate() {
+        println!("   {}. {}", position + 1, candidate.url);
+        let rule = candidate.rule.as_deref().unwrap_or("original URL");
+        let server = match candidate.performance {
+            Some(performance) => format!(
+                "server score {performance:.2}, {:.0}% success, {}/s over {} attempts",
+                candidate.success_rate.unwrap_or(0.0) * 100.0,
+                format_bytes(candidate.average_speed.unwrap_or(0.0) as u64),
+                candidate.attempts
+            ),
+            None => "server not used yet".to_string(),
+        };
+        println!(
+            "      {rule}, weight {}, {server} → score {:.3}",
+            candidate.weight, candidate.score
+        );
+    }
+    if explanation.cache_hit {
+        println!();
+        println!("🗄️  Cached order returned by the mapper:");
+        for (position, url) in explanation.urls.iter().enumerate() {
+            println!("   {}. {url}", position + 1);
+        }
+    }
+
+    if !explanation.offline {
+        print_policy_report(&explanation.policy);
+    }
+    Ok(())
+}
+
+/// Print the mirror rewriting policy decision for a URL
+fn print_policy_report(report: &PolicyReport) {
+    println!();
+    match &report.reason {
+        Some(reason) => println!("🛡️  Policy: mirror rewriting denied ({reason})"),
+        None => println!("🛡️  Policy: mirror rewriting allowed"),
+    }
+    for mirror in &report.rejected_mirrors {
+        println!("   ✗ {} ({})", mirror.url, mirror.reason);
+    }
+}
+
 async fn handle_download_command(
     url: &str,
     output_path: Option<PathBuf>,
     verbose: bool,
+    global: &GlobalOptions,
     no_cdn: bool,
     force_cdn: bool,
     smart: bool,
@@ -206,7 +509,7 @@ async fn handle_download_command(
     }
 
     // Create a TurboCdn instance
-    let turbo_cdn = TurboCdn::new().await?;
+    let turbo_cdn = create_turbo_cdn(global).await?;
     if verbose {
         if smart {
             println!("✓ TurboCdn initialized in smart mode (auto-selecting best method)");
@@ -255,6 +558,9 @@ async fn handle_download_command(
             println!("🎉 Download completed successfully!");
             let path_display = result.path.display();
             println!("   📁 {path_display}");
+            if let Some(tag) = &result.resolved_tag {
+                println!("   🏷️  Latest release: {tag}");
+            }
             println!(
                 "   📊 {:.2} MB ({:.2} MB/s)",
                 result.size as f64 / 1024.0 / 1024.0,
@@ -268,6 +574,9 @@ async fn handle_download_command(
                 if result.resumed {
                     println!("   🔄 Download was resumed from previous attempt");
                 }
+                if result.from_cache {
+                    println!("   📦 Served from local download cache");
+                }
 
                 // Show performance tip
                 if result.speed > 5.0 * 1024.0 * 1024.0 {
@@ -328,6 +637,292 @@ async fn handle_stats_command() -> std::result::Result<(), Box<dyn std::error::E
     Ok(())
 }
 
+#[cfg(feature = "server")]
+async fn handle_serve_command(
+    listen: std::net::SocketAddr,
+    resolve_only: bool,
+    global: &GlobalOptions,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let turbo_cdn = std::sync::Arc::new(create_turbo_cdn(global).await?);
+
+    println!("🚀 Turbo CDN - Local Proxy Server");
+    println!("================================");
+    println!("   Listening on http://{listen}");
+    println!("   Resolve: curl \"http://{listen}/resolve?url=<url>\"");
+    if !resolve_only {
+        println!("   Fetch:   curl \"http://{listen}/fetch?url=<url>\"");
+        println!("   Proxy:   export http_proxy=http://{listen}");
+    }
+    println!();
+
+    turbo_cdn::server::ProxyServer::new(turbo_cdn)
+        .with_resolve_only(resolve_only)
+        .run(listen)
+        .await?;
+
+    Ok(())
+}
+
+fn handle_cache_command(
+    command: CacheCommands,
+    global: &GlobalOptions,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let config = load_config(global)?;
+    let cache = DownloadCache::open(&config.cache)?;
+
+    match command {
+        CacheCommands::List => {
+            let entries = cache.list();
+            if entries.is_empty() {
+                println!("ℹ️  Download cache is empty");
+            }
+            for entry in entries {
+                println!("📦 {}", entry.url);
+                println!(
+                    "   {} | sha256 {} | last used {}",
+                    format_bytes(entry.size),
+                    &entry.sha256[..12.min(entry.sha256.len())],
+                    entry.last_accessed.format("%Y-%m-%d %H:%M:%S")
+                );
+            }
+        }
+        CacheCommands::Prune { max_size } => {
+            let limit = max_size.unwrap_or(config.cache.max_size);
+            let report = cache.prune(limit)?;
+            println!(
+                "🧹 Pruned {} entries ({} objects, {} freed)",
+                report.removed_entries,
+                report.removed_objects,
+                format_bytes(report.freed_bytes)
+            );
+        }
+        CacheCommands::Clear => {
+            let report = cache.clear()?;
+            println!(
+                "🧹 Cleared {} entries ({} freed)",
+                report.removed_entries,
+                format_bytes(report.freed_bytes)
+            );
+        }
+        CacheCommands::Stats => {
+            let stats = cache.stats();
+            println!("📊 Turbo CDN - Download Cache");
+            println!("=============================");
+            println!("   📁 {}", stats.directory.display());
+            println!("   Entries: {}", stats.entries);
+            println!("   Objects: {}", stats.objects);
+            println!(
+                "   Size: {} / {}",
+                format_bytes(stats.total_size),
+                format_bytes(stats.max_size)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_mirrors_command(
+    command: MirrorCommands,
+    global: &GlobalOptions,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let config = load_config(global)?;
+    let catalog = MirrorCatalog::new(&config.mirrors, &config.mapping.disabled_mirrors)?;
+    if catalog.is_empty() {
+        println!("ℹ️  No mirrors in the catalog");
+        return Ok(());
+    }
+
+    match command {
+        MirrorCommands::List => {
+            for mirror in catalog.iter() {
+                let status = if catalog.is_enabled(&mirror.name) {
+                    "✅"
+                } else {
+                    "⛔"
+                };
+                println!("{status} {} ({:?})", mirror.name, mirror.style);
+                println!("   {}", mirror.url);
+                if !mirror.regions.is_empty() {
+                    println!("   Regions: {:?}", mirror.regions);
+                }
+            }
+        }
+        MirrorCommands::Check => {
+            if config.general.offline {
+                return Err(TurboCdnError::offline("mirror health checks").into());
+            }
+            let backend = ReqwestBackend::from_config(&config)?;
+            let timeout = std::time::Duration::from_secs(config.performance.timeout);
+            let checks = catalog
+                .iter()
+                .filter(|mirror| catalog.is_enabled(&mirror.name))
+                .map(|mirror| mirror::check_health(&backend, timeout, mirror));
+
+            for health in futures::future::join_all(checks).await {
+                let outcome = match (health.status, &health.error) {
+                    (Some(status), _) => format!("HTTP {status}"),
+                    (None, Some(error)) => error.clone(),
+                    (None, None) => "no response".to_string(),
+                };
+                let icon = if health.is_healthy() { "✅" } else { "❌" };
+                println!(
+                    "{icon} {} {} ({} ms)",
+                    health.name,
+                    outcome,
+                    health.latency.as_millis()
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_config_command(
+    command: ConfigCommands,
+    global: &GlobalOptions,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let loaded = load_layered_config(global)?;
+
+    match command {
+        ConfigCommands::Show { origin: false } => {
+            print!("{}", toml::to_string(&loaded.config)?);
+        }
+        ConfigCommands::Show { origin: true } => {
+            let layers: Vec<String> = std::iter::once("built-in".to_string())
+                .chain(loaded.files.iter().map(|path| path.display().to_string()))
+                .collect();
+            println!("# Layers: {}", layers.join(" → "));
+            for entry in loaded.entries()? {
+                println!("{} = {}  # {}", entry.key, entry.value, entry.origin);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_rules_command(
+    command: RuleCommands,
+    global: &GlobalOptions,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let config = load_config(global)?;
+
+    match command {
+        RuleCommands::Check => {
+            let report = rule_check::check_rules(&config);
+
+            for issue in report.issues_for("[[mirrors]]") {
+                println!("❌ [[mirrors]]: {}", issue.message);
+            }
+            for rule in &config.url_mapping_rules {
+                let issues: Vec<_> = report.issues_for(&rule.name).collect();
+                let icon = if issues
+                    .iter()
+                    .any(|i| i.severity == rule_check::Severity::Error)
+                {
+                    "❌"
+                } else if !issues.is_empty() {
+                    "⚠️ "
+                } else {
+                    "✅"
+                };
+                println!("{icon} {} ({} examples)", rule.name, rule.examples.len());
+                for issue in issues {
+                    println!("   {}", issue.message);
+                }
+            }
+
+            let errors = report
+                .issues
+                .iter()
+                .filter(|i| i.severity == rule_check::Severity::Error)
+                .count();
+            println!(
+                "\n📋 {} rules, {} examples, {} errors, {} warnings",
+                report.rules,
+                report.examples,
+                errors,
+                report.issues.len() - errors
+            );
+            if report.has_errors() {
+                return Err(TurboCdnError::config(format!("{errors} rule errors")).into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_subscriptions_command(
+    command: SubscriptionCommands,
+    global: &GlobalOptions,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    match command {
+        SubscriptionCommands::List => {
+            let subscriptions = SubscriptionSet::new(&load_config(global)?)?;
+            if subscriptions.is_empty() {
+                println!("No subscriptions configured");
+            }
+            for subscription in subscriptions.subscriptions() {
+                let version = subscriptions
+                    .version(subscription.name())
+                    .map_or_else(|| "not fetched".to_string(), |v| format!("v{v}"));
+                println!("📦 {} ({})", subscription.name(), version);
+                println!("   {}", subscription.url());
+            }
+        }
+        SubscriptionCommands::Update => {
+            let turbo_cdn = create_turbo_cdn(global).await?;
+            let statuses = turbo_cdn.refresh_subscriptions().await?;
+            if statuses.is_empty() {
+                println!("No subscriptions configured");
+            }
+            let mut failed = 0;
+            for status in &statuses {
+                let version = status
+                    .version
+                    .map_or_else(String::new, |v| format!(" v{v}"));
+                match &status.outcome {
+                    RefreshOutcome::Updated => println!("✅ {}: updated to{version}", status.name),
+                    RefreshOutcome::Unchanged | RefreshOutcome::NotDue => {
+                        println!("✅ {}: up to date{version}", status.name)
+                    }
+                    RefreshOutcome::KeptLastGood { error } => {
+                        failed += 1;
+                        println!("⚠️  {}: kept{version}, {error}", status.name);
+                    }
+                    RefreshOutcome::Unavailable { error } => {
+                        failed += 1;
+                        println!("❌ {}: {error}", status.name);
+                    }
+                }
+            }
+            if failed > 0 {
+                return Err(TurboCdnError::source_validation(format!(
+                    "{failed} rule set updates rejected"
+                ))
+                .into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 * 1024 {
+        format!("{:.2} GB", bytes as f64 / 1024.0 / 1024.0 / 1024.0)
+    } else if bytes >= 1024 * 1024 {
+        format!("{:.2} MB", bytes as f64 / 1024.0 / 1024.0)
+    } else if bytes >= 1024 {
+        format!("{:.2} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{bytes} B")
+    }
+}
+
 #[cfg(feature = "self-update")]
 async fn handle_self_update_command(
     check_only: bool,
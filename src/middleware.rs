@@ -0,0 +1,132 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Request middleware
+//!
+//! A [`RequestMiddleware`] sees every request the client sends for a
+//! download (size probes, chunk and stream requests, revalidation, speed
+//! tests and GitHub API calls) before it goes out, and its response when it
+//! comes back. Use it to add headers per host, sign requests for object
+//! stores or audit outbound URLs.
+
+use crate::error::{Result, TurboCdnError};
+use crate::http_backend::HttpBackend;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// What a request is sent for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequestKind {
+    /// `HEAD` request learning the size and range support of a download
+    Probe,
+    /// Ranged `GET` of one chunk of a concurrent download
+    Chunk,
+    /// `GET` of a whole file, or of its remainder when resuming
+    Download,
+    /// Conditional `HEAD` checking whether a cached file is still current
+    Revalidate,
+    /// Small ranged `GET` comparing the speed of download methods
+    SpeedTest,
+    /// GitHub or jsDelivr API call
+    GitHubApi,
+}
+
+/// Hooks run around outgoing requests
+///
+/// Both hooks default to doing nothing. Middleware runs in registration
+/// order.
+///
+/// # Example
+/// ```rust
+/// use async_trait::async_trait;
+/// use turbo_cdn::middleware::{RequestKind, RequestMiddleware};
+///
+/// struct TraceId;
+///
+/// #[async_trait]
+/// impl RequestMiddleware for TraceId {
+///     async fn on_request(
+///         &self,
+///         request: &mut reqwest::Request,
+///         _kind: RequestKind,
+///     ) -> turbo_cdn::Result<()> {
+///         if request.url().host_str() == Some("artifacts.internal") {
+///             request
+///                 .headers_mut()
+///                 .insert("x-trace-id", "build-42".parse().unwrap());
+///         }
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait RequestMiddleware: Send + Sync {
+    /// Inspect or modify a request before it is sent
+    ///
+    /// Returning an error fails the request without sending it.
+    async fn on_request(&self, _request: &mut reqwest::Request, _kind: RequestKind) -> Result<()> {
+        Ok(())
+    }
+
+    /// Inspect the response to a request, before its body is read
+    async fn on_response(&self, _response: &reqwest::Response, _kind: RequestKind) {}
+}
+
+/// Middleware applied to the requests of a client, in registration order
+#[derive(Clone, Default)]
+pub struct MiddlewareStack {
+    middleware: Vec<Arc<dyn RequestMiddleware>>,
+}
+
+impl MiddlewareStack {
+    /// Create an empty stack
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add middleware after the registered ones
+    pub fn push(&mut self, middleware: Arc<dyn RequestMiddleware>) {
+        self.middleware.push(middleware);
+    }
+
+    /// Number of registered middleware
+    pub fn len(&self) -> usize {
+        self.middleware.len()
+    }
+
+    /// Whether no middleware is registered
+    pub fn is_empty(&self) -> bool {
+        self.middleware.is_empty()
+    }
+
+    /// Build a request, run it through the middleware and send it with `backend`
+    ///
+    /// Transport errors are converted with `map_err`, so callers report
+    /// them as they did before middleware existed.
+    pub async fn send(
+        &self,
+        backend: &dyn HttpBackend,
+        request: reqwest::RequestBuilder,
+        kind: RequestKind,
+        map_err: impl Fn(reqwest::Error) -> TurboCdnError,
+    ) -> Result<reqwest::Response> {
+        let mut request = request.build().map_err(&map_err)?;
+        for middleware in &self.middleware {
+            middleware.on_request(&mut request, kind).await?;
+        }
+
+        let response = crate::http_backend::execute(backend, request, map_err).await?;
+        for middleware in &self.middleware {
+            middleware.on_response(&response, kind).await;
+        }
+        Ok(response)
+    }
+}
+
+impl std::fmt::Debug for MiddlewareStack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MiddlewareStack")
+            .field("middleware", &self.middleware.len())
+            .finish()
+    }
+}
@@ -0,0 +1,365 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Named mirror catalog
+//!
+//! Mirrors are declared once under `[[mirrors]]` and referenced by name from
+//! rule replacements (`{ mirror = "ghproxy" }`), so a mirror that goes away
+//! is disabled in one place instead of in every rule.
+//!
+//! Mirror capabilities let the downloader skip mirrors that can't serve a
+//! request, e.g. files over a size limit, before spending retries on them.
+
+use crate::config::{MirrorCapabilities, MirrorConfig, MirrorStyle, Region};
+use crate::error::{Result, TurboCdnError};
+use crate::http_backend::HttpBackend;
+use crate::url_template::UrlTemplate;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// A catalog shared by the URL mapper and the downloader, swapped whenever
+/// the mapper is rebuilt
+pub type SharedMirrorCatalog = Arc<RwLock<Arc<MirrorCatalog>>>;
+
+/// Mirrors available to URL mapping rules
+#[derive(Debug, Clone, Default)]
+pub struct MirrorCatalog {
+    mirrors: Vec<MirrorConfig>,
+    index: HashMap<String, usize>,
+    disabled: HashSet<String>,
+}
+
+impl MirrorCatalog {
+    /// Build the catalog, `disabled` names are skipped regardless of their
+    /// `enabled` flag
+    pub fn new(mirrors: &[MirrorConfig], disabled: &[String]) -> Result<Self> {
+        let mut index = HashMap::new();
+        for (position, mirror) in mirrors.iter().enumerate() {
+            if index.insert(mirror.name.clone(), position).is_some() {
+                return Err(TurboCdnError::config(format!(
+                    "Duplicate mirror name '{}'",
+                    mirror.name
+                )));
+            }
+            mirror_template(mirror)?;
+        }
+
+        for name in disabled {
+            if !index.contains_key(name) {
+                warn!("Cannot disable unknown mirror '{}'", name);
+            }
+        }
+
+        Ok(Self {
+            mirrors: mirrors.to_vec(),
+            index,
+            disabled: disabled.iter().cloned().collect(),
+        })
+    }
+
+    /// Look up a mirror by name
+    pub fn get(&self, name: &str) -> Option<&MirrorConfig> {
+        self.index
+            .get(name)
+            .map(|&position| &self.mirrors[position])
+    }
+
+    /// All mirrors in declaration order
+    pub fn iter(&self) -> impl Iterator<Item = &MirrorConfig> {
+        self.mirrors.iter()
+    }
+
+    /// Number of mirrors in the catalog
+    pub fn len(&self) -> usize {
+        self.mirrors.len()
+    }
+
+    /// Whether the catalog is empty
+    pub fn is_empty(&self) -> bool {
+        self.mirrors.is_empty()
+    }
+
+    /// Whether a mirror is enabled and not disabled by name
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.get(name)
+            .is_some_and(|mirror| mirror.enabled && !self.disabled.contains(name))
+    }
+
+    /// Whether a mirror is enabled and serves the region
+    pub fn serves(&self, name: &str, region: &Region) -> bool {
+        self.is_enabled(name)
+            && self
+                .get(name)
+                .is_some_and(|mirror| mirror.regions.is_empty() || mirror.regions.contains(region))
+    }
+
+    /// Find the mirror a candidate URL was generated for
+    ///
+    /// The longest matching mirror URL wins.
+    pub fn find_by_url(&self, url: &str) -> Option<&MirrorConfig> {
+        self.mirrors
+            .iter()
+            .filter(|mirror| {
+                let base = mirror.url.trim_end_matches('/');
+                url.strip_prefix(base)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?', '#']))
+            })
+            .max_by_key(|mirror| mirror.url.trim_end_matches('/').len())
+    }
+
+    /// Replacement template for a mirror
+    pub fn template(&self, name: &str) -> Result<UrlTemplate> {
+        let mirror = self
+            .get(name)
+            .ok_or_else(|| TurboCdnError::config(format!("Unknown mirror '{name}'")))?;
+        mirror_template(mirror)
+    }
+}
+
+/// What a download needs from the server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestShape {
+    /// File size, when known
+    pub file_size: Option<u64>,
+    /// Whether the download continues a partial file with a `Range` request
+    pub resume: bool,
+    /// Whether the URL asks for the latest release instead of a tag
+    pub latest_release: bool,
+}
+
+impl RequestShape {
+    /// Shape of a request for `url`
+    pub fn for_url(url: &str, file_size: Option<u64>, resume: bool) -> Self {
+        Self {
+            file_size,
+            resume,
+            latest_release: url.contains("/releases/latest/"),
+        }
+    }
+}
+
+impl MirrorCapabilities {
+    /// Why a mirror with these capabilities can't serve the request, if it can't
+    pub fn incompatibility(&self, shape: &RequestShape) -> Option<String> {
+        if let (Some(limit), Some(size)) = (self.max_file_size, shape.file_size) {
+            if size > limit {
+                return Some(format!("file size {size} exceeds limit of {limit} bytes"));
+            }
+        }
+        if shape.resume && !self.range_requests {
+            return Some("range requests unsupported, cannot resume".to_string());
+        }
+        if shape.latest_release && !self.latest_release {
+            return Some("cannot resolve releases/latest".to_string());
+        }
+        None
+    }
+}
+
+/// Result of probing a mirror's health check URL
+#[derive(Debug, Clone)]
+pub struct MirrorHealth {
+    /// Mirror name
+    pub name: String,
+    /// URL that was probed
+    pub url: String,
+    /// HTTP status, `None` when the request failed
+    pub status: Option<u16>,
+    /// Time until the response headers arrived
+    pub latency: Duration,
+    /// Request error, if any
+    pub error: Option<String>,
+}
+
+impl MirrorHealth {
+    /// Whether the mirror answered without an error status
+    pub fn is_healthy(&self) -> bool {
+        self.status.is_some_and(|status| status < 400)
+    }
+}
+
+/// Probe a mirror with a `HEAD` request to its health check URL
+pub async fn check_health(
+    backend: &dyn HttpBackend,
+    timeout: Duration,
+    mirror: &MirrorConfig,
+) -> MirrorHealth {
+    let url = mirror
+        .health_check_url
+        .clone()
+        .unwrap_or_else(|| mirror.url.clone());
+    let start = Instant::now();
+    let response = match reqwest::Url::parse(&url) {
+        Ok(parsed) => {
+            let mut request = reqwest::Request::new(reqwest::Method::HEAD, parsed);
+            *request.timeout_mut() = Some(timeout);
+            backend.execute(request).await
+        }
+        Err(e) => Err(TurboCdnError::config(format!("Invalid URL {url}: {e}"))),
+    };
+
+    MirrorHealth {
+        name: mirror.name.clone(),
+        status: response.as_ref().ok().map(|r| r.status().as_u16()),
+        error: response.err().map(|e| e.to_string()),
+        latency: start.elapsed(),
+        url,
+    }
+}
+
+/// Build the replacement template a mirror stands for
+fn mirror_template(mirror: &MirrorConfig) -> Result<UrlTemplate> {
+    let base = mirror.url.replace('$', "$$");
+    let source = match mirror.style {
+        MirrorStyle::PrefixProxy if base.ends_with('/') => format!("{base}${{url}}"),
+        MirrorStyle::PrefixProxy => format!("{base}/${{url}}"),
+        MirrorStyle::PathRewrite => format!("{}${{path}}", base.trim_end_matches('/')),
+    };
+
+    UrlTemplate::parse(&source)
+        .map_err(|e| TurboCdnError::config(format!("Invalid mirror '{}': {e}", mirror.name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mirror(name: &str, url: &str, style: MirrorStyle) -> MirrorConfig {
+        MirrorConfig {
+            name: name.to_string(),
+            url: url.to_string(),
+            style,
+            regions: vec![],
+            capabilities: Default::default(),
+            health_check_url: None,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_mirror_templates() {
+        let catalog = MirrorCatalog::new(
+            &[
+                mirror("proxy", "https://ghproxy.net/", MirrorStyle::PrefixProxy),
+                mirror("bare", "https://gh-proxy.com", MirrorStyle::PrefixProxy),
+                mirror("rewrite", "https://bgithub.xyz/", MirrorStyle::PathRewrite),
+            ],
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(
+            catalog.template("proxy").unwrap().as_str(),
+            "https://ghproxy.net/${url}"
+        );
+        assert_eq!(
+            catalog.template("bare").unwrap().as_str(),
+            "https://gh-proxy.com/${url}"
+        );
+        assert_eq!(
+            catalog.template("rewrite").unwrap().as_str(),
+            "https://bgithub.xyz${path}"
+        );
+        assert!(catalog.template("missing").is_err());
+    }
+
+    #[test]
+    fn test_disabled_and_regional_mirrors() {
+        let mut off = mirror("off", "https://off.example/", MirrorStyle::PrefixProxy);
+        off.enabled = false;
+        let mut china = mirror("china", "https://cn.example/", MirrorStyle::PrefixProxy);
+        china.regions = vec![Region::China];
+        let catalog = MirrorCatalog::new(
+            &[
+                off,
+                china,
+                mirror("on", "https://on.example/", MirrorStyle::PrefixProxy),
+                mirror(
+                    "killed",
+                    "https://killed.example/",
+                    MirrorStyle::PrefixProxy,
+                ),
+            ],
+            &["killed".to_string()],
+        )
+        .unwrap();
+
+        assert!(!catalog.is_enabled("off"));
+        assert!(!catalog.is_enabled("killed"));
+        assert!(!catalog.is_enabled("missing"));
+        assert!(catalog.serves("on", &Region::Europe));
+        assert!(catalog.serves("china", &Region::China));
+        assert!(!catalog.serves("china", &Region::Europe));
+    }
+
+    #[test]
+    fn test_find_by_url() {
+        let catalog = MirrorCatalog::new(
+            &[
+                mirror("cc", "https://ghproxy.cc/", MirrorStyle::PrefixProxy),
+                mirror(
+                    "fastly",
+                    "https://fastly.jsdelivr.net",
+                    MirrorStyle::PathRewrite,
+                ),
+                mirror(
+                    "fastly-gh",
+                    "https://fastly.jsdelivr.net/gh",
+                    MirrorStyle::PathRewrite,
+                ),
+            ],
+            &[],
+        )
+        .unwrap();
+
+        let name = |url| catalog.find_by_url(url).map(|m| m.name.as_str());
+        assert_eq!(name("https://ghproxy.cc/https://github.com/a"), Some("cc"));
+        assert_eq!(name("https://ghproxy.cccc/https://github.com/a"), None);
+        assert_eq!(name("https://fastly.jsdelivr.net/npm/x"), Some("fastly"));
+        assert_eq!(
+            name("https://fastly.jsdelivr.net/gh/a/b"),
+            Some("fastly-gh")
+        );
+        assert_eq!(name("https://github.com/a"), None);
+    }
+
+    #[test]
+    fn test_capability_incompatibility() {
+        let limited = MirrorCapabilities {
+            range_requests: false,
+            latest_release: false,
+            max_file_size: Some(100),
+        };
+        let url = "https://m.example/https://github.com/o/r/releases/download/v1/f";
+        let latest = "https://m.example/https://github.com/o/r/releases/latest/download/f";
+
+        assert!(limited
+            .incompatibility(&RequestShape::for_url(url, Some(100), false))
+            .is_none());
+        assert!(limited
+            .incompatibility(&RequestShape::for_url(url, None, false))
+            .is_none());
+        assert!(limited
+            .incompatibility(&RequestShape::for_url(url, Some(101), false))
+            .is_some());
+        assert!(limited
+            .incompatibility(&RequestShape::for_url(url, Some(10), true))
+            .is_some());
+        assert!(limited
+            .incompatibility(&RequestShape::for_url(latest, Some(10), false))
+            .is_some());
+
+        let capable = MirrorCapabilities::default();
+        assert!(capable
+            .incompatibility(&RequestShape::for_url(latest, Some(u64::MAX), true))
+            .is_none());
+    }
+
+    #[test]
+    fn test_duplicate_names_rejected() {
+        let duplicate = mirror("a", "https://a.example/", MirrorStyle::PrefixProxy);
+        assert!(MirrorCatalog::new(&[duplicate.clone(), duplicate], &[]).is_err());
+    }
+}
@@ -10,15 +10,66 @@ use crate::error::{Result, TurboCdnError};
 use memmap2::{MmapMut, MmapOptions};
 use std::fs::{File, OpenOptions};
 use std::io::{Seek, SeekFrom, Write};
+use std::ops::Range;
 use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tracing::{debug, info, warn};
 
+/// A writable mapping that chunk writers fill concurrently
+///
+/// Writes go through a raw pointer so chunks never wait for each other.
+/// Each write first claims its byte range; a write overlapping one in
+/// progress is rejected, so no two threads ever copy into the same bytes.
+struct SharedMmap {
+    mmap: MmapMut,
+    ptr: *mut u8,
+    in_flight: std::sync::Mutex<Vec<Range<usize>>>,
+}
+
+// SAFETY: `ptr` points into the mapping owned by the same struct and lives
+// as long as it. Copies only happen into ranges claimed through
+// `SharedMmap::claim`, which never hands out overlapping ranges at once.
+unsafe impl Send for SharedMmap {}
+unsafe impl Sync for SharedMmap {}
+
+impl SharedMmap {
+    /// Reserve `range` for one write, failing if a write in progress overlaps it
+    fn claim(&self, range: Range<usize>) -> Result<RangeClaim<'_>> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if let Some(busy) = in_flight
+            .iter()
+            .find(|busy| busy.start < range.end && range.start < busy.end)
+        {
+            return Err(TurboCdnError::internal(format!(
+                "Write to bytes {}..{} overlaps a write in progress to {}..{}",
+                range.start, range.end, busy.start, busy.end
+            )));
+        }
+        in_flight.push(range.clone());
+        Ok(RangeClaim { mmap: self, range })
+    }
+}
+
+/// A byte range reserved for a write in progress, released on drop
+struct RangeClaim<'a> {
+    mmap: &'a SharedMmap,
+    range: Range<usize>,
+}
+
+impl Drop for RangeClaim<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self.mmap.in_flight.lock().unwrap();
+        if let Some(position) = in_flight.iter().position(|busy| *busy == self.range) {
+            in_flight.swap_remove(position);
+        }
+    }
+}
+
 /// Memory-mapped file writer with concurrent chunk support
 pub struct MmapWriter {
     file: Arc<Mutex<File>>,
-    mmap: Arc<Mutex<Option<MmapMut>>>,
+    mmap: Option<SharedMmap>,
     file_size: u64,
     path: std::path::PathBuf,
     use_mmap: bool,
@@ -50,15 +101,13 @@ impl MmapWriter {
             })?;
 
         // Pre-allocate file space
-        file.set_len(file_size)
-            .map_err(|e| TurboCdnError::io(format!("Failed to set file size: {e}")))?;
+        crate::write_backend::preallocate(&file, &path, file_size)?;
 
         let file = Arc::new(Mutex::new(file));
-        let mmap = Arc::new(Mutex::new(None));
 
         let mut writer = Self {
             file,
-            mmap,
+            mmap: None,
             file_size,
             path,
             use_mmap,
@@ -88,49 +137,54 @@ impl MmapWriter {
     async fn init_mmap(&mut self) -> Result<()> {
         let file = self.file.lock().await;
 
-        let mmap = unsafe {
+        let mut mmap = unsafe {
             MmapOptions::new()
                 .len(self.file_size as usize)
                 .map_mut(&*file)
                 .map_err(|e| TurboCdnError::io(format!("Failed to create memory map: {e}")))?
         };
+        drop(file);
 
-        *self.mmap.lock().await = Some(mmap);
+        let ptr = mmap.as_mut_ptr();
+        self.mmap = Some(SharedMmap {
+            mmap,
+            ptr,
+            in_flight: std::sync::Mutex::new(Vec::new()),
+        });
         debug!("Memory mapping initialized for {} bytes", self.file_size);
 
         Ok(())
     }
 
     /// Write data at specific offset
+    ///
+    /// Concurrent writes should target non-overlapping ranges, as the chunks
+    /// of a download do: mapped writes don't wait for each other, and a write
+    /// overlapping one in progress fails instead.
     pub async fn write_at(&self, offset: u64, data: &[u8]) -> Result<usize> {
-        if self.use_mmap {
-            self.write_mmap(offset, data).await
-        } else {
-            self.write_file(offset, data).await
+        match &self.mmap {
+            Some(mmap) => self.write_mmap(mmap, offset, data),
+            None => self.write_file(offset, data).await,
         }
     }
 
     /// Write data using memory mapping
-    async fn write_mmap(&self, offset: u64, data: &[u8]) -> Result<usize> {
-        let mut mmap_guard = self.mmap.lock().await;
-
-        let mmap = mmap_guard
-            .as_mut()
-            .ok_or_else(|| TurboCdnError::io("Memory map not initialized".to_string()))?;
-
-        let start = offset as usize;
-        let end = start + data.len();
-
-        if end > mmap.len() {
+    fn write_mmap(&self, mmap: &SharedMmap, offset: u64, data: &[u8]) -> Result<usize> {
+        let len = mmap.mmap.len();
+        let start = usize::try_from(offset).unwrap_or(usize::MAX);
+        let end = start.saturating_add(data.len());
+        if end > len {
             return Err(TurboCdnError::io(format!(
-                "Write would exceed file bounds: {} > {}",
-                end,
-                mmap.len()
+                "Write would exceed file bounds: {end} > {len}"
             )));
         }
 
-        // Copy data to memory-mapped region
-        mmap[start..end].copy_from_slice(data);
+        let _claim = mmap.claim(start..end)?;
+        // SAFETY: `start..end` lies inside the mapping and is claimed, so no
+        // other write touches these bytes until the claim is dropped
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), mmap.ptr.add(start), data.len());
+        }
 
         debug!(
             "Memory-mapped write: {} bytes at offset {}",
@@ -157,23 +211,18 @@ impl MmapWriter {
 
     /// Flush all pending writes
     pub async fn flush(&self) -> Result<()> {
-        if self.use_mmap {
-            self.flush_mmap().await
-        } else {
-            self.flush_file().await
+        match &self.mmap {
+            Some(mmap) => Self::flush_mmap(mmap),
+            None => self.flush_file().await,
         }
     }
 
     /// Flush memory-mapped data
-    async fn flush_mmap(&self) -> Result<()> {
-        let mmap_guard = self.mmap.lock().await;
-
-        if let Some(mmap) = mmap_guard.as_ref() {
-            mmap.flush()
-                .map_err(|e| TurboCdnError::io(format!("Failed to flush memory map: {e}")))?;
-            debug!("Memory map flushed");
-        }
-
+    fn flush_mmap(mmap: &SharedMmap) -> Result<()> {
+        mmap.mmap
+            .flush()
+            .map_err(|e| TurboCdnError::io(format!("Failed to flush memory map: {e}")))?;
+        debug!("Memory map flushed");
         Ok(())
     }
 
@@ -333,6 +382,56 @@ mod tests {
         assert!(writer.verify_integrity().await.unwrap());
     }
 
+    #[tokio::test]
+    async fn test_concurrent_chunk_writes() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.dat");
+        let chunk = 4096;
+        let chunks = 8;
+
+        let writer = Arc::new(
+            MmapWriter::new(&file_path, chunk * chunks, Some(0))
+                .await
+                .unwrap(),
+        );
+        let tasks: Vec<_> = (0..chunks)
+            .map(|index| {
+                let writer = writer.clone();
+                tokio::spawn(async move {
+                    let data = vec![index as u8; chunk as usize];
+                    writer.write_at(index * chunk, &data).await.unwrap();
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        writer.flush().await.unwrap();
+
+        let contents = std::fs::read(&file_path).unwrap();
+        for (index, bytes) in contents.chunks(chunk as usize).enumerate() {
+            assert!(bytes.iter().all(|&b| b == index as u8));
+        }
+        assert!(writer.write_at(chunk * chunks - 1, b"ab").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_claims_are_rejected() {
+        let dir = tempdir().unwrap();
+        let writer = MmapWriter::new(dir.path().join("test.dat"), 4096, Some(0))
+            .await
+            .unwrap();
+        let mmap = writer.mmap.as_ref().unwrap();
+
+        let claim = mmap.claim(0..100).unwrap();
+        assert!(mmap.claim(50..150).is_err());
+        let neighbour = mmap.claim(100..200).unwrap();
+        drop(claim);
+        assert!(mmap.claim(50..100).is_ok());
+        drop(neighbour);
+        assert!(mmap.in_flight.lock().unwrap().is_empty());
+    }
+
     #[test]
     fn test_optimal_chunk_size() {
         assert_eq!(optimal_mmap_chunk_size(500_000, None), 256 * 1024);
@@ -0,0 +1,399 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Static checks for URL mapping rules
+//!
+//! Backs `turbo-cdn rules check`: every rule is compiled, the examples
+//! embedded in it are run, and rules that a higher-priority rule always
+//! wins against are flagged. `UrlMapper::new` only logs and skips rules that
+//! fail to compile, this reports why.
+
+use crate::config::{Region, RuleMode, TurboCdnConfig, UrlMappingRuleConfig};
+use crate::mirror::MirrorCatalog;
+use crate::url_mapper::{UrlMapper, UrlMappingRule};
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// How serious a rule issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The rule works, but probably not as intended
+    Warning,
+    /// The rule is skipped or generates wrong URLs
+    Error,
+}
+
+/// Problem found in a rule
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleIssue {
+    /// Rule name, `[[mirrors]]` for catalog problems
+    pub rule: String,
+    /// Severity
+    pub severity: Severity,
+    /// Description of the problem
+    pub message: String,
+}
+
+/// Result of checking the mapping rules of a configuration
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RuleCheckReport {
+    /// Number of rules checked
+    pub rules: usize,
+    /// Number of examples run
+    pub examples: usize,
+    /// Problems found, grouped by rule in configuration order
+    pub issues: Vec<RuleIssue>,
+}
+
+impl RuleCheckReport {
+    /// Whether any issue is an error
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|i| i.severity == Severity::Error)
+    }
+
+    /// Issues reported for a rule
+    pub fn issues_for<'a>(&'a self, rule: &'a str) -> impl Iterator<Item = &'a RuleIssue> {
+        self.issues.iter().filter(move |issue| issue.rule == rule)
+    }
+
+    fn push(&mut self, rule: &str, severity: Severity, message: String) {
+        self.issues.push(RuleIssue {
+            rule: rule.to_string(),
+            severity,
+            message,
+        });
+    }
+}
+
+/// Check the URL mapping rules of a configuration
+pub fn check_rules(config: &TurboCdnConfig) -> RuleCheckReport {
+    let mut report = RuleCheckReport {
+        rules: config.url_mapping_rules.len(),
+        ..Default::default()
+    };
+
+    let mirrors = MirrorCatalog::new(&config.mirrors, &config.mapping.disabled_mirrors)
+        .unwrap_or_else(|e| {
+            report.push("[[mirrors]]", Severity::Error, e.to_string());
+            MirrorCatalog::default()
+        });
+
+    let mut names = HashSet::new();
+    let mut compiled = Vec::new();
+    for rule_config in &config.url_mapping_rules {
+        if !names.insert(rule_config.name.as_str()) {
+            report.push(
+                &rule_config.name,
+                Severity::Warning,
+                "duplicate rule name".to_string(),
+            );
+        }
+        match UrlMapper::create_rule_from_config(rule_config, &mirrors) {
+            Ok(rule) => {
+                run_examples(&mut report, rule_config, &rule);
+                compiled.push((rule_config, rule));
+            }
+            Err(e) => report.push(&rule_config.name, Severity::Error, e.to_string()),
+        }
+    }
+
+    // Only the first matching rule is used in `first` mode, in `merge` mode
+    // every matching rule contributes candidates
+    if config.mapping.mode == RuleMode::First {
+        compiled.sort_by_key(|(_, rule)| rule.priority);
+        check_shadowing(&mut report, &compiled);
+    }
+
+    // Keep the issues of a rule together, in configuration order
+    let position = |rule: &str| {
+        config
+            .url_mapping_rules
+            .iter()
+            .position(|r| r.name == rule)
+            .map_or(0, |p| p + 1)
+    };
+    report.issues.sort_by_key(|issue| position(&issue.rule));
+    report
+}
+
+/// Run the examples of a rule against its templates
+fn run_examples(
+    report: &mut RuleCheckReport,
+    config: &UrlMappingRuleConfig,
+    rule: &UrlMappingRule,
+) {
+    for example in &config.examples {
+        report.examples += 1;
+        let region = example.region.clone().unwrap_or(Region::Global);
+        let input = &example.input;
+
+        let rendered = match rule.render(input, &region) {
+            None if example.expected.is_empty() => continue,
+            None => {
+                report.push(
+                    &config.name,
+                    Severity::Error,
+                    format!("example {input} does not match the pattern"),
+                );
+                continue;
+            }
+            Some(_) if example.expected.is_empty() => {
+                report.push(
+                    &config.name,
+                    Severity::Error,
+                    format!("example {input} should not match the pattern"),
+                );
+                continue;
+            }
+            Some(rendered) => rendered,
+        };
+
+        let mut actual = Vec::new();
+        for url in rendered {
+            match url {
+                Ok(url) => actual.push(url),
+                Err(reason) => report.push(
+                    &config.name,
+                    Severity::Error,
+                    format!("example {input}: {reason}"),
+                ),
+            }
+        }
+        if actual != example.expected {
+            report.push(
+                &config.name,
+                Severity::Error,
+                format!(
+                    "example {input} generated {actual:?}, expected {:?}",
+                    example.expected
+                ),
+            );
+        }
+    }
+}
+
+/// Flag rules that are never used because an earlier rule always matches first
+fn check_shadowing(
+    report: &mut RuleCheckReport,
+    compiled: &[(&UrlMappingRuleConfig, UrlMappingRule)],
+) {
+    for (position, (config, rule)) in compiled.iter().enumerate() {
+        if !rule.enabled {
+            continue;
+        }
+        let earlier: Vec<&UrlMappingRule> = compiled[..position]
+            .iter()
+            .map(|(_, r)| r)
+            .filter(|r| r.enabled && regions_cover(&r.regions, &rule.regions))
+            .collect();
+
+        if let Some(winner) = earlier
+            .iter()
+            .find(|r| r.pattern.as_str() == rule.pattern.as_str())
+        {
+            report.push(
+                &config.name,
+                Severity::Warning,
+                format!(
+                    "unreachable: rule '{}' has the same pattern and a higher priority",
+                    winner.name
+                ),
+            );
+            continue;
+        }
+
+        let matching: Vec<&str> = config
+            .examples
+            .iter()
+            .filter(|e| !e.expected.is_empty())
+            .map(|e| e.input.as_str())
+            .collect();
+        let shadowed: Vec<(&str, &str)> = matching
+            .iter()
+            .filter_map(|&input| {
+                earlier
+                    .iter()
+                    .find(|r| r.pattern.is_match(input))
+                    .map(|r| (input, r.name.as_str()))
+            })
+            .collect();
+
+        if !matching.is_empty() && shadowed.len() == matching.len() {
+            let mut winners: Vec<String> = shadowed
+                .iter()
+                .map(|&(_, name)| format!("'{name}'"))
+                .collect();
+            winners.dedup();
+            report.push(
+                &config.name,
+                Severity::Warning,
+                format!(
+                    "unreachable: every example is handled by higher-priority rule {}",
+                    winners.join(", ")
+                ),
+            );
+        } else {
+            for (input, winner) in shadowed {
+                report.push(
+                    &config.name,
+                    Severity::Warning,
+                    format!("example {input} is handled by higher-priority rule '{winner}'"),
+                );
+            }
+        }
+    }
+}
+
+/// Whether a rule for `outer` regions applies wherever one for `inner` does
+///
+/// An empty region list applies everywhere.
+fn regions_cover(outer: &[Region], inner: &[Region]) -> bool {
+    outer.is_empty() || (!inner.is_empty() && inner.iter().all(|r| outer.contains(r)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RuleExampleConfig;
+
+    fn rule(name: &str, pattern: &str, replacement: &str, priority: u32) -> UrlMappingRuleConfig {
+        UrlMappingRuleConfig {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            replacements: vec![replacement.into()],
+            regions: vec![],
+            priority,
+            enabled: true,
+            examples: vec![],
+        }
+    }
+
+    fn example(input: &str, expected: &[&str]) -> RuleExampleConfig {
+        RuleExampleConfig {
+            input: input.to_string(),
+            expected: expected.iter().map(|s| s.to_string()).collect(),
+            region: None,
+        }
+    }
+
+    fn config(rules: Vec<UrlMappingRuleConfig>) -> TurboCdnConfig {
+        TurboCdnConfig {
+            url_mapping_rules: rules,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_examples_pass_and_fail() {
+        let mut good = rule(
+            "good",
+            r"^https://a\.example/(.+)$",
+            "https://m.example/$1",
+            1,
+        );
+        good.examples = vec![
+            example("https://a.example/x", &["https://m.example/x"]),
+            example("https://b.example/x", &[]),
+        ];
+        let mut bad = rule(
+            "bad",
+            r"^https://b\.example/(.+)$",
+            "https://m.example/$1",
+            2,
+        );
+        bad.examples = vec![
+            example("https://b.example/x", &["https://other.example/x"]),
+            example("https://c.example/x", &["https://m.example/x"]),
+            example("https://b.example/y", &[]),
+        ];
+
+        let report = check_rules(&config(vec![good, bad]));
+        assert_eq!(report.rules, 2);
+        assert_eq!(report.examples, 5);
+        assert_eq!(report.issues_for("good").count(), 0);
+        assert_eq!(report.issues_for("bad").count(), 3);
+        assert!(report.has_errors());
+    }
+
+    #[test]
+    fn test_compile_errors_are_reported() {
+        let report = check_rules(&config(vec![
+            rule("regex", r"^https://(unclosed$", "https://m.example/$1", 1),
+            rule(
+                "variable",
+                r"^https://a/(.+)$",
+                "https://m.example/${nope}",
+                2,
+            ),
+            rule("group", r"^https://a/(.+)$", "https://m.example/$2", 3),
+        ]));
+
+        for name in ["regex", "variable", "group"] {
+            let issues: Vec<_> = report.issues_for(name).collect();
+            assert_eq!(issues.len(), 1, "{name}");
+            assert_eq!(issues[0].severity, Severity::Error);
+        }
+    }
+
+    #[test]
+    fn test_shadowed_rules_are_flagged() {
+        let broad = rule(
+            "broad",
+            r"^https://a\.example/(.+)$",
+            "https://m.example/$1",
+            1,
+        );
+        let same = rule(
+            "same",
+            r"^https://a\.example/(.+)$",
+            "https://n.example/$1",
+            2,
+        );
+        let mut narrow = rule(
+            "narrow",
+            r"^https://a\.example/dl/(.+)$",
+            "https://d.example/$1",
+            3,
+        );
+        narrow.examples = vec![example("https://a.example/dl/x", &["https://d.example/x"])];
+
+        let report = check_rules(&config(vec![broad.clone(), same, narrow.clone()]));
+        assert_eq!(report.issues_for("broad").count(), 0);
+        assert!(report
+            .issues_for("same")
+            .all(|i| i.message.contains("unreachable")));
+        assert!(report
+            .issues_for("narrow")
+            .any(|i| i.message.contains("unreachable") && i.message.contains("'broad'")));
+        assert!(!report.has_errors());
+
+        // A regional rule doesn't shadow a rule that applies everywhere
+        let mut regional = broad.clone();
+        regional.regions = vec![Region::China];
+        let report = check_rules(&config(vec![regional, narrow.clone()]));
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+
+        // Every matching rule is used in merge mode
+        let mut merged = config(vec![broad, narrow]);
+        merged.mapping.mode = RuleMode::Merge;
+        assert!(check_rules(&merged).issues.is_empty());
+    }
+
+    #[test]
+    fn test_example_region() {
+        let mut regional = rule(
+            "regional",
+            r"^https://a\.example/(.+)$",
+            "https://${region}.example/$1",
+            1,
+        );
+        regional.examples = vec![RuleExampleConfig {
+            region: Some(Region::China),
+            ..example("https://a.example/x", &["https://China.example/x"])
+        }];
+
+        let report = check_rules(&config(vec![regional]));
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+    }
+}
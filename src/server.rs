@@ -0,0 +1,832 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Local caching forward-proxy server
+//!
+//! Exposes the download engine over plain HTTP so tools that cannot link
+//! against the library (pip, npm, curl, ...) benefit from mirror selection
+//! and the local download cache.
+//!
+//! Supported requests:
+//! - `GET /resolve?url=<url>` - JSON with ranked mirror candidates for `url`
+//! - `GET /fetch?url=<url>` - stream `url` back while turbo-cdn downloads it
+//! - `GET http://host/path` - plain forward-proxy request (absolute-form URI)
+//!
+//! Uncached files are downloaded with the concurrent chunked downloader into
+//! a scratch file, and the response follows its readable front: chunks are
+//! sent in order as they complete, while later chunks keep downloading.
+//!
+//! Responses support single `Range` requests and `HEAD`. Uncached ranges and
+//! `HEAD` requests are answered by a single request to the first mirror that
+//! answers rather than by downloading the whole file. In resolve-only mode
+//! only `/resolve` is served and nothing is downloaded. `CONNECT` tunnels are
+//! refused: every request must go through the download engine, so URL
+//! policies and proxy settings apply.
+
+use crate::download_cache::CacheValidators;
+use crate::download_watch::{DownloadWatch, WatchState};
+use crate::error::{Result, TurboCdnError};
+use crate::{DownloadResult, DownloadStream, TurboCdn};
+use bytes::Bytes;
+use futures_util::{StreamExt, TryStreamExt};
+use http_body_util::{combinators::BoxBody, BodyExt, Full, StreamBody};
+use hyper::body::{Frame, Incoming};
+use hyper::header::{self, HeaderValue};
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tracing::{debug, info, warn};
+
+/// Default listen address for `turbo-cdn serve`
+pub const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:8787";
+
+/// Upstream chunks buffered for a client that reads slower than the mirror
+const RELAY_BUFFER_CHUNKS: usize = 16;
+
+/// Largest piece of a followed download sent at once
+const FOLLOW_READ_SIZE: u64 = 64 * 1024;
+
+/// Response body type used by the server
+type ServerBody = BoxBody<Bytes, std::io::Error>;
+
+/// Local caching forward-proxy server
+#[derive(Debug, Clone)]
+pub struct ProxyServer {
+    turbo_cdn: Arc<TurboCdn>,
+    scratch_dir: PathBuf,
+    resolve_only: bool,
+    request_counter: Arc<AtomicU64>,
+}
+
+impl ProxyServer {
+    /// Create a new server backed by a TurboCdn client
+    pub fn new(turbo_cdn: Arc<TurboCdn>) -> Self {
+        Self {
+            turbo_cdn,
+            scratch_dir: std::env::temp_dir().join("turbo-cdn-serve"),
+            resolve_only: false,
+            request_counter: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Set the directory used for in-flight downloads
+    pub fn with_scratch_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.scratch_dir = dir.into();
+        self
+    }
+
+    /// Only answer `/resolve` requests, never download or proxy
+    pub fn with_resolve_only(mut self, resolve_only: bool) -> Self {
+        self.resolve_only = resolve_only;
+        self
+    }
+
+    /// Bind to an address and serve until the task is cancelled
+    pub async fn run(self, addr: SocketAddr) -> Result<()> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| TurboCdnError::io(format!("Failed to bind {addr}: {e}")))?;
+        self.serve(listener).await
+    }
+
+    /// Serve connections from an already bound listener
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        tokio::fs::create_dir_all(&self.scratch_dir)
+            .await
+            .map_err(|e| TurboCdnError::io(format!("Failed to create scratch directory: {e}")))?;
+
+        if let Ok(addr) = listener.local_addr() {
+            info!("turbo-cdn server listening on http://{}", addr);
+        }
+
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    warn!("Failed to accept connection: {}", e);
+                    continue;
+                }
+            };
+
+            let server = self.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |req| {
+                    let server = server.clone();
+                    async move { Ok::<_, std::convert::Infallible>(server.handle(req).await) }
+                });
+
+                if let Err(e) = hyper::server::conn::http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    debug!("Connection from {} closed with error: {}", peer, e);
+                }
+            });
+        }
+    }
+
+    /// Route a single request
+    async fn handle(&self, req: Request<Incoming>) -> Response<ServerBody> {
+        debug!("{} {}", req.method(), req.uri());
+
+        if req.uri().scheme().is_none() && req.uri().path() == "/resolve" {
+            return self.handle_resolve(&req).await;
+        }
+
+        if self.resolve_only {
+            return text_response(
+                StatusCode::NOT_FOUND,
+                "Server runs in resolve-only mode, use /resolve?url=<url>",
+            );
+        }
+
+        if req.method() != Method::GET && req.method() != Method::HEAD {
+            return text_response(
+                StatusCode::METHOD_NOT_ALLOWED,
+                "Only GET and HEAD are supported",
+            );
+        }
+
+        let target = if req.uri().scheme().is_some() {
+            // Forward-proxy request with an absolute-form URI
+            Some(req.uri().to_string())
+        } else if req.uri().path() == "/fetch" {
+            query_param(req.uri().query().unwrap_or_default(), "url")
+        } else {
+            return text_response(StatusCode::NOT_FOUND, "Unknown endpoint");
+        };
+
+        match target {
+            Some(url) => self.handle_fetch(&req, &url).await,
+            None => text_response(StatusCode::BAD_REQUEST, "Missing 'url' query parameter"),
+        }
+    }
+
+    /// Answer with the ranked candidate URLs for a URL as JSON
+    async fn handle_resolve(&self, req: &Request<Incoming>) -> Response<ServerBody> {
+        if req.method() != Method::GET && req.method() != Method::HEAD {
+            return text_response(StatusCode::METHOD_NOT_ALLOWED, "Only GET is supported");
+        }
+
+        let Some(url) = query_param(req.uri().query().unwrap_or_default(), "url") else {
+            return text_response(StatusCode::BAD_REQUEST, "Missing 'url' query parameter");
+        };
+        if url::Url::parse(&url).is_err() {
+            return text_response(StatusCode::BAD_REQUEST, "Invalid URL");
+        }
+
+        let resolution = match self.turbo_cdn.resolve_url(&url).await {
+            Ok(resolution) => resolution,
+            Err(e) => return error_response(&e),
+        };
+
+        match serde_json::to_vec_pretty(&resolution) {
+            Ok(json) => {
+                let mut response = Response::new(full_body(json));
+                response.headers_mut().insert(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("application/json"),
+                );
+                response
+            }
+            Err(e) => text_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        }
+    }
+
+    /// Stream a URL back from the download cache, or from a mirror while it downloads
+    async fn handle_fetch(&self, req: &Request<Incoming>, url: &str) -> Response<ServerBody> {
+        if url::Url::parse(url).is_err() {
+            return text_response(StatusCode::BAD_REQUEST, "Invalid URL");
+        }
+
+        let range = req
+            .headers()
+            .get(header::RANGE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let head = req.method() == Method::HEAD;
+
+        match self.turbo_cdn.lookup_stream(url).await {
+            DownloadStream::Cached { entry, path } => {
+                let Some((status, start, length)) = select_range(range.as_deref(), entry.size)
+                else {
+                    return unsatisfiable_response(entry.size);
+                };
+                let builder = fetch_response(
+                    status,
+                    &entry.source_url,
+                    true,
+                    entry.validators.etag.as_deref(),
+                    entry.validators.last_modified.as_deref(),
+                )
+                .header(header::CONTENT_LENGTH, length);
+                let builder = if status == StatusCode::PARTIAL_CONTENT {
+                    builder.header(
+                        header::CONTENT_RANGE,
+                        content_range(start, length, entry.size),
+                    )
+                } else {
+                    builder
+                };
+
+                let body = if head {
+                    empty_body()
+                } else {
+                    match file_body(&path, start, length).await {
+                        Ok(body) => body,
+                        Err(e) => return error_response(&e),
+                    }
+                };
+                build(builder, body)
+            }
+            // Neither is worth downloading the whole file for
+            DownloadStream::Uncached { key } if head || range.is_some() => {
+                match self.turbo_cdn.open_stream(&key, range.as_deref()).await {
+                    Ok((source_url, response)) => {
+                        self.relay_response(key, source_url, response, range.as_deref(), head)
+                    }
+                    Err(e) => {
+                        warn!("Failed to fetch {}: {}", url, e);
+                        error_response(&e)
+                    }
+                }
+            }
+            DownloadStream::Uncached { key } => self.follow_download(key).await,
+        }
+    }
+
+    /// Download a URL with the chunked downloader, answering with the file while it is written
+    ///
+    /// The response starts once a mirror answered. The finished download is
+    /// added to the download cache; it stops when the client goes away.
+    async fn follow_download(&self, key: String) -> Response<ServerBody> {
+        let scratch = self.scratch_file();
+        let watch = Arc::new(DownloadWatch::new());
+        let mut state = watch.subscribe();
+        let mut task = DownloadTask(tokio::spawn({
+            let turbo_cdn = self.turbo_cdn.clone();
+            let key = key.clone();
+            let path = scratch.0.clone();
+            async move { turbo_cdn.download_watched(&key, &path, watch).await }
+        }));
+
+        // Headers need the size and validators of the mirror that answered
+        let attempt = loop {
+            if let Some(attempt) = state.borrow_and_update().attempt.clone() {
+                break attempt;
+            }
+            if state.changed().await.is_err() {
+                let error = match (&mut task.0).await {
+                    Ok(Err(e)) => e,
+                    Ok(Ok(_)) => TurboCdnError::internal("Download finished without a mirror"),
+                    Err(e) => TurboCdnError::internal(e.to_string()),
+                };
+                warn!("Failed to fetch {}: {}", key, error);
+                return error_response(&error);
+            }
+        };
+
+        let mut builder = fetch_response(
+            StatusCode::OK,
+            &attempt.url,
+            false,
+            attempt.etag.as_deref(),
+            attempt.last_modified.as_deref(),
+        );
+        if attempt.total_size > 0 {
+            builder = builder.header(header::CONTENT_LENGTH, attempt.total_size);
+        }
+
+        let follower = DownloadFollower {
+            key,
+            state,
+            task,
+            scratch,
+            file: None,
+            size: attempt.total_size,
+            sent: 0,
+            done: false,
+        };
+        let stream = futures_util::stream::unfold(follower, |mut follower| async move {
+            let item = follower.next().await?;
+            Some((item.map(Frame::data), follower))
+        });
+        build(builder, BodyExt::boxed(StreamBody::new(stream)))
+    }
+
+    /// Pick a unique file in the scratch directory
+    fn scratch_file(&self) -> ScratchFile {
+        let id = self.request_counter.fetch_add(1, Ordering::Relaxed);
+        ScratchFile(
+            self.scratch_dir
+                .join(format!("{}-{id}.download", std::process::id())),
+        )
+    }
+
+    /// Answer with an upstream response, relaying its body while it arrives
+    ///
+    /// Complete bodies are also added to the download cache. Servers that
+    /// ignore the `Range` header are cut down to the requested range here.
+    fn relay_response(
+        &self,
+        key: String,
+        source_url: String,
+        response: reqwest::Response,
+        range: Option<&str>,
+        head: bool,
+    ) -> Response<ServerBody> {
+        let headers = response.headers();
+        let etag = header_str(headers, header::ETAG);
+        let last_modified = header_str(headers, header::LAST_MODIFIED);
+        let size = response.content_length();
+
+        let mut builder = fetch_response(
+            StatusCode::OK,
+            &source_url,
+            false,
+            etag.as_deref(),
+            last_modified.as_deref(),
+        );
+        let mut window = None;
+        let mut cache = true;
+
+        if response.status() == reqwest::StatusCode::PARTIAL_CONTENT {
+            // The mirror served the range itself, which is not worth caching
+            cache = false;
+            builder = builder.status(StatusCode::PARTIAL_CONTENT);
+            if let Some(content_range) = header_str(headers, header::CONTENT_RANGE) {
+                builder = builder.header(header::CONTENT_RANGE, content_range);
+            }
+            if let Some(size) = size {
+                builder = builder.header(header::CONTENT_LENGTH, size);
+            }
+        } else if let Some(size) = size {
+            let Some((status, start, length)) = select_range(range, size) else {
+                return unsatisfiable_response(size);
+            };
+            builder = builder
+                .status(status)
+                .header(header::CONTENT_LENGTH, length);
+            if status == StatusCode::PARTIAL_CONTENT {
+                cache = false;
+                window = Some((start, length));
+                builder = builder.header(header::CONTENT_RANGE, content_range(start, length, size));
+            }
+        }
+
+        if head {
+            return build(builder, empty_body());
+        }
+
+        let target = cache.then(|| CacheTarget {
+            key,
+            source_url,
+            validators: CacheValidators {
+                etag,
+                last_modified,
+            },
+            scratch: self.scratch_file(),
+        });
+        build(
+            builder,
+            relay_body(self.turbo_cdn.clone(), response, window, target),
+        )
+    }
+}
+
+/// Removes an in-flight download once it is no longer needed
+struct ScratchFile(PathBuf);
+
+impl Drop for ScratchFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+        let _ = std::fs::remove_file(crate::concurrent_downloader::incomplete_marker(&self.0));
+    }
+}
+
+/// Download running for a client, aborted when the client goes away
+struct DownloadTask(tokio::task::JoinHandle<Result<DownloadResult>>);
+
+impl Drop for DownloadTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Body of a followed download, read from its scratch file in order
+struct DownloadFollower {
+    key: String,
+    state: tokio::sync::watch::Receiver<WatchState>,
+    task: DownloadTask,
+    scratch: ScratchFile,
+    /// Scratch file and the attempt it was opened for
+    file: Option<(u64, tokio::fs::File)>,
+    /// Size announced to the client, 0 if unknown
+    size: u64,
+    sent: u64,
+    done: bool,
+}
+
+impl DownloadFollower {
+    /// Next piece of the body, `None` once it was sent completely
+    async fn next(&mut self) -> Option<std::io::Result<Bytes>> {
+        if self.done {
+            return None;
+        }
+
+        loop {
+            let (generation, readable, size) = {
+                let state = self.state.borrow_and_update();
+                let size = state.attempt.as_ref().map(|attempt| attempt.total_size);
+                (state.generation, state.readable, size)
+            };
+            if self.size > 0 && size.is_some_and(|size| size != self.size) {
+                self.done = true;
+                return Some(Err(std::io::Error::other(
+                    "Mirror reported a different size",
+                )));
+            }
+
+            if readable > self.sent {
+                match self.read(generation, readable).await {
+                    Ok(Some(bytes)) => return Some(Ok(bytes)),
+                    // The attempt was restarted while reading
+                    Ok(None) => continue,
+                    Err(e) => {
+                        self.done = true;
+                        return Some(Err(e));
+                    }
+                }
+            }
+
+            if self.state.changed().await.is_err() {
+                self.done = true;
+                return self.finish().await;
+            }
+        }
+    }
+
+    /// Read the next piece up to `readable`, `None` if `generation` ended meanwhile
+    async fn read(&mut self, generation: u64, readable: u64) -> std::io::Result<Option<Bytes>> {
+        // Restarted attempts may recreate the file
+        if self
+            .file
+            .as_ref()
+            .is_none_or(|(opened, _)| *opened != generation)
+        {
+            let file = tokio::fs::File::open(&self.scratch.0).await?;
+            self.file = Some((generation, file));
+        }
+        let Some((_, file)) = self.file.as_mut() else {
+            return Ok(None);
+        };
+
+        let mut buffer = vec![0; (readable - self.sent).min(FOLLOW_READ_SIZE) as usize];
+        file.seek(std::io::SeekFrom::Start(self.sent)).await?;
+        file.read_exact(&mut buffer).await?;
+
+        // The bytes may belong to a discarded attempt
+        if self.state.borrow().generation != generation {
+            return Ok(None);
+        }
+        self.sent += buffer.len() as u64;
+        Ok(Some(Bytes::from(buffer)))
+    }
+
+    /// Report how the download ended once everything readable was sent
+    async fn finish(&mut self) -> Option<std::io::Result<Bytes>> {
+        let result = match (&mut self.task.0).await {
+            Ok(result) => result,
+            Err(e) => Err(TurboCdnError::internal(e.to_string())),
+        };
+        match result {
+            Ok(_) if self.size == 0 || self.sent == self.size => None,
+            Ok(_) => Some(Err(std::io::Error::other("Download ended early"))),
+            Err(e) => {
+                warn!("Failed to fetch {}: {}", self.key, e);
+                Some(Err(std::io::Error::other(e)))
+            }
+        }
+    }
+}
+
+/// Where a relayed body is stored for the download cache
+struct CacheTarget {
+    key: String,
+    source_url: String,
+    validators: CacheValidators,
+    scratch: ScratchFile,
+}
+
+/// Relay an upstream body to the client while it downloads
+///
+/// `window` limits the relayed bytes to a start offset and length. With a
+/// `target`, the body is also written to a scratch file which is added to
+/// the download cache once the whole body arrived. The download stops when
+/// the client goes away.
+fn relay_body(
+    turbo_cdn: Arc<TurboCdn>,
+    response: reqwest::Response,
+    window: Option<(u64, u64)>,
+    target: Option<CacheTarget>,
+) -> ServerBody {
+    let (tx, rx) = tokio::sync::mpsc::channel::<std::io::Result<Bytes>>(RELAY_BUFFER_CHUNKS);
+
+    tokio::spawn(async move {
+        let mut copy = match &target {
+            Some(target) => match tokio::fs::File::create(&target.scratch.0).await {
+                Ok(file) => Some(file),
+                Err(e) => {
+                    warn!("Failed to create {}: {}", target.scratch.0.display(), e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let (mut skip, mut remaining) = window.unwrap_or((0, u64::MAX));
+        let mut upstream = response.bytes_stream();
+        let mut complete = false;
+
+        while remaining > 0 {
+            let mut bytes = match upstream.next().await {
+                Some(Ok(bytes)) => bytes,
+                Some(Err(e)) => {
+                    warn!("Upstream body failed: {}", e);
+                    let _ = tx.send(Err(std::io::Error::other(e))).await;
+                    break;
+                }
+                None => {
+                    complete = true;
+                    break;
+                }
+            };
+
+            if let Some(file) = copy.as_mut() {
+                if let Err(e) = file.write_all(&bytes).await {
+                    warn!("Failed to write cache copy: {}", e);
+                    copy = None;
+                }
+            }
+
+            let skipped = skip.min(bytes.len() as u64);
+            skip -= skipped;
+            let mut bytes = bytes.split_off(skipped as usize);
+            bytes.truncate(remaining.min(bytes.len() as u64) as usize);
+            remaining -= bytes.len() as u64;
+            if !bytes.is_empty() && tx.send(Ok(bytes)).await.is_err() {
+                debug!("Client went away, stopping download");
+                break;
+            }
+        }
+
+        if let (true, Some(target), Some(mut file)) = (complete, target, copy) {
+            if let Err(e) = file.flush().await {
+                warn!("Failed to write cache copy: {}", e);
+                return;
+            }
+            drop(file);
+            turbo_cdn
+                .store_in_cache(
+                    &target.key,
+                    &target.source_url,
+                    &target.scratch.0,
+                    target.validators,
+                )
+                .await;
+        }
+    });
+
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|item| (item.map(Frame::data), rx))
+    });
+    BodyExt::boxed(StreamBody::new(stream))
+}
+
+/// Stream a byte range of a file
+async fn file_body(path: &Path, start: u64, length: u64) -> Result<ServerBody> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| TurboCdnError::io(format!("Failed to open {}: {e}", path.display())))?;
+    file.seek(std::io::SeekFrom::Start(start))
+        .await
+        .map_err(|e| TurboCdnError::io(format!("Failed to seek: {e}")))?;
+
+    let stream = tokio_util::io::ReaderStream::new(file.take(length)).map_ok(Frame::data);
+    Ok(BodyExt::boxed(StreamBody::new(stream)))
+}
+
+/// Start a `/fetch` response with the headers every answer carries
+fn fetch_response(
+    status: StatusCode,
+    source_url: &str,
+    cached: bool,
+    etag: Option<&str>,
+    last_modified: Option<&str>,
+) -> hyper::http::response::Builder {
+    let mut builder = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header("x-turbo-cdn-source", source_url)
+        .header("x-turbo-cdn-cache", if cached { "HIT" } else { "MISS" });
+    if let Some(etag) = etag {
+        builder = builder.header(header::ETAG, etag);
+    }
+    if let Some(last_modified) = last_modified {
+        builder = builder.header(header::LAST_MODIFIED, last_modified);
+    }
+    builder
+}
+
+/// Pick the part of a resource to send: status, first byte and length
+///
+/// Returns `None` for ranges outside the resource.
+fn select_range(range: Option<&str>, size: u64) -> Option<(StatusCode, u64, u64)> {
+    match range.map_or(ByteRange::Full, |v| parse_range(v, size)) {
+        ByteRange::Partial(start, end) => {
+            Some((StatusCode::PARTIAL_CONTENT, start, end - start + 1))
+        }
+        ByteRange::Unsatisfiable => None,
+        ByteRange::Full => Some((StatusCode::OK, 0, size)),
+    }
+}
+
+fn unsatisfiable_response(size: u64) -> Response<ServerBody> {
+    let mut response = text_response(
+        StatusCode::RANGE_NOT_SATISFIABLE,
+        "Requested range not satisfiable",
+    );
+    if let Ok(value) = HeaderValue::from_str(&format!("bytes */{size}")) {
+        response.headers_mut().insert(header::CONTENT_RANGE, value);
+    }
+    response
+}
+
+fn content_range(start: u64, length: u64, size: u64) -> String {
+    format!("bytes {}-{}/{}", start, start + length - 1, size)
+}
+
+fn header_str(headers: &reqwest::header::HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+fn build(builder: hyper::http::response::Builder, body: ServerBody) -> Response<ServerBody> {
+    builder
+        .body(body)
+        .unwrap_or_else(|e| text_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))
+}
+
+/// Byte range requested by a client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ByteRange {
+    /// No (usable) range, send the whole resource
+    Full,
+    /// Inclusive byte range
+    Partial(u64, u64),
+    /// Range outside the resource
+    Unsatisfiable,
+}
+
+/// Parse a `Range` header against the resource size
+///
+/// Malformed headers are ignored as RFC 9110 allows. Only the first range of
+/// a multi-range request is honored.
+fn parse_range(value: &str, size: u64) -> ByteRange {
+    let Some(spec) = value.strip_prefix("bytes=") else {
+        return ByteRange::Full;
+    };
+    let spec = spec.split(',').next().unwrap_or_default().trim();
+    let Some((start, end)) = spec.split_once('-') else {
+        return ByteRange::Full;
+    };
+
+    let range = if start.is_empty() {
+        // Suffix range: last N bytes
+        match end.parse::<u64>() {
+            Ok(0) => None,
+            Ok(suffix) => size
+                .checked_sub(1)
+                .map(|last| (size.saturating_sub(suffix), last)),
+            Err(_) => return ByteRange::Full,
+        }
+    } else {
+        let Ok(start) = start.parse::<u64>() else {
+            return ByteRange::Full;
+        };
+        let end = if end.is_empty() {
+            Some(u64::MAX)
+        } else {
+            match end.parse::<u64>() {
+                Ok(end) if end >= start => Some(end),
+                _ => return ByteRange::Full,
+            }
+        };
+        end.zip(size.checked_sub(1))
+            .map(|(end, last)| (start, end.min(last)))
+    };
+
+    match range {
+        Some((start, end)) if start <= end => ByteRange::Partial(start, end),
+        _ => ByteRange::Unsatisfiable,
+    }
+}
+
+/// Extract a query parameter value
+fn query_param(query: &str, name: &str) -> Option<String> {
+    url::form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// Map a download error to an HTTP response
+fn error_response(error: &TurboCdnError) -> Response<ServerBody> {
+    let status = match error {
+        TurboCdnError::HttpStatus { status_code, .. } => {
+            StatusCode::from_u16(*status_code).unwrap_or(StatusCode::BAD_GATEWAY)
+        }
+        TurboCdnError::Offline { .. } => StatusCode::GATEWAY_TIMEOUT,
+        TurboCdnError::Timeout { .. } => StatusCode::GATEWAY_TIMEOUT,
+        TurboCdnError::InsufficientSpace { .. } => StatusCode::INSUFFICIENT_STORAGE,
+        _ => StatusCode::BAD_GATEWAY,
+    };
+    text_response(status, &error.to_string())
+}
+
+fn text_response(status: StatusCode, message: &str) -> Response<ServerBody> {
+    let mut response = Response::new(full_body(format!("{message}\n")));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    response
+}
+
+fn full_body<T: Into<Bytes>>(data: T) -> ServerBody {
+    Full::new(data.into())
+        .map_err(|never| match never {})
+        .boxed()
+}
+
+fn empty_body() -> ServerBody {
+    full_body(Bytes::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), ByteRange::Partial(0, 99));
+        assert_eq!(
+            parse_range("bytes=900-", 1000),
+            ByteRange::Partial(900, 999)
+        );
+        assert_eq!(
+            parse_range("bytes=-100", 1000),
+            ByteRange::Partial(900, 999)
+        );
+        assert_eq!(
+            parse_range("bytes=500-5000", 1000),
+            ByteRange::Partial(500, 999)
+        );
+        assert_eq!(
+            parse_range("bytes=0-0,10-20", 1000),
+            ByteRange::Partial(0, 0)
+        );
+    }
+
+    #[test]
+    fn test_parse_range_unsatisfiable() {
+        assert_eq!(parse_range("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-10", 0), ByteRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn test_parse_range_malformed_is_ignored() {
+        assert_eq!(parse_range("items=0-10", 1000), ByteRange::Full);
+        assert_eq!(parse_range("bytes=50-10", 1000), ByteRange::Full);
+        assert_eq!(parse_range("bytes=abc", 1000), ByteRange::Full);
+    }
+
+    #[test]
+    fn test_query_param() {
+        let query = "url=https%3A%2F%2Fexample.com%2Fa%3Fb%3D1&x=y";
+        assert_eq!(
+            query_param(query, "url").as_deref(),
+            Some("https://example.com/a?b=1")
+        );
+        assert_eq!(query_param(query, "missing"), None);
+    }
+}
@@ -8,6 +8,7 @@
 
 use crate::concurrent_downloader::DownloadResult;
 use crate::error::{Result, TurboCdnError};
+use crate::middleware::RequestKind;
 use crate::{api_info, cli_info};
 use std::time::{Duration, Instant};
 use tokio::time::timeout;
@@ -77,41 +78,98 @@ pub struct SmartDownloader {
 }
 
 impl SmartDownloader {
+    /// Create a smart downloader on top of an existing client
+    ///
+    /// Speed tests and downloads use the client's configuration, including
+    /// its proxy, TLS and credential settings.
+    pub fn for_client(turbo_cdn: Arc<crate::TurboCdn>) -> Self {
+        Self {
+            config: SmartDownloadConfig::default(),
+            turbo_cdn,
+            verbose: false,
+        }
+    }
+
+    /// Set the speed test configuration
+    pub fn with_smart_config(mut self, config: SmartDownloadConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Print method selection details
+    pub fn with_verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
     /// Create a new smart downloader
+    #[deprecated(note = "Use SmartDownloader::for_client with a configured TurboCdn instead")]
     pub async fn new() -> Result<Self> {
+        #[allow(deprecated)]
         Self::new_with_verbose(false).await
     }
 
     /// Create a new smart downloader with verbose flag
+    #[deprecated(note = "Use SmartDownloader::for_client with a configured TurboCdn instead")]
     pub async fn new_with_verbose(verbose: bool) -> Result<Self> {
         let turbo_cdn = Arc::new(crate::TurboCdn::new().await?);
-        Ok(Self {
-            config: SmartDownloadConfig::default(),
-            turbo_cdn,
-            verbose,
-        })
+        Ok(Self::for_client(turbo_cdn).with_verbose(verbose))
     }
 
     /// Create a smart downloader with custom configuration
+    #[deprecated(note = "Use SmartDownloader::for_client with a configured TurboCdn instead")]
     pub async fn with_config(config: SmartDownloadConfig) -> Result<Self> {
+        #[allow(deprecated)]
         Self::with_config_and_verbose(config, false).await
     }
 
     /// Create a smart downloader with custom configuration and verbose flag
+    #[deprecated(note = "Use SmartDownloader::for_client with a configured TurboCdn instead")]
     pub async fn with_config_and_verbose(
         config: SmartDownloadConfig,
         verbose: bool,
     ) -> Result<Self> {
         let turbo_cdn = Arc::new(crate::TurboCdn::new().await?);
-        Ok(Self {
-            config,
-            turbo_cdn,
-            verbose,
-        })
+        Ok(Self::for_client(turbo_cdn)
+            .with_smart_config(config)
+            .with_verbose(verbose))
     }
 
     /// Smart download that automatically selects the best method
     pub async fn download_smart(&self, url: &str) -> Result<DownloadResult> {
+        self.session().download_smart(url).await
+    }
+
+    /// Download to specific path with smart method selection
+    pub async fn download_smart_to_path<P: AsRef<std::path::Path>>(
+        &self,
+        url: &str,
+        output_path: P,
+    ) -> Result<DownloadResult> {
+        self.session()
+            .download_smart_to_path(url, output_path)
+            .await
+    }
+
+    fn session(&self) -> SmartSession<'_> {
+        SmartSession {
+            config: &self.config,
+            turbo_cdn: &self.turbo_cdn,
+            verbose: self.verbose,
+        }
+    }
+}
+
+/// Speed tests and method selection on a borrowed client
+pub(crate) struct SmartSession<'a> {
+    pub(crate) config: &'a SmartDownloadConfig,
+    pub(crate) turbo_cdn: &'a crate::TurboCdn,
+    pub(crate) verbose: bool,
+}
+
+impl SmartSession<'_> {
+    /// Smart download that automatically selects the best method
+    pub(crate) async fn download_smart(&self, url: &str) -> Result<DownloadResult> {
         if self.verbose {
             cli_info!("🧠 Smart download starting for: {}", url);
         } else {
@@ -149,7 +207,7 @@ impl SmartDownloader {
     }
 
     /// Download to specific path with smart method selection
-    pub async fn download_smart_to_path<P: AsRef<std::path::Path>>(
+    pub(crate) async fn download_smart_to_path<P: AsRef<std::path::Path>>(
         &self,
         url: &str,
         output_path: P,
@@ -314,10 +372,7 @@ impl SmartDownloader {
 
     /// Perform a range request to test download speed
     async fn perform_range_request(&self, url: &str, direct: bool) -> Result<u64> {
-        // Initialize rustls provider before creating reqwest client
-        crate::init_rustls_provider();
-
-        let client = reqwest::Client::new();
+        let client = self.turbo_cdn.http_client();
         let test_url = if direct {
             url.to_string()
         } else {
@@ -330,12 +385,17 @@ impl SmartDownloader {
 
         let range_header = format!("bytes=0-{}", self.config.test_size - 1);
 
-        let response = client
-            .get(&test_url)
-            .header("Range", range_header)
-            .send()
-            .await
-            .map_err(|e| TurboCdnError::network(format!("Request failed: {e}")))?;
+        let request = client.get(&test_url).header("Range", range_header);
+        let response = self
+            .turbo_cdn
+            .middleware()
+            .send(
+                self.turbo_cdn.http_backend().as_ref(),
+                request,
+                RequestKind::SpeedTest,
+                |e| TurboCdnError::network(format!("Request failed: {e}")),
+            )
+            .await?;
 
         if !response.status().is_success() && response.status() != 206 {
             return Err(TurboCdnError::network(format!(
@@ -0,0 +1,640 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Signed remote rule-set subscriptions
+//!
+//! A subscription points at a TOML rule set with a `version` and its own
+//! `[[mirrors]]` and `[[url_mapping_rules]]`, plus a detached base64 ed25519
+//! signature over the exact file bytes. Rule sets are verified before use,
+//! both when fetched and when read back from the on-disk cache, and replace
+//! configured entries with the same name.
+//!
+//! A fetched set that fails verification, goes back in version or breaks its
+//! own rule checks is rejected and the last good set stays in use.
+
+use crate::config::{MirrorConfig, SubscriptionConfig, TurboCdnConfig, UrlMappingRuleConfig};
+use crate::error::{Result, TurboCdnError};
+use crate::http_backend::HttpBackend;
+use crate::rule_check;
+use base64::Engine;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
+
+/// Shortest interval between two update checks
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Largest rule set or signature accepted from a server
+const MAX_FETCH_SIZE: u64 = 4 * 1024 * 1024;
+
+/// First line of a cached rule set, followed by its signature
+const SIGNATURE_LINE: &str = "# turbo-cdn signature: ";
+
+/// A versioned set of mirrors and mapping rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleSet {
+    /// Version, a fetched set must not be older than the cached one
+    pub version: u64,
+    /// Mirrors added to or replacing the catalog
+    #[serde(default)]
+    pub mirrors: Vec<MirrorConfig>,
+    /// Rules added to or replacing the mapping rules
+    #[serde(default)]
+    pub url_mapping_rules: Vec<UrlMappingRuleConfig>,
+}
+
+impl RuleSet {
+    /// Merge the rule set into a configuration, entries replace those with
+    /// the same name
+    pub fn apply_to(&self, config: &mut TurboCdnConfig) {
+        for mirror in &self.mirrors {
+            match config.mirrors.iter_mut().find(|m| m.name == mirror.name) {
+                Some(existing) => *existing = mirror.clone(),
+                None => config.mirrors.push(mirror.clone()),
+            }
+        }
+        for rule in &self.url_mapping_rules {
+            match config
+                .url_mapping_rules
+                .iter_mut()
+                .find(|r| r.name == rule.name)
+            {
+                Some(existing) => *existing = rule.clone(),
+                None => config.url_mapping_rules.push(rule.clone()),
+            }
+        }
+    }
+}
+
+/// What happened when a subscription was refreshed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RefreshOutcome {
+    /// A newer rule set was fetched and verified
+    Updated,
+    /// The fetched rule set has the version already in use
+    Unchanged,
+    /// The cached rule set is recent enough, nothing was fetched
+    NotDue,
+    /// The update was rejected, the last good rule set stays in use
+    KeptLastGood { error: String },
+    /// The update was rejected and there is no good rule set
+    Unavailable { error: String },
+}
+
+/// Refresh result of a subscription
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriptionStatus {
+    /// Subscription name
+    pub name: String,
+    /// Version of the rule set in use, if any
+    pub version: Option<u64>,
+    /// What happened
+    pub outcome: RefreshOutcome,
+}
+
+/// A subscription with its verification key and cache location
+#[derive(Debug, Clone)]
+pub struct Subscription {
+    config: SubscriptionConfig,
+    key: VerifyingKey,
+    cache_file: PathBuf,
+}
+
+impl Subscription {
+    /// Create a subscription caching its rule set in `directory`
+    pub fn new(config: &SubscriptionConfig, directory: &Path) -> Result<Self> {
+        let key = decode_public_key(&config.public_key)
+            .map_err(|e| TurboCdnError::config(format!("Subscription '{}': {e}", config.name)))?;
+        let file_name: String = config
+            .name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            config: config.clone(),
+            key,
+            cache_file: directory.join(format!("{file_name}.toml")),
+        })
+    }
+
+    /// Subscription name
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// URL of the rule set
+    pub fn url(&self) -> &str {
+        &self.config.url
+    }
+
+    /// Where the last good rule set is cached
+    pub fn cache_file(&self) -> &Path {
+        &self.cache_file
+    }
+
+    /// Read and verify the cached rule set
+    pub fn load_cached(&self) -> Result<Option<RuleSet>> {
+        let cached = match std::fs::read(&self.cache_file) {
+            Ok(cached) => cached,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let signed = cached
+            .strip_prefix(SIGNATURE_LINE.as_bytes())
+            .and_then(|rest| {
+                let end = rest.iter().position(|&b| b == b'\n')?;
+                Some((&rest[..end], &rest[end + 1..]))
+            });
+        match signed {
+            Some((signature, content)) => self
+                .verify(content, &String::from_utf8_lossy(signature))
+                .map(Some),
+            None => {
+                // Caches written before the signature moved into the file
+                let signature = std::fs::read_to_string(signature_file(&self.cache_file))?;
+                self.verify(&cached, &signature).map(Some)
+            }
+        }
+    }
+
+    /// Whether the cached rule set is older than the refresh interval
+    pub fn is_due(&self) -> bool {
+        std::fs::metadata(&self.cache_file)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_none_or(|age| age >= self.refresh_interval())
+    }
+
+    /// Interval between update checks
+    pub fn refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.config.refresh_interval).max(MIN_REFRESH_INTERVAL)
+    }
+
+    /// Whether the rule set is checked for updates periodically, a
+    /// `refresh_interval` of 0 means only on demand
+    pub fn auto_refresh(&self) -> bool {
+        self.config.refresh_interval > 0
+    }
+
+    /// Fetch the rule set and its signature, and verify them
+    pub async fn fetch(&self, backend: &dyn HttpBackend) -> Result<(Vec<u8>, String, RuleSet)> {
+        let signature_url = self
+            .config
+            .signature_url
+            .clone()
+            .unwrap_or_else(|| format!("{}.sig", self.config.url));
+        let content = fetch_bytes(backend, &self.config.url).await?;
+        let signature =
+            String::from_utf8_lossy(&fetch_bytes(backend, &signature_url).await?).into_owned();
+        let rule_set = self.verify(&content, &signature)?;
+        Ok((content, signature, rule_set))
+    }
+
+    /// Store a verified rule set as the last good one
+    ///
+    /// The signature is kept in the same file as the set, so one rename
+    /// replaces both and a crash never leaves a mismatched pair behind.
+    fn store(&self, content: &[u8], signature: &str) -> Result<()> {
+        if let Some(parent) = self.cache_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut data = format!("{SIGNATURE_LINE}{}\n", signature.trim()).into_bytes();
+        data.extend_from_slice(content);
+
+        let tmp_path = self
+            .cache_file
+            .with_extension(format!("{}.tmp", std::process::id()));
+        std::fs::write(&tmp_path, data)?;
+        std::fs::rename(&tmp_path, &self.cache_file)?;
+
+        let _ = std::fs::remove_file(signature_file(&self.cache_file));
+        Ok(())
+    }
+
+    /// Mark the cached rule set as checked now
+    fn touch(&self) {
+        let result = std::fs::File::options()
+            .write(true)
+            .open(&self.cache_file)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        if let Err(e) = result {
+            debug!("Cannot touch {}: {}", self.cache_file.display(), e);
+        }
+    }
+
+    /// Verify the signature of a rule set and parse it
+    fn verify(&self, content: &[u8], signature: &str) -> Result<RuleSet> {
+        let signature = base64::engine::general_purpose::STANDARD
+            .decode(signature.trim())
+            .ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or_else(|| self.rejected("signature is not base64 ed25519"))?;
+        self.key
+            .verify(content, &signature)
+            .map_err(|_| self.rejected("signature does not match"))?;
+
+        let content =
+            std::str::from_utf8(content).map_err(|_| self.rejected("rule set is not UTF-8"))?;
+        toml::from_str(content).map_err(|e| self.rejected(&format!("invalid rule set: {e}")))
+    }
+
+    fn rejected(&self, reason: &str) -> TurboCdnError {
+        TurboCdnError::source_validation(format!(
+            "Rule set of subscription '{}' rejected: {reason}",
+            self.config.name
+        ))
+    }
+}
+
+/// The subscriptions of a configuration and their rule sets in use
+#[derive(Debug)]
+pub struct SubscriptionSet {
+    subscriptions: Vec<Subscription>,
+    /// Last good rule set of each subscription
+    current: Mutex<HashMap<String, RuleSet>>,
+}
+
+impl SubscriptionSet {
+    /// Set up the enabled subscriptions and load their cached rule sets
+    ///
+    /// Cached sets that fail verification are ignored with a warning.
+    pub fn new(config: &TurboCdnConfig) -> Result<Self> {
+        let directory = config.cache.resolve_directory().join("subscriptions");
+        let subscriptions = config
+            .subscriptions
+            .iter()
+            .filter(|subscription| subscription.enabled)
+            .map(|subscription| Subscription::new(subscription, &directory))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut current = HashMap::new();
+        for subscription in &subscriptions {
+            match subscription.load_cached() {
+                Ok(Some(rule_set)) => {
+                    debug!(
+                        "Loaded rule set '{}' version {}",
+                        subscription.name(),
+                        rule_set.version
+                    );
+                    current.insert(subscription.name().to_string(), rule_set);
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Ignoring cached rule set: {}", e),
+            }
+        }
+
+        Ok(Self {
+            subscriptions,
+            current: Mutex::new(current),
+        })
+    }
+
+    /// Whether there are no enabled subscriptions
+    pub fn is_empty(&self) -> bool {
+        self.subscriptions.is_empty()
+    }
+
+    /// The enabled subscriptions
+    pub fn subscriptions(&self) -> &[Subscription] {
+        &self.subscriptions
+    }
+
+    /// Version of the rule set in use for a subscription
+    pub fn version(&self, name: &str) -> Option<u64> {
+        self.lock().get(name).map(|rule_set| rule_set.version)
+    }
+
+    /// Interval at which the most eager subscription wants to be checked,
+    /// `None` if no subscription is refreshed automatically
+    pub fn refresh_interval(&self) -> Option<Duration> {
+        self.subscriptions
+            .iter()
+            .filter(|subscription| subscription.auto_refresh())
+            .map(Subscription::refresh_interval)
+            .min()
+    }
+
+    /// Merge the rule sets in use into a configuration, in subscription order
+    pub fn apply_to(&self, config: &mut TurboCdnConfig) {
+        let current = self.lock();
+        for subscription in &self.subscriptions {
+            if let Some(rule_set) = current.get(subscription.name()) {
+                rule_set.apply_to(config);
+            }
+        }
+    }
+
+    /// Fetch the automatically refreshed rule sets that are due, or all of
+    /// them with `force`
+    ///
+    /// Each fetched set is checked against `base` with the other rule sets
+    /// in use applied; rejected sets leave the last good set in place.
+    pub async fn refresh(
+        &self,
+        backend: &dyn HttpBackend,
+        base: &TurboCdnConfig,
+        force: bool,
+    ) -> Vec<SubscriptionStatus> {
+        let mut statuses = Vec::with_capacity(self.subscriptions.len());
+        for subscription in &self.subscriptions {
+            let name = subscription.name();
+            let in_use = self.version(name);
+            let due = force || (subscription.auto_refresh() && subscription.is_due());
+            let outcome = if !due {
+                RefreshOutcome::NotDue
+            } else {
+                match self.update(subscription, backend, base).await {
+                    Ok(outcome) => outcome,
+                    Err(e) => {
+                        warn!("{}", e);
+                        match in_use {
+                            Some(_) => RefreshOutcome::KeptLastGood {
+                                error: e.to_string(),
+                            },
+                            None => RefreshOutcome::Unavailable {
+                                error: e.to_string(),
+                            },
+                        }
+                    }
+                }
+            };
+
+            statuses.push(SubscriptionStatus {
+                name: name.to_string(),
+                version: self.version(name),
+                outcome,
+            });
+        }
+        statuses
+    }
+
+    /// Fetch, validate and adopt the rule set of one subscription
+    async fn update(
+        &self,
+        subscription: &Subscription,
+        backend: &dyn HttpBackend,
+        base: &TurboCdnConfig,
+    ) -> Result<RefreshOutcome> {
+        let (content, signature, rule_set) = subscription.fetch(backend).await?;
+
+        match self.version(subscription.name()) {
+            Some(version) if rule_set.version < version => {
+                return Err(subscription.rejected(&format!(
+                    "version {} is older than version {version} in use",
+                    rule_set.version
+                )));
+            }
+            Some(version) if rule_set.version == version => {
+                subscription.touch();
+                return Ok(RefreshOutcome::Unchanged);
+            }
+            _ => {}
+        }
+
+        self.check(subscription, &rule_set, base)?;
+        subscription.store(&content, &signature)?;
+        info!(
+            "Updated rule set '{}' to version {}",
+            subscription.name(),
+            rule_set.version
+        );
+        self.lock()
+            .insert(subscription.name().to_string(), rule_set);
+        Ok(RefreshOutcome::Updated)
+    }
+
+    /// Reject rule sets whose own rules don't compile or fail their examples
+    fn check(
+        &self,
+        subscription: &Subscription,
+        rule_set: &RuleSet,
+        base: &TurboCdnConfig,
+    ) -> Result<()> {
+        let mut config = base.clone();
+        {
+            let current = self.lock();
+            for other in &self.subscriptions {
+                if other.name() == subscription.name() {
+                    rule_set.apply_to(&mut config);
+                } else if let Some(other_set) = current.get(other.name()) {
+                    other_set.apply_to(&mut config);
+                }
+            }
+        }
+
+        let report = rule_check::check_rules(&config);
+        let errors: Vec<String> = report
+            .issues
+            .iter()
+            .filter(|issue| issue.severity == rule_check::Severity::Error)
+            .filter(|issue| {
+                issue.rule == "[[mirrors]]"
+                    || rule_set
+                        .url_mapping_rules
+                        .iter()
+                        .any(|rule| rule.name == issue.rule)
+            })
+            .map(|issue| format!("{}: {}", issue.rule, issue.message))
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(subscription.rejected(&errors.join("; ")))
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, RuleSet>> {
+        self.current
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Decode a base64 ed25519 public key
+pub fn decode_public_key(key: &str) -> Result<VerifyingKey> {
+    let bytes: [u8; 32] = base64::engine::general_purpose::STANDARD
+        .decode(key.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| TurboCdnError::config("public key is not a base64 ed25519 key"))?;
+    VerifyingKey::from_bytes(&bytes)
+        .map_err(|e| TurboCdnError::config(format!("invalid ed25519 public key: {e}")))
+}
+
+/// Signature file of caches written by earlier versions
+fn signature_file(cache_file: &Path) -> PathBuf {
+    cache_file.with_extension("toml.sig")
+}
+
+async fn fetch_bytes(backend: &dyn HttpBackend, url: &str) -> Result<Vec<u8>> {
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|e| TurboCdnError::config(format!("Invalid rule set URL {url}: {e}")))?;
+    let request = reqwest::Request::new(reqwest::Method::GET, parsed);
+    let response = crate::http_backend::execute(backend, request, |e| {
+        TurboCdnError::network(format!("Failed to fetch {url}: {e}"))
+    })
+    .await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(TurboCdnError::from_status_code(status.as_u16(), url));
+    }
+
+    let too_large =
+        || TurboCdnError::source_validation(format!("{url} is larger than {MAX_FETCH_SIZE} bytes"));
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_FETCH_SIZE)
+    {
+        return Err(too_large());
+    }
+    let mut response = response;
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| TurboCdnError::network(format!("Failed to read {url}: {e}")))?
+    {
+        if body.len() as u64 + chunk.len() as u64 > MAX_FETCH_SIZE {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const RULE_SET: &str = r#"
+version = 3
+
+[[mirrors]]
+name = "team"
+url = "https://mirror.team.example/"
+
+[[url_mapping_rules]]
+name = "Team"
+pattern = "^https://github\\.com/team/(.+)$"
+replacements = [{ mirror = "team" }, "https://github.com/team/$1"]
+regions = []
+priority = 1
+enabled = true
+"#;
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_bytes(&[7; 32])
+    }
+
+    fn subscription(directory: &Path) -> Subscription {
+        let config = SubscriptionConfig {
+            name: "team/rules".to_string(),
+            url: "https://rules.example/team.toml".to_string(),
+            signature_url: None,
+            public_key: base64::engine::general_purpose::STANDARD
+                .encode(signing_key().verifying_key().as_bytes()),
+            refresh_interval: 3600,
+            enabled: true,
+        };
+        Subscription::new(&config, directory).unwrap()
+    }
+
+    fn sign(content: &str) -> String {
+        base64::engine::general_purpose::STANDARD
+            .encode(signing_key().sign(content.as_bytes()).to_bytes())
+    }
+
+    #[test]
+    fn test_verify_and_apply() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let subscription = subscription(dir.path());
+        assert_eq!(
+            subscription.cache_file(),
+            dir.path().join("team_rules.toml")
+        );
+
+        let rule_set = subscription
+            .verify(RULE_SET.as_bytes(), &sign(RULE_SET))
+            .unwrap();
+        assert_eq!(rule_set.version, 3);
+
+        let tampered = RULE_SET.replace("version = 3", "version = 4");
+        assert!(subscription
+            .verify(tampered.as_bytes(), &sign(RULE_SET))
+            .is_err());
+        assert!(subscription.verify(RULE_SET.as_bytes(), "garbage").is_err());
+
+        let mut config = TurboCdnConfig::load().unwrap();
+        let rules = config.url_mapping_rules.len();
+        rule_set.apply_to(&mut config);
+        rule_set.apply_to(&mut config);
+        assert_eq!(config.url_mapping_rules.len(), rules + 1);
+        assert_eq!(
+            config.mirrors.iter().filter(|m| m.name == "team").count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let subscription = subscription(dir.path());
+        assert!(subscription.load_cached().unwrap().is_none());
+        assert!(subscription.is_due());
+
+        subscription
+            .store(RULE_SET.as_bytes(), &sign(RULE_SET))
+            .unwrap();
+        assert_eq!(subscription.load_cached().unwrap().unwrap().version, 3);
+        assert!(!subscription.is_due());
+
+        // A cached set modified on disk is not trusted
+        let cached = std::fs::read_to_string(subscription.cache_file()).unwrap();
+        std::fs::write(subscription.cache_file(), cached.replace("= 3", "= 9")).unwrap();
+        assert!(subscription.load_cached().is_err());
+    }
+
+    #[test]
+    fn test_legacy_cache_with_signature_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let subscription = subscription(dir.path());
+        std::fs::write(subscription.cache_file(), RULE_SET).unwrap();
+        std::fs::write(signature_file(subscription.cache_file()), sign(RULE_SET)).unwrap();
+        assert_eq!(subscription.load_cached().unwrap().unwrap().version, 3);
+
+        subscription
+            .store(RULE_SET.as_bytes(), &sign(RULE_SET))
+            .unwrap();
+        assert!(!signature_file(subscription.cache_file()).exists());
+        assert_eq!(subscription.load_cached().unwrap().unwrap().version, 3);
+    }
+
+    #[test]
+    fn test_invalid_public_key() {
+        let config = SubscriptionConfig {
+            name: "broken".to_string(),
+            url: "https://rules.example/broken.toml".to_string(),
+            signature_url: None,
+            public_key: "not a key".to_string(),
+            refresh_interval: 3600,
+            enabled: true,
+        };
+        assert!(Subscription::new(&config, Path::new(".")).is_err());
+    }
+}
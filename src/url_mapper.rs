@@ -7,10 +7,21 @@
 //! based on regex patterns and geographic location.
 
 use crate::cdn_quality::CdnQualityAssessor;
-use crate::config::{Region, TurboCdnConfig, UrlMappingRuleConfig};
+use crate::config::{
+    Region, RuleMode, TurboCdnConfig, UrlMappingRuleConfig, DEFAULT_REPLACEMENT_WEIGHT,
+};
+use crate::constants::DEFAULT_SERVER_SCORE;
+use crate::credentials::CredentialStore;
 use crate::error::{Result, TurboCdnError};
+use crate::geo_detection::RegionSource;
+use crate::mirror::{MirrorCatalog, SharedMirrorCatalog};
 use crate::server_tracker::ServerTracker;
+use crate::url_policy::{PolicyReport, RejectedMirror, UrlPolicy};
+use crate::url_resolver::ResolverOutput;
+use crate::url_template::{TemplateContext, UrlTemplate};
 use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
@@ -20,10 +31,18 @@ use dashmap::DashMap;
 /// URL mapping rule configuration
 #[derive(Debug, Clone)]
 pub struct UrlMappingRule {
+    /// Rule name for identification
+    pub name: String,
     /// Regex pattern to match URLs
     pub pattern: Regex,
     /// Replacement URL templates (in priority order)
     pub replacements: Vec<String>,
+    /// Parsed replacement templates, in the same order as `replacements`
+    pub templates: Vec<UrlTemplate>,
+    /// Static weight of each replacement, higher is tried earlier
+    pub weights: Vec<u32>,
+    /// Catalog mirror each replacement refers to, if any
+    pub mirrors: Vec<Option<String>>,
     /// Applicable regions for this rule
     pub regions: Vec<Region>,
     /// Priority (lower = higher priority)
@@ -32,6 +51,26 @@ pub struct UrlMappingRule {
     pub enabled: bool,
 }
 
+impl UrlMappingRule {
+    /// Render every replacement for a URL, ignoring mirror availability
+    ///
+    /// Returns `None` if the pattern doesn't match. Replacements that can't
+    /// be rendered yield the reason instead of a URL.
+    pub fn render(
+        &self,
+        url: &str,
+        region: &Region,
+    ) -> Option<Vec<std::result::Result<String, String>>> {
+        let captures = self.pattern.captures(url)?;
+        let context = TemplateContext {
+            captures: &captures,
+            url,
+            region,
+        };
+        Some(self.templates.iter().map(|t| t.render(&context)).collect())
+    }
+}
+
 /// Cache entry for URL mappings
 #[derive(Debug, Clone)]
 struct CacheEntry {
@@ -57,6 +96,157 @@ impl CacheEntry {
     }
 }
 
+/// Resolution of a URL into ranked download candidates
+#[derive(Debug, Clone, Serialize)]
+pub struct UrlResolution {
+    /// URL that was resolved
+    pub url: String,
+    /// Region used for rule selection
+    pub region: String,
+    /// Name of the rule that matched, if any
+    pub matched_rule: Option<String>,
+    /// Names of all rules that contributed candidates, in priority order
+    pub matched_rules: Vec<String>,
+    /// Release tag a `releases/latest` URL was resolved to before mapping
+    pub resolved_tag: Option<String>,
+    /// Candidate URLs in the order they will be tried
+    pub candidates: Vec<ResolvedCandidate>,
+    /// Mirror rewriting policy decision
+    pub policy: PolicyReport,
+}
+
+/// A candidate URL with its observed server performance
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedCandidate {
+    /// Candidate URL
+    pub url: String,
+    /// Performance score (0.0 - 1.0), `None` when the server was never used
+    pub score: Option<f64>,
+    /// Success rate of previous downloads (0.0 - 1.0)
+    pub success_rate: Option<f64>,
+    /// Average download speed in bytes per second
+    pub average_speed: Option<f64>,
+    /// Number of download attempts recorded
+    pub attempts: u32,
+}
+
+/// Why a URL was mapped the way it was
+#[derive(Debug, Clone, Serialize)]
+pub struct UrlExplanation {
+    /// URL that was explained
+    pub url: String,
+    /// Release tag a `releases/latest` URL was resolved to before mapping
+    pub resolved_tag: Option<String>,
+    /// Region used for rule selection
+    pub region: String,
+    /// How the region was determined
+    pub region_source: RegionSource,
+    /// How matching rules are combined
+    pub rule_mode: RuleMode,
+    /// Whether mirrors are bypassed because the client is offline
+    pub offline: bool,
+    /// Every rule in priority order and what happened to it
+    pub rules: Vec<RuleTrace>,
+    /// Generated candidates, ranked by their blended score
+    pub candidates: Vec<ExplainedCandidate>,
+    /// Whether `map_url` answers from the mapping cache
+    pub cache_hit: bool,
+    /// URLs `map_url` returns, in order; the cached order on a cache hit
+    pub urls: Vec<String>,
+    /// Mirror rewriting policy decision
+    pub policy: PolicyReport,
+}
+
+/// A rule considered while mapping a URL
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleTrace {
+    /// Rule name
+    pub name: String,
+    /// Rule priority
+    pub priority: u32,
+    /// What happened to the rule
+    pub outcome: RuleOutcome,
+}
+
+/// Outcome of a rule for a URL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RuleOutcome {
+    /// The rule contributed candidates
+    Used,
+    /// The rule is disabled
+    Disabled,
+    /// The rule doesn't apply to the current region
+    OtherRegion,
+    /// The pattern doesn't match the URL
+    NoMatch,
+    /// The pattern matches, but the policy forbids rewriting the URL
+    PolicyDenied { reason: String },
+    /// The pattern matches, but `first` mode already used a higher-priority rule
+    NotReached,
+}
+
+impl std::fmt::Display for RuleOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Used => write!(f, "used"),
+            Self::Disabled => write!(f, "skipped, disabled"),
+            Self::OtherRegion => write!(f, "skipped, not for this region"),
+            Self::NoMatch => write!(f, "no match"),
+            Self::PolicyDenied { reason } => write!(f, "matched, denied by policy: {reason}"),
+            Self::NotReached => write!(f, "matched, but a higher-priority rule is used"),
+        }
+    }
+}
+
+/// A candidate URL with the inputs of its ranking
+#[derive(Debug, Clone, Serialize)]
+pub struct ExplainedCandidate {
+    /// Candidate URL
+    pub url: String,
+    /// Rule or resolver that generated the candidate, `None` for the original URL
+    pub rule: Option<String>,
+    /// Static replacement weight
+    pub weight: u32,
+    /// Details reported by the resolver that generated the candidate
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    /// Server performance score (0.0 - 1.0), `None` when the server was never used
+    pub performance: Option<f64>,
+    /// Success rate of previous downloads (0.0 - 1.0)
+    pub success_rate: Option<f64>,
+    /// Average download speed in bytes per second
+    pub average_speed: Option<f64>,
+    /// Number of download attempts recorded
+    pub attempts: u32,
+    /// Weight blended with performance, candidates are tried highest first
+    pub score: f64,
+}
+
+/// A generated candidate URL with its static weight
+#[derive(Debug, Clone)]
+struct Candidate {
+    url: String,
+    weight: u32,
+    /// Rule or resolver that generated the candidate, `None` for the original URL
+    rule: Option<String>,
+    metadata: BTreeMap<String, String>,
+}
+
+/// Normalize a URL for deduplication
+///
+/// Parsing lowercases the scheme and host, drops default ports and resolves
+/// dot segments; the fragment never reaches the server so it is ignored.
+fn normalize_url(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(mut parsed) => {
+            parsed.set_fragment(None);
+            parsed.into()
+        }
+        Err(_) => url.to_string(),
+    }
+}
+
 /// High-performance concurrent cache
 type CacheMap = DashMap<String, CacheEntry>;
 
@@ -64,11 +254,19 @@ type CacheMap = DashMap<String, CacheEntry>;
 #[derive(Debug)]
 pub struct UrlMapper {
     rules: Vec<UrlMappingRule>,
+    mirrors: Arc<MirrorCatalog>,
+    /// Where the catalog is published for the downloader
+    shared_mirrors: SharedMirrorCatalog,
     current_region: Region,
+    region_source: RegionSource,
     cache: CacheMap,
     cache_enabled: bool,
     cache_ttl: Duration,
     max_cache_entries: usize,
+    offline: bool,
+    policy: UrlPolicy,
+    rule_mode: RuleMode,
+    performance_weight: f64,
     server_tracker: Arc<Mutex<ServerTracker>>,
     #[allow(dead_code)]
     quality_assessor: Option<Arc<CdnQualityAssessor>>,
@@ -82,14 +280,17 @@ impl UrlMapper {
 
     /// Create a new URL mapper with configuration
     pub fn new(config: &TurboCdnConfig, region: Region) -> Result<Self> {
+        let mirrors = MirrorCatalog::new(&config.mirrors, &config.mapping.disabled_mirrors)?;
         let mut rules = Vec::new();
 
         // Load rules from configuration
         for rule_config in &config.url_mapping_rules {
-            if let Ok(rule) = Self::create_rule_from_config(rule_config) {
-                rules.push(rule);
-            } else {
-                warn!("Failed to create rule from config: {}", rule_config.name);
+            match Self::create_rule_from_config(rule_config, &mirrors) {
+                Ok(rule) => rules.push(rule),
+                Err(e) => warn!(
+                    "Skipping URL mapping rule '{}': {} (run `turbo-cdn rules check` for details)",
+                    rule_config.name, e
+                ),
             }
         }
 
@@ -108,30 +309,66 @@ impl UrlMapper {
             region
         );
 
+        // Private hosts stay off public mirrors
+        let credentials = CredentialStore::from_config(&config.credentials)?;
+
         // CDN quality assessor will be created separately to avoid async complexity
         let quality_assessor = None;
 
+        let mirrors = Arc::new(mirrors);
         Ok(Self {
             rules,
+            shared_mirrors: Arc::new(std::sync::RwLock::new(mirrors.clone())),
+            mirrors,
             current_region: region,
+            region_source: RegionSource::default(),
             cache: Self::create_cache(),
             cache_enabled: config.general.enable_url_cache,
             cache_ttl: Duration::from_secs(config.general.url_cache_ttl),
             max_cache_entries: config.general.max_cache_entries,
+            offline: config.general.offline,
+            policy: UrlPolicy::new(&config.policy).with_credential_hosts(credentials.hosts()),
+            rule_mode: config.mapping.mode,
+            performance_weight: config.mapping.performance_weight.clamp(0.0, 1.0),
             server_tracker: Arc::new(Mutex::new(ServerTracker::new())),
             quality_assessor,
         })
     }
 
-    /// Create a rule from configuration
-    fn create_rule_from_config(config: &UrlMappingRuleConfig) -> Result<UrlMappingRule> {
+    /// Create a rule from configuration, resolving mirror references
+    pub(crate) fn create_rule_from_config(
+        config: &UrlMappingRuleConfig,
+        mirrors: &MirrorCatalog,
+    ) -> Result<UrlMappingRule> {
         let pattern = Regex::new(&config.pattern).map_err(|e| {
             TurboCdnError::config(format!("Invalid regex pattern '{}': {e}", config.pattern))
         })?;
 
+        let templates = config
+            .replacements
+            .iter()
+            .map(|replacement| {
+                let template = match (replacement.url(), replacement.mirror()) {
+                    (Some(url), _) => UrlTemplate::parse(url)?,
+                    (None, Some(mirror)) => mirrors.template(mirror)?,
+                    (None, None) => unreachable!("replacement is a URL or a mirror"),
+                };
+                template.validate(&pattern)?;
+                Ok(template)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
         Ok(UrlMappingRule {
+            name: config.name.clone(),
             pattern,
-            replacements: config.replacements.clone(),
+            replacements: templates.iter().map(|t| t.as_str().to_string()).collect(),
+            templates,
+            weights: config.replacements.iter().map(|r| r.weight()).collect(),
+            mirrors: config
+                .replacements
+                .iter()
+                .map(|r| r.mirror().map(str::to_string))
+                .collect(),
             regions: config.regions.clone(),
             priority: config.priority,
             enabled: config.enabled,
@@ -142,68 +379,197 @@ impl UrlMapper {
     ///
     /// This is a non-mutable version that works with RwLock read guards.
     pub fn map_url(&self, original_url: &str) -> Result<Vec<String>> {
+        self.map_url_with(original_url, &[])
+    }
+
+    /// Map a URL, merging the candidates of [`UrlResolver`]s with those of the rules
+    ///
+    /// The merged candidates are ranked and cached together.
+    ///
+    /// [`UrlResolver`]: crate::url_resolver::UrlResolver
+    pub fn map_url_with(
+        &self,
+        original_url: &str,
+        resolved: &[ResolverOutput],
+    ) -> Result<Vec<String>> {
         debug!("Mapping URL: {}", original_url);
 
+        // Mirrors cannot be reached offline, only the original URL identifies the resource
+        if self.offline {
+            return Ok(vec![original_url.to_string()]);
+        }
+
         // Check cache first
+        if let Some(urls) = self.cached_urls(original_url) {
+            debug!("Cache hit for URL: {}", original_url);
+            return Ok(urls);
+        }
+
+        let (candidates, _) = self.generate_candidates(original_url, resolved);
+
+        // Blend static weights with observed server performance
+        let mapped_urls = self.rank_candidates(candidates);
+
+        // Cache the result
         if self.cache_enabled {
-            if let Some(entry) = self.cache.get(original_url) {
-                if !entry.is_expired() {
-                    debug!("Cache hit for URL: {}", original_url);
-                    return Ok(entry.urls.clone());
-                }
-            }
+            self.cache_result(original_url.to_string(), mapped_urls.clone());
         }
 
-        let mut mapped_urls = Vec::new();
+        debug!("Mapped to {} URLs: {:?}", mapped_urls.len(), mapped_urls);
+        Ok(mapped_urls)
+    }
 
-        // Try to match against rules
-        for rule in &self.rules {
-            if !rule.enabled {
-                continue;
-            }
+    /// Mapped URLs cached for a URL, if the cache is enabled and fresh
+    pub fn cached_urls(&self, url: &str) -> Option<Vec<String>> {
+        if !self.cache_enabled {
+            return None;
+        }
+        self.cache
+            .get(url)
+            .filter(|entry| !entry.is_expired())
+            .map(|entry| entry.urls.clone())
+    }
 
-            // Check if rule applies to current region
-            if !rule.regions.is_empty() && !rule.regions.contains(&self.current_region) {
-                continue;
+    /// Clear the mapping cache
+    pub fn clear_cache(&self) {
+        self.cache.clear();
+    }
+
+    /// Evaluate the mirror rewriting policy for a URL
+    pub fn evaluate_policy(&self, url: &str) -> PolicyReport {
+        self.generate_candidates(url, &[]).1
+    }
+
+    /// Generate weighted candidates from the matching rules and the resolver
+    /// output, subject to the policy
+    ///
+    /// In `first` mode only the highest-priority matching rule is used, in
+    /// `merge` mode the candidates of all matching rules are combined.
+    /// Resolver candidates follow the rule candidates. Candidates are
+    /// deduplicated by normalized URL, keeping the highest weight. The
+    /// original URL is always the last candidate.
+    fn generate_candidates(
+        &self,
+        original_url: &str,
+        resolved: &[ResolverOutput],
+    ) -> (Vec<Candidate>, PolicyReport) {
+        let mut candidates: Vec<Candidate> = Vec::new();
+        let mut index = HashMap::new();
+        let original_key = normalize_url(original_url);
+        let mut original_weight = None;
+        let mut report = PolicyReport {
+            rewrite_allowed: true,
+            ..Default::default()
+        };
+        let mut matched = false;
+        let mut denied = None;
+
+        let mut add = |url: String, weight: u32, source: &str, metadata: &BTreeMap<_, _>| {
+            let key = normalize_url(&url);
+            if key == original_key {
+                original_weight = Some(original_weight.unwrap_or(0).max(weight));
+                return;
+            }
+            if let Some(&existing) = index.get(&key) {
+                let candidate: &mut Candidate = &mut candidates[existing];
+                candidate.weight = candidate.weight.max(weight);
+                return;
             }
 
-            // Try to match the pattern
-            if let Some(captures) = rule.pattern.captures(original_url) {
-                debug!(
-                    "Matched rule '{}' with pattern: {}",
-                    rule.pattern.as_str(),
-                    rule.pattern.as_str()
+            match self.policy.check_mirror(&url) {
+                Ok(()) => {
+                    index.insert(key, candidates.len());
+                    candidates.push(Candidate {
+                        url,
+                        weight,
+                        rule: Some(source.to_string()),
+                        metadata: metadata.clone(),
+                    });
+                }
+                Err(reason) => {
+                    debug!("Skipping mirror {}: {}", url, reason);
+                    report.rejected_mirrors.push(RejectedMirror { url, reason });
+                }
+            }
+        };
+
+        for (rule, captures) in self.matching_rules_with_captures(original_url) {
+            debug!(
+                "Matched rule '{}' with pattern: {}",
+                rule.name,
+                rule.pattern.as_str()
+            );
+
+            if let Err(reason) = self.policy.check_rewrite(original_url, &rule.name) {
+                info!(
+                    "Not rewriting {} with '{}': {}",
+                    original_url, rule.name, reason
                 );
-
-                // Generate replacement URLs
-                for template in &rule.replacements {
-                    if let Ok(mapped_url) = Self::apply_template(template, &captures) {
-                        if mapped_url != original_url && !mapped_urls.contains(&mapped_url) {
-                            mapped_urls.push(mapped_url);
-                        }
+                denied.get_or_insert(reason);
+                continue;
+            }
+            matched = true;
+
+            let context = TemplateContext {
+                captures: &captures,
+                url: original_url,
+                region: &self.current_region,
+            };
+
+            // Generate replacement URLs
+            let replacements = rule.templates.iter().zip(&rule.weights).zip(&rule.mirrors);
+            for ((template, &weight), mirror) in replacements {
+                if let Some(mirror) = mirror {
+                    if !self.mirrors.serves(mirror, &self.current_region) {
+                        debug!("Skipping unavailable mirror '{}'", mirror);
+                        continue;
                     }
                 }
 
-                // Only use the first matching rule
-                break;
+                match template.render(&context) {
+                    Ok(url) => add(url, weight, &rule.name, &BTreeMap::new()),
+                    Err(reason) => debug!("Skipping template {}: {}", template, reason),
+                }
             }
         }
 
-        // Always include the original URL as fallback
-        mapped_urls.push(original_url.to_string());
-
-        // Sort URLs by server performance if we have performance data
-        if mapped_urls.len() > 1 {
-            mapped_urls = self.sort_urls_by_performance(mapped_urls);
+        // Resolvers are subject to the policy like rules, under their own name
+        for output in resolved {
+            if let Err(reason) = self.policy.check_rewrite(original_url, &output.resolver) {
+                info!(
+                    "Not rewriting {} with '{}': {}",
+                    original_url, output.resolver, reason
+                );
+                denied.get_or_insert(reason);
+                continue;
+            }
+            matched = true;
+
+            for candidate in &output.candidates {
+                add(
+                    candidate.url.clone(),
+                    candidate.weight,
+                    &output.resolver,
+                    &candidate.metadata,
+                );
+            }
         }
 
-        // Cache the result
-        if self.cache_enabled {
-            self.cache_result(original_url.to_string(), mapped_urls.clone());
+        // Rewriting is denied when no matching rule was allowed
+        if let (false, Some(reason)) = (matched, denied) {
+            report.rewrite_allowed = false;
+            report.reason = Some(reason);
         }
 
-        debug!("Mapped to {} URLs: {:?}", mapped_urls.len(), mapped_urls);
-        Ok(mapped_urls)
+        // Always include the original URL as fallback
+        candidates.push(Candidate {
+            url: original_url.to_string(),
+            weight: original_weight.unwrap_or(DEFAULT_REPLACEMENT_WEIGHT),
+            rule: None,
+            metadata: BTreeMap::new(),
+        });
+
+        (candidates, report)
     }
 
     /// Map a URL (mutable version for backward compatibility)
@@ -222,17 +588,18 @@ impl UrlMapper {
             pattern: r"^https://github\.com/([^/]+)/([^/]+)/releases/download/([^/]+)/(.+)$"
                 .to_string(),
             replacements: vec![
-                "https://ghproxy.net/https://github.com/$1/$2/releases/download/$3/$4".to_string(),
-                "https://github.com/$1/$2/releases/download/$3/$4".to_string(),
+                "https://ghproxy.net/https://github.com/$1/$2/releases/download/$3/$4".into(),
+                "https://github.com/$1/$2/releases/download/$3/$4".into(),
             ],
             regions: vec![Region::Global],
             priority: 100, // Lower priority than config rules
             enabled: true,
+            examples: vec![],
         }];
 
         let mut rules = Vec::new();
         for config in fallback_configs {
-            if let Ok(rule) = Self::create_rule_from_config(&config) {
+            if let Ok(rule) = Self::create_rule_from_config(&config, &MirrorCatalog::default()) {
                 rules.push(rule);
             }
         }
@@ -240,25 +607,45 @@ impl UrlMapper {
         Ok(rules)
     }
 
-    /// Apply template with regex captures
-    fn apply_template(template: &str, captures: &regex::Captures) -> Result<String> {
-        let mut result = template.to_string();
+    /// Get the highest-priority rule that applies to a URL in the current region, if any
+    pub fn matched_rule(&self, url: &str) -> Option<&UrlMappingRule> {
+        self.matching_rules_with_captures(url)
+            .next()
+            .map(|(rule, _)| rule)
+    }
 
-        // Replace $1, $2, etc. with capture groups
-        for (i, capture) in captures.iter().enumerate() {
-            if let Some(matched) = capture {
-                let placeholder = format!("${i}");
-                result = result.replace(&placeholder, matched.as_str());
-            }
-        }
+    /// Get every rule whose candidates are used for a URL
+    ///
+    /// At most one rule in `first` mode, all matching rules in `merge` mode.
+    pub fn matching_rules(&self, url: &str) -> Vec<&UrlMappingRule> {
+        self.matching_rules_with_captures(url)
+            .map(|(rule, _)| rule)
+            .collect()
+    }
 
-        Ok(result)
+    /// Enabled rules for the current region matching a URL, in priority order
+    fn matching_rules_with_captures<'a, 'u>(
+        &'a self,
+        url: &'u str,
+    ) -> impl Iterator<Item = (&'a UrlMappingRule, regex::Captures<'u>)> + use<'a, 'u> {
+        let limit = match self.rule_mode {
+            RuleMode::First => 1,
+            RuleMode::Merge => usize::MAX,
+        };
+
+        self.rules
+            .iter()
+            .filter(|rule| rule.enabled)
+            .filter(|rule| rule.regions.is_empty() || rule.regions.contains(&self.current_region))
+            .filter_map(move |rule| rule.pattern.captures(url).map(|captures| (rule, captures)))
+            .take(limit)
     }
 
     /// Update current region
     pub fn set_region(&mut self, region: Region) {
         info!("Updated URL mapper region to: {:?}", region);
         self.current_region = region;
+        self.region_source = RegionSource::Manual;
     }
 
     /// Get current region
@@ -266,27 +653,148 @@ impl UrlMapper {
         &self.current_region
     }
 
-    /// Sort URLs by server performance (best performing first)
-    /// Note: CDN quality assessment is handled separately to avoid async complexity
-    fn sort_urls_by_performance(&self, mut urls: Vec<String>) -> Vec<String> {
-        // Use server tracker performance for sorting
-        if let Ok(tracker) = self.server_tracker.lock() {
-            urls.sort_by(|a, b| {
-                let score_a = tracker
-                    .get_stats(a)
-                    .map(|s| s.performance_score())
-                    .unwrap_or(0.0);
-                let score_b = tracker
-                    .get_stats(b)
-                    .map(|s| s.performance_score())
-                    .unwrap_or(0.0);
-                // Higher score = better performance, so reverse order
-                score_b
-                    .partial_cmp(&score_a)
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            });
+    /// Record how the current region was determined
+    pub fn with_region_source(mut self, source: RegionSource) -> Self {
+        self.region_source = source;
+        self
+    }
+
+    /// Get how the current region was determined
+    pub fn region_source(&self) -> &RegionSource {
+        &self.region_source
+    }
+
+    /// Explain how a URL is mapped: the rules tried, the candidates they
+    /// generated and the scores that ordered them
+    ///
+    /// Unlike `map_url` this neither reads nor fills the mapping cache, it
+    /// only reports whether `map_url` would answer from it.
+    pub fn explain(&self, url: &str) -> UrlExplanation {
+        self.explain_with(url, &[])
+    }
+
+    /// Explain how a URL is mapped with the candidates of [`UrlResolver`]s
+    ///
+    /// [`UrlResolver`]: crate::url_resolver::UrlResolver
+    pub fn explain_with(&self, url: &str, resolved: &[ResolverOutput]) -> UrlExplanation {
+        let cached = self.cached_urls(url);
+
+        let (rules, candidates, policy) = if self.offline {
+            let original = Candidate {
+                url: url.to_string(),
+                weight: DEFAULT_REPLACEMENT_WEIGHT,
+                rule: None,
+                metadata: BTreeMap::new(),
+            };
+            (
+                vec![],
+                self.score_candidates(vec![original]),
+                PolicyReport::default(),
+            )
+        } else {
+            let (candidates, policy) = self.generate_candidates(url, resolved);
+            (
+                self.trace_rules(url),
+                self.score_candidates(candidates),
+                policy,
+            )
+        };
+
+        UrlExplanation {
+            url: url.to_string(),
+            resolved_tag: None,
+            region: self.current_region.to_string(),
+            region_source: self.region_source.clone(),
+            rule_mode: self.rule_mode,
+            offline: self.offline,
+            rules,
+            cache_hit: cached.is_some(),
+            urls: cached.unwrap_or_else(|| candidates.iter().map(|c| c.url.clone()).collect()),
+            candidates,
+            policy,
         }
-        urls
+    }
+
+    /// Outcome of every rule for a URL, following `generate_candidates`
+    fn trace_rules(&self, url: &str) -> Vec<RuleTrace> {
+        let mut matched = 0;
+        self.rules
+            .iter()
+            .map(|rule| {
+                let outcome = if !rule.enabled {
+                    RuleOutcome::Disabled
+                } else if !rule.regions.is_empty() && !rule.regions.contains(&self.current_region) {
+                    RuleOutcome::OtherRegion
+                } else if !rule.pattern.is_match(url) {
+                    RuleOutcome::NoMatch
+                } else if self.rule_mode == RuleMode::First && matched > 0 {
+                    RuleOutcome::NotReached
+                } else {
+                    matched += 1;
+                    match self.policy.check_rewrite(url, &rule.name) {
+                        Ok(()) => RuleOutcome::Used,
+                        Err(reason) => RuleOutcome::PolicyDenied { reason },
+                    }
+                };
+                RuleTrace {
+                    name: rule.name.clone(),
+                    priority: rule.priority,
+                    outcome,
+                }
+            })
+            .collect()
+    }
+
+    /// Order candidates by static weight blended with server performance
+    ///
+    /// Servers without recorded downloads get the neutral default score, so
+    /// until there is data the order follows the weights and then the rule
+    /// order.
+    fn rank_candidates(&self, candidates: Vec<Candidate>) -> Vec<String> {
+        self.score_candidates(candidates)
+            .into_iter()
+            .map(|c| c.url)
+            .collect()
+    }
+
+    /// Score candidates and sort them, highest score first
+    fn score_candidates(&self, candidates: Vec<Candidate>) -> Vec<ExplainedCandidate> {
+        let max_weight = candidates
+            .iter()
+            .map(|c| c.weight)
+            .max()
+            .unwrap_or(0)
+            .max(1) as f64;
+        let tracker = self.server_tracker.lock().ok();
+
+        let mut scored: Vec<ExplainedCandidate> = candidates
+            .into_iter()
+            .map(|candidate| {
+                let stats = tracker.as_ref().and_then(|t| t.get_stats(&candidate.url));
+                let performance = stats.map(|s| s.performance_score());
+                let score = (1.0 - self.performance_weight) * candidate.weight as f64 / max_weight
+                    + self.performance_weight * performance.unwrap_or(DEFAULT_SERVER_SCORE);
+                ExplainedCandidate {
+                    performance,
+                    success_rate: stats.map(|s| s.success_rate),
+                    average_speed: stats.map(|s| s.average_speed),
+                    attempts: stats.map_or(0, |s| s.total_attempts),
+                    score,
+                    url: candidate.url,
+                    rule: candidate.rule,
+                    weight: candidate.weight,
+                    metadata: candidate.metadata,
+                }
+            })
+            .collect();
+
+        // Stable sort keeps rule order among equal scores
+        scored.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        scored
     }
 
     /// Get CDN quality assessor for external use
@@ -337,6 +845,40 @@ impl UrlMapper {
         self.server_tracker.clone()
     }
 
+    /// Rank candidates with a server tracker shared with the downloader
+    pub fn with_server_tracker(mut self, server_tracker: Arc<Mutex<ServerTracker>>) -> Self {
+        self.server_tracker = server_tracker;
+        self.cache.clear();
+        self
+    }
+
+    /// Publish the mirror catalog to a handle shared with the downloader
+    ///
+    /// The handle's catalog is replaced now and on every rebuild, so mirror
+    /// capabilities from reloads and rule sets apply to downloads too.
+    pub fn with_shared_mirrors(mut self, shared_mirrors: SharedMirrorCatalog) -> Self {
+        *shared_mirrors.write().unwrap() = self.mirrors.clone();
+        self.shared_mirrors = shared_mirrors;
+        self
+    }
+
+    /// Rebuild rules, mirrors and settings from a new configuration
+    ///
+    /// The region, its source, the server tracker and the shared mirror
+    /// catalog handle are kept, the mapping cache is cleared.
+    pub fn reconfigure(&mut self, config: &TurboCdnConfig) -> Result<()> {
+        *self = Self::new(config, self.current_region.clone())?
+            .with_server_tracker(self.server_tracker.clone())
+            .with_shared_mirrors(self.shared_mirrors.clone())
+            .with_region_source(self.region_source.clone());
+        Ok(())
+    }
+
+    /// Get the mirror catalog
+    pub fn mirrors(&self) -> &MirrorCatalog {
+        &self.mirrors
+    }
+
     /// Get number of active rules
     pub fn rule_count(&self) -> usize {
         self.rules.iter().filter(|rule| rule.enabled).count()
@@ -370,6 +912,106 @@ mod tests {
         assert!(mapped_urls.contains(&original_url.to_string()));
     }
 
+    #[test]
+    fn test_default_rules_load() {
+        let config = TurboCdnConfig::load().unwrap();
+        let mapper = UrlMapper::new(&config, Region::China).unwrap();
+        assert_eq!(mapper.rule_count(), config.url_mapping_rules.len());
+
+        let mapped_urls = mapper
+            .map_url("https://github.com/owner/repo/releases/download/v1/tool.zip")
+            .unwrap();
+        assert!(mapped_urls.contains(
+            &"https://ghproxy.net/https://github.com/owner/repo/releases/download/v1/tool.zip"
+                .to_string()
+        ));
+    }
+
+    #[test]
+    fn test_template_variables_in_rules() {
+        let config = TurboCdnConfig {
+            url_mapping_rules: vec![UrlMappingRuleConfig {
+            name: "Versioned mirror".to_string(),
+            pattern: r"^https://example\.com/(?P<name>[^/]+)/(?P<tag>v[^/]+)/(?P<file>.+)$"
+                .to_string(),
+            replacements: vec![
+                "https://mirror.example.org/${lower(region)}/${name}/${strip_prefix(tag, 'v')}/${file}"
+                    .into(),
+            ],
+            regions: vec![],
+            priority: 1,
+            enabled: true,
+            examples: vec![],
+        }],
+            ..Default::default()
+        };
+        let mapper = UrlMapper::new(&config, Region::Europe).unwrap();
+
+        let mapped_urls = mapper
+            .map_url("https://example.com/tool/v2.0/tool.tar.gz")
+            .unwrap();
+        assert_eq!(
+            mapped_urls[0],
+            "https://mirror.example.org/europe/tool/2.0/tool.tar.gz"
+        );
+    }
+
+    #[test]
+    fn test_invalid_template_rejects_rule() {
+        let config = UrlMappingRuleConfig {
+            name: "Broken".to_string(),
+            pattern: r"^https://example\.com/(.+)$".to_string(),
+            replacements: vec!["https://mirror.example.org/${missing}".into()],
+            regions: vec![],
+            priority: 1,
+            enabled: true,
+            examples: vec![],
+        };
+        assert!(UrlMapper::create_rule_from_config(&config, &MirrorCatalog::default()).is_err());
+    }
+
+    #[test]
+    fn test_matched_rule() {
+        let config = TurboCdnConfig::default();
+        let mapper = UrlMapper::new(&config, Region::Global).unwrap();
+
+        let rule = mapper
+            .matched_rule("https://github.com/owner/repo/releases/download/v1/tool.zip")
+            .unwrap();
+        assert_eq!(rule.name, "GitHub Releases - Global Fallback");
+        assert!(mapper
+            .matched_rule("https://example.com/file.zip")
+            .is_none());
+    }
+
+    #[test]
+    fn test_policy_blocks_rewrite() {
+        let mut config = TurboCdnConfig::default();
+        config.policy.deny_hosts = vec!["github.com".to_string()];
+        let mapper = UrlMapper::new(&config, Region::Global).unwrap();
+
+        let url = "https://github.com/owner/repo/releases/download/v1/tool.zip";
+        assert_eq!(mapper.map_url(url).unwrap(), vec![url.to_string()]);
+
+        let report = mapper.evaluate_policy(url);
+        assert!(!report.rewrite_allowed);
+        assert!(report.reason.unwrap().contains("github.com"));
+    }
+
+    #[test]
+    fn test_policy_trusted_mirrors() {
+        let mut config = TurboCdnConfig::default();
+        config.policy.trusted_mirrors = vec!["nonexistent.example".to_string()];
+        let mapper = UrlMapper::new(&config, Region::Global).unwrap();
+
+        let url = "https://github.com/owner/repo/releases/download/v1/tool.zip";
+        assert_eq!(mapper.map_url(url).unwrap(), vec![url.to_string()]);
+
+        let report = mapper.evaluate_policy(url);
+        assert!(report.rewrite_allowed);
+        assert!(!report.rejected_mirrors.is_empty());
+    }
+
     #[test]
     fn test_jsdelivr_url_mapping() {
         let config = TurboCdnConfig::default();
@@ -409,4 +1051,246 @@ mod tests {
             .count();
         assert_eq!(occurrences, 1, "original URL should not be duplicated");
     }
+
+    #[test]
+    fn test_rules_reference_catalog_mirrors() {
+        let url = "https://github.com/owner/repo/releases/download/v1/tool.zip";
+        let rewritten = "https://bgithub.xyz/owner/repo/releases/download/v1/tool.zip";
+        let proxied = format!("https://ghproxy.net/{url}");
+
+        let mut config = TurboCdnConfig::load().unwrap();
+        let mapper = UrlMapper::new(&config, Region::China).unwrap();
+        let mapped_urls = mapper.map_url(url).unwrap();
+        assert!(mapped_urls.contains(&rewritten.to_string()));
+        assert!(mapped_urls.contains(&proxied));
+
+        config.mapping.disabled_mirrors = vec!["bgithub".to_string()];
+        let mapper = UrlMapper::new(&config, Region::China).unwrap();
+        let mapped_urls = mapper.map_url(url).unwrap();
+        assert!(!mapped_urls.contains(&rewritten.to_string()));
+        assert!(mapped_urls.contains(&proxied));
+    }
+
+    #[test]
+    fn test_unknown_mirror_rejects_rule() {
+        let config = UrlMappingRuleConfig {
+            name: "Missing mirror".to_string(),
+            pattern: r"^https://example\.com/(.+)$".to_string(),
+            replacements: vec![crate::config::ReplacementConfig::Mirror {
+                mirror: "nowhere".to_string(),
+                weight: DEFAULT_REPLACEMENT_WEIGHT,
+            }],
+            regions: vec![],
+            priority: 1,
+            enabled: true,
+            examples: vec![],
+        };
+        assert!(UrlMapper::create_rule_from_config(&config, &MirrorCatalog::default()).is_err());
+    }
+
+    fn overlapping_rules(mode: RuleMode, performance_weight: f64) -> TurboCdnConfig {
+        use crate::config::{MappingConfig, ReplacementConfig};
+
+        TurboCdnConfig {
+            url_mapping_rules: vec![
+                UrlMappingRuleConfig {
+                    name: "GitHub".to_string(),
+                    pattern: r"^https://github\.com/(.+)$".to_string(),
+                    replacements: vec![
+                        "https://ghproxy.net/https://github.com/$1".into(),
+                        "https://github.com/$1".into(),
+                    ],
+                    regions: vec![],
+                    priority: 1,
+                    enabled: true,
+                    examples: vec![],
+                },
+                UrlMappingRuleConfig {
+                    name: "Team mirror".to_string(),
+                    pattern: r"^https://github\.com/team/(.+)$".to_string(),
+                    replacements: vec![
+                        ReplacementConfig::Weighted {
+                            url: "https://mirror.team.example/$1".to_string(),
+                            weight: 300,
+                        },
+                        ReplacementConfig::Weighted {
+                            url: "https://GHPROXY.net:443/https://github.com/team/$1#dup"
+                                .to_string(),
+                            weight: 150,
+                        },
+                    ],
+                    regions: vec![],
+                    priority: 2,
+                    enabled: true,
+                    examples: vec![],
+                },
+            ],
+            mapping: MappingConfig {
+                mode,
+                performance_weight,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_first_mode_uses_single_rule() {
+        let config = overlapping_rules(RuleMode::First, 0.0);
+        let mapper = UrlMapper::new(&config, Region::Global).unwrap();
+
+        let url = "https://github.com/team/tool/v1/tool.zip";
+        assert_eq!(mapper.matching_rules(url).len(), 1);
+        assert_eq!(
+            mapper.map_url(url).unwrap(),
+            vec![
+                "https://ghproxy.net/https://github.com/team/tool/v1/tool.zip".to_string(),
+                url.to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_mode_combines_weighted_candidates() {
+        let config = overlapping_rules(RuleMode::Merge, 0.0);
+        let mapper = UrlMapper::new(&config, Region::Global).unwrap();
+
+        let url = "https://github.com/team/tool/v1/tool.zip";
+        let names: Vec<_> = mapper
+            .matching_rules(url)
+            .iter()
+            .map(|rule| rule.name.as_str())
+            .collect();
+        assert_eq!(names, ["GitHub", "Team mirror"]);
+
+        // The duplicate ghproxy URL keeps its first spelling and the higher weight
+        assert_eq!(
+            mapper.map_url(url).unwrap(),
+            vec![
+                "https://mirror.team.example/tool/v1/tool.zip".to_string(),
+                "https://ghproxy.net/https://github.com/team/tool/v1/tool.zip".to_string(),
+                url.to_string(),
+            ]
+        );
+
+        // Only the generic rule applies to other repositories
+        let other = "https://github.com/owner/repo/v1/tool.zip";
+        assert_eq!(mapper.map_url(other).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_merge_mode_blends_server_performance() {
+        let config = overlapping_rules(RuleMode::Merge, 0.9);
+        let tracker = Arc::new(Mutex::new(ServerTracker::new()));
+        let mirror = "https://mirror.team.example/tool/v1/tool.zip";
+        for _ in 0..3 {
+            tracker
+                .lock()
+                .unwrap()
+                .record_failure(mirror, Duration::from_secs(2));
+        }
+        let mapper = UrlMapper::new(&config, Region::Global)
+            .unwrap()
+            .with_server_tracker(tracker);
+
+        let mapped_urls = mapper
+            .map_url("https://github.com/team/tool/v1/tool.zip")
+            .unwrap();
+        assert_eq!(mapped_urls.last().map(String::as_str), Some(mirror));
+    }
+
+    #[test]
+    fn test_explain_traces_rules_and_scores() {
+        let mut config = overlapping_rules(RuleMode::First, 0.5);
+        config.general.enable_url_cache = true;
+        let extra =
+            |name: &str, pattern: &str, regions: Vec<Region>, enabled: bool| UrlMappingRuleConfig {
+                name: name.to_string(),
+                pattern: pattern.to_string(),
+                replacements: vec!["https://other.example/$1".into()],
+                regions,
+                priority: 10,
+                enabled,
+                examples: vec![],
+            };
+        config.url_mapping_rules.extend([
+            extra("Off", r"^https://github\.com/(.+)$", vec![], false),
+            extra(
+                "China",
+                r"^https://github\.com/(.+)$",
+                vec![Region::China],
+                true,
+            ),
+            extra("npm", r"^https://registry\.npmjs\.org/(.+)$", vec![], true),
+        ]);
+
+        let tracker = Arc::new(Mutex::new(ServerTracker::new()));
+        let proxy = "https://ghproxy.net/https://github.com/team/tool.zip";
+        tracker
+            .lock()
+            .unwrap()
+            .record_failure(proxy, Duration::from_secs(1));
+        let mapper = UrlMapper::new(&config, Region::Global)
+            .unwrap()
+            .with_server_tracker(tracker)
+            .with_region_source(RegionSource::Configured);
+
+        let url = "https://github.com/team/tool.zip";
+        let explanation = mapper.explain(url);
+        let outcomes: Vec<_> = explanation
+            .rules
+            .iter()
+            .map(|r| (r.name.as_str(), r.outcome.clone()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("GitHub", RuleOutcome::Used),
+                ("Team mirror", RuleOutcome::NotReached),
+                ("Off", RuleOutcome::Disabled),
+                ("China", RuleOutcome::OtherRegion),
+                ("npm", RuleOutcome::NoMatch),
+            ]
+        );
+        assert!(!explanation.cache_hit);
+
+        // The failing proxy ranks below the original URL
+        let candidates = &explanation.candidates;
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].url, url);
+        assert_eq!(candidates[0].rule, None);
+        assert_eq!(candidates[1].url, proxy);
+        assert_eq!(candidates[1].rule.as_deref(), Some("GitHub"));
+        assert_eq!(candidates[1].attempts, 1);
+        assert!(candidates[1].performance.is_some());
+        assert!(candidates[0].score > candidates[1].score);
+        assert_eq!(mapper.map_url(url).unwrap(), explanation.urls);
+
+        assert!(mapper.explain(url).cache_hit);
+    }
+
+    #[test]
+    fn test_reconfigure_keeps_history_and_clears_cache() {
+        let mut config = overlapping_rules(RuleMode::First, 0.5);
+        config.general.enable_url_cache = true;
+        let tracker = Arc::new(Mutex::new(ServerTracker::new()));
+        let mut mapper = UrlMapper::new(&config, Region::Europe)
+            .unwrap()
+            .with_server_tracker(tracker.clone())
+            .with_region_source(RegionSource::Manual);
+
+        let url = "https://github.com/team/tool.zip";
+        mapper.map_url(url).unwrap();
+        assert!(mapper.explain(url).cache_hit);
+
+        config.url_mapping_rules[0].enabled = false;
+        mapper.reconfigure(&config).unwrap();
+
+        let explanation = mapper.explain(url);
+        assert!(!explanation.cache_hit);
+        assert_eq!(explanation.rules[0].outcome, RuleOutcome::Disabled);
+        assert_eq!(mapper.region(), &Region::Europe);
+        assert!(matches!(mapper.region_source(), RegionSource::Manual));
+        assert!(Arc::ptr_eq(&mapper.server_tracker, &tracker));
+    }
 }
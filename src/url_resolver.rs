@@ -0,0 +1,206 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Pluggable URL resolvers
+//!
+//! Regex rules can only rewrite a URL. A [`UrlResolver`] may look things up
+//! first, such as a registry mirror's package metadata or a list of Maven
+//! repositories, and returns candidates that are merged with the rule
+//! candidates before they are ranked by server performance.
+
+use crate::config::{Region, DEFAULT_REPLACEMENT_WEIGHT};
+use crate::error::Result;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use tracing::{debug, warn};
+
+/// Generates download candidates for a URL
+///
+/// # Example
+/// ```rust
+/// use async_trait::async_trait;
+/// use turbo_cdn::url_resolver::{ResolverCandidate, UrlResolver};
+/// use turbo_cdn::Region;
+///
+/// struct NpmMirror;
+///
+/// #[async_trait]
+/// impl UrlResolver for NpmMirror {
+///     fn name(&self) -> &str {
+///         "npm-mirror"
+///     }
+///
+///     async fn resolve(
+///         &self,
+///         url: &str,
+///         _region: &Region,
+///     ) -> turbo_cdn::Result<Vec<ResolverCandidate>> {
+///         Ok(url
+///             .strip_prefix("https://registry.npmjs.org/")
+///             .map(|path| format!("https://registry.npmmirror.com/{path}"))
+///             .map(ResolverCandidate::new)
+///             .into_iter()
+///             .collect())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait UrlResolver: Send + Sync {
+    /// Name reported as the source of the candidates
+    fn name(&self) -> &str;
+
+    /// Candidates for `url`, empty if the resolver doesn't handle it
+    ///
+    /// Errors are logged and the resolver is skipped for this URL.
+    async fn resolve(&self, url: &str, region: &Region) -> Result<Vec<ResolverCandidate>>;
+}
+
+impl std::fmt::Debug for dyn UrlResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UrlResolver")
+            .field("name", &self.name())
+            .finish()
+    }
+}
+
+/// A candidate URL returned by a resolver
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolverCandidate {
+    /// Candidate URL
+    pub url: String,
+    /// Static preference, higher is tried earlier; comparable to rule
+    /// replacement weights
+    pub weight: u32,
+    /// Free-form details shown by explain mode
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl ResolverCandidate {
+    /// Create a candidate with the default weight
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            weight: DEFAULT_REPLACEMENT_WEIGHT,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    /// Set the weight
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Add a metadata entry
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// Candidates a resolver returned for a URL
+#[derive(Debug, Clone)]
+pub struct ResolverOutput {
+    /// Resolver name
+    pub resolver: String,
+    /// Candidates in the order the resolver returned them
+    pub candidates: Vec<ResolverCandidate>,
+}
+
+/// Resolvers registered on a client, consulted in registration order
+#[derive(Default)]
+pub struct ResolverSet {
+    resolvers: RwLock<Vec<Arc<dyn UrlResolver>>>,
+}
+
+impl ResolverSet {
+    /// Create an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a resolver, replacing one with the same name
+    pub fn register(&self, resolver: Arc<dyn UrlResolver>) {
+        let mut resolvers = self.write();
+        match resolvers.iter_mut().find(|r| r.name() == resolver.name()) {
+            Some(existing) => *existing = resolver,
+            None => resolvers.push(resolver),
+        }
+    }
+
+    /// Remove a resolver by name, returning whether it was registered
+    pub fn remove(&self, name: &str) -> bool {
+        let mut resolvers = self.write();
+        let before = resolvers.len();
+        resolvers.retain(|r| r.name() != name);
+        resolvers.len() != before
+    }
+
+    /// Names of the registered resolvers
+    pub fn names(&self) -> Vec<String> {
+        self.read().iter().map(|r| r.name().to_string()).collect()
+    }
+
+    /// Whether no resolver is registered
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// Run every resolver on a URL concurrently
+    ///
+    /// Resolvers that fail or return nothing are left out.
+    pub async fn resolve(&self, url: &str, region: &Region) -> Vec<ResolverOutput> {
+        let resolvers = self.read().clone();
+        let results = futures::future::join_all(
+            resolvers
+                .iter()
+                .map(|resolver| resolver.resolve(url, region)),
+        )
+        .await;
+
+        resolvers
+            .iter()
+            .zip(results)
+            .filter_map(|(resolver, result)| match result {
+                Ok(candidates) if candidates.is_empty() => None,
+                Ok(candidates) => {
+                    debug!(
+                        "Resolver '{}' returned {} candidates for {}",
+                        resolver.name(),
+                        candidates.len(),
+                        url
+                    );
+                    Some(ResolverOutput {
+                        resolver: resolver.name().to_string(),
+                        candidates,
+                    })
+                }
+                Err(e) => {
+                    warn!("Resolver '{}' failed for {}: {}", resolver.name(), url, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Vec<Arc<dyn UrlResolver>>> {
+        self.resolvers
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Vec<Arc<dyn UrlResolver>>> {
+        self.resolvers
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl std::fmt::Debug for ResolverSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResolverSet")
+            .field("resolvers", &self.names())
+            .finish()
+    }
+}
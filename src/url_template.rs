@@ -0,0 +1,511 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Replacement templates for URL mapping rules
+//!
+//! Templates are parsed once when a rule is loaded and rendered for every
+//! matching URL. Supported syntax:
+//! - `$1`, `${1}`: positional capture groups (`$10` is group ten, not `$1`
+//!   followed by `0`)
+//! - `${owner}`: named capture groups from `(?P<owner>...)`
+//! - `${region}`, `${os}`, `${arch}`, `${filename}`, `${url}`, `${path}`:
+//!   built-in variables
+//! - `${lower(tag)}`, `${strip_prefix(tag, 'v')}`: function calls, which may
+//!   be nested and take variables or quoted strings as arguments
+//! - `$$`: a literal `$`
+
+use crate::config::Region;
+use crate::error::{Result, TurboCdnError};
+use regex::{Captures, Regex};
+use std::fmt::Write;
+
+/// Built-in variables available to every template
+pub const BUILTIN_VARIABLES: &[&str] = &["region", "os", "arch", "filename", "url", "path"];
+
+/// A parsed replacement template
+#[derive(Debug, Clone, PartialEq)]
+pub struct UrlTemplate {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    /// Positional capture group
+    Group(usize),
+    /// Named capture group or built-in variable
+    Variable(String),
+    /// Quoted string argument
+    Literal(String),
+    Call(Function, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    UrlEncode,
+    Lower,
+    Upper,
+    StripPrefix,
+    StripSuffix,
+    Replace,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "url_encode" => Some(Self::UrlEncode),
+            "lower" => Some(Self::Lower),
+            "upper" => Some(Self::Upper),
+            "strip_prefix" => Some(Self::StripPrefix),
+            "strip_suffix" => Some(Self::StripSuffix),
+            "replace" => Some(Self::Replace),
+            _ => None,
+        }
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Self::UrlEncode | Self::Lower | Self::Upper => 1,
+            Self::StripPrefix | Self::StripSuffix => 2,
+            Self::Replace => 3,
+        }
+    }
+
+    fn apply(self, args: &[String]) -> String {
+        match self {
+            Self::UrlEncode => url_encode(&args[0]),
+            Self::Lower => args[0].to_lowercase(),
+            Self::Upper => args[0].to_uppercase(),
+            Self::StripPrefix => args[0]
+                .strip_prefix(args[1].as_str())
+                .unwrap_or(&args[0])
+                .to_string(),
+            Self::StripSuffix => args[0]
+                .strip_suffix(args[1].as_str())
+                .unwrap_or(&args[0])
+                .to_string(),
+            Self::Replace => args[0].replace(args[1].as_str(), &args[2]),
+        }
+    }
+}
+
+/// Values a template is rendered with
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateContext<'a> {
+    /// Captures of the rule pattern against the URL
+    pub captures: &'a Captures<'a>,
+    /// URL being mapped
+    pub url: &'a str,
+    /// Current region
+    pub region: &'a Region,
+}
+
+impl TemplateContext<'_> {
+    fn variable(&self, name: &str) -> std::result::Result<String, String> {
+        if let Some(value) = self.captures.name(name) {
+            return Ok(value.as_str().to_string());
+        }
+
+        match name {
+            "region" => Ok(self.region.to_string()),
+            "os" => Ok(std::env::consts::OS.to_string()),
+            "arch" => Ok(std::env::consts::ARCH.to_string()),
+            "url" => Ok(self.url.to_string()),
+            "filename" => Ok(filename(self.url).to_string()),
+            "path" => Ok(path_and_query(self.url).to_string()),
+            _ => Err(format!("capture '{name}' did not match")),
+        }
+    }
+}
+
+impl UrlTemplate {
+    /// Parse a template
+    pub fn parse(source: &str) -> Result<Self> {
+        Parser::new(source)
+            .parse_template()
+            .map(|segments| Self {
+                source: source.to_string(),
+                segments,
+            })
+            .map_err(|e| TurboCdnError::config(format!("Invalid template '{source}': {e}")))
+    }
+
+    /// Original template text
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Check that every variable exists for URLs matched by `pattern`
+    pub fn validate(&self, pattern: &Regex) -> Result<()> {
+        fn check(expr: &Expr, pattern: &Regex) -> std::result::Result<(), String> {
+            match expr {
+                Expr::Group(index) if *index >= pattern.captures_len() => Err(format!(
+                    "group ${index} does not exist in pattern '{}'",
+                    pattern.as_str()
+                )),
+                Expr::Variable(name)
+                    if !BUILTIN_VARIABLES.contains(&name.as_str())
+                        && !pattern.capture_names().flatten().any(|n| n == name) =>
+                {
+                    Err(format!("unknown variable '{name}'"))
+                }
+                Expr::Call(_, args) => args.iter().try_for_each(|arg| check(arg, pattern)),
+                _ => Ok(()),
+            }
+        }
+
+        self.segments
+            .iter()
+            .try_for_each(|segment| match segment {
+                Segment::Expr(expr) => check(expr, pattern),
+                Segment::Literal(_) => Ok(()),
+            })
+            .map_err(|e| TurboCdnError::config(format!("Invalid template '{}': {e}", self.source)))
+    }
+
+    /// Render the template
+    ///
+    /// Fails when a referenced capture group did not participate in the match.
+    pub fn render(&self, context: &TemplateContext) -> std::result::Result<String, String> {
+        let mut output = String::with_capacity(self.source.len() + context.url.len());
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => output.push_str(text),
+                Segment::Expr(expr) => output.push_str(&eval(expr, context)?),
+            }
+        }
+        Ok(output)
+    }
+}
+
+impl std::fmt::Display for UrlTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn eval(expr: &Expr, context: &TemplateContext) -> std::result::Result<String, String> {
+    match expr {
+        Expr::Group(index) => context
+            .captures
+            .get(*index)
+            .map(|m| m.as_str().to_string())
+            .ok_or_else(|| format!("group ${index} did not match")),
+        Expr::Variable(name) => context.variable(name),
+        Expr::Literal(text) => Ok(text.clone()),
+        Expr::Call(function, args) => {
+            let args = args
+                .iter()
+                .map(|arg| eval(arg, context))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            Ok(function.apply(&args))
+        }
+    }
+}
+
+/// Recursive descent parser over the template text
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.pos = self.input.len() - trimmed.len();
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !predicate(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn parse_template(&mut self) -> std::result::Result<Vec<Segment>, String> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+
+        while let Some(c) = self.peek() {
+            if c != '$' {
+                literal.push_str(self.take_while(|c| c != '$'));
+                continue;
+            }
+            self.pos += 1;
+
+            let expr = match self.peek() {
+                Some('$') => {
+                    self.pos += 1;
+                    literal.push('$');
+                    continue;
+                }
+                Some('{') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    let expr = self.parse_expr()?;
+                    self.skip_whitespace();
+                    if !self.eat('}') {
+                        return Err(format!("expected '}}' at offset {}", self.pos));
+                    }
+                    expr
+                }
+                Some(c) if c.is_ascii_digit() => self.parse_group()?,
+                // A lone `$` is kept as is
+                _ => {
+                    literal.push('$');
+                    continue;
+                }
+            };
+
+            if !literal.is_empty() {
+                segments.push(Segment::Literal(std::mem::take(&mut literal)));
+            }
+            segments.push(Segment::Expr(expr));
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(segments)
+    }
+
+    fn parse_group(&mut self) -> std::result::Result<Expr, String> {
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        digits
+            .parse()
+            .map(Expr::Group)
+            .map_err(|_| format!("invalid group '{digits}'"))
+    }
+
+    fn parse_expr(&mut self) -> std::result::Result<Expr, String> {
+        match self.peek() {
+            Some(quote @ ('\'' | '"')) => {
+                self.pos += 1;
+                let text = self.take_while(|c| c != quote);
+                if !self.eat(quote) {
+                    return Err("unterminated string".to_string());
+                }
+                Ok(Expr::Literal(text.to_string()))
+            }
+            Some(c) if c.is_ascii_digit() => self.parse_group(),
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                self.skip_whitespace();
+                if self.eat('(') {
+                    self.parse_call(name)
+                } else {
+                    Ok(Expr::Variable(name.to_string()))
+                }
+            }
+            _ => Err(format!("expected a variable at offset {}", self.pos)),
+        }
+    }
+
+    fn parse_call(&mut self, name: &str) -> std::result::Result<Expr, String> {
+        let function =
+            Function::from_name(name).ok_or_else(|| format!("unknown function '{name}'"))?;
+
+        let mut args = Vec::new();
+        self.skip_whitespace();
+        if !self.eat(')') {
+            loop {
+                self.skip_whitespace();
+                args.push(self.parse_expr()?);
+                self.skip_whitespace();
+                if self.eat(')') {
+                    break;
+                }
+                if !self.eat(',') {
+                    return Err(format!("expected ',' or ')' in call to '{name}'"));
+                }
+            }
+        }
+
+        if args.len() != function.arity() {
+            return Err(format!(
+                "'{name}' takes {} argument(s), got {}",
+                function.arity(),
+                args.len()
+            ));
+        }
+        Ok(Expr::Call(function, args))
+    }
+}
+
+/// Last non-empty path segment of a URL
+fn filename(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/').find(|s| !s.is_empty()).unwrap_or_default()
+}
+
+/// Path and query of a URL, without the scheme, host and fragment
+fn path_and_query(url: &str) -> &str {
+    let url = url.split('#').next().unwrap_or(url);
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.find(['/', '?']).map_or("", |start| &rest[start..])
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters
+fn url_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => {
+                let _ = write!(encoded, "%{byte:02X}");
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://github.com/owner/repo/releases/download/v1.2.0/Tool_Linux.tar.gz";
+
+    fn render(pattern: &str, template: &str) -> std::result::Result<String, String> {
+        let pattern = Regex::new(pattern).unwrap();
+        let template = UrlTemplate::parse(template).unwrap();
+        template.validate(&pattern).unwrap();
+        let captures = pattern.captures(URL).unwrap();
+        template.render(&TemplateContext {
+            captures: &captures,
+            url: URL,
+            region: &Region::China,
+        })
+    }
+
+    #[test]
+    fn test_positional_groups() {
+        let pattern = "^https://github\\.com/([^/]+)/([^/]+)/releases/download/([^/]+)/(.+)$";
+        assert_eq!(
+            render(pattern, "https://mirror/$1/$2/$3/$4").unwrap(),
+            "https://mirror/owner/repo/v1.2.0/Tool_Linux.tar.gz"
+        );
+        assert_eq!(render(pattern, "${1}0").unwrap(), "owner0");
+        assert_eq!(render(pattern, "$$1 costs $").unwrap(), "$1 costs $");
+    }
+
+    #[test]
+    fn test_double_digit_groups() {
+        // `$10` must not be rendered as `$1` followed by `0`
+        let pattern = "^(h)(t)(t)(p)(s)(:)(/)(/)(g)(i)(t)";
+        assert_eq!(render(pattern, "$10-$1-$11").unwrap(), "i-h-t");
+    }
+
+    #[test]
+    fn test_named_captures_and_builtins() {
+        let pattern = "^https://github\\.com/(?P<owner>[^/]+)/(?P<repo>[^/]+)/releases/download/(?P<tag>[^/]+)/";
+        assert_eq!(
+            render(pattern, "${owner}/${repo}@${tag}").unwrap(),
+            "owner/repo@v1.2.0"
+        );
+        assert_eq!(
+            render(pattern, "${region}/${filename}").unwrap(),
+            "China/Tool_Linux.tar.gz"
+        );
+        assert_eq!(
+            render(pattern, "${os}-${arch}").unwrap(),
+            format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+        );
+        assert_eq!(
+            render(pattern, "https://p/${url}").unwrap(),
+            format!("https://p/{URL}")
+        );
+        assert_eq!(
+            render(pattern, "https://bgithub.xyz${path}").unwrap(),
+            "https://bgithub.xyz/owner/repo/releases/download/v1.2.0/Tool_Linux.tar.gz"
+        );
+    }
+
+    #[test]
+    fn test_path_and_query() {
+        assert_eq!(path_and_query("https://h.example/a/b?x=1#frag"), "/a/b?x=1");
+        assert_eq!(path_and_query("https://h.example?x=1"), "?x=1");
+        assert_eq!(path_and_query("https://h.example"), "");
+    }
+
+    #[test]
+    fn test_functions() {
+        let pattern = "/download/(?P<tag>[^/]+)/(?P<file>.+)$";
+        assert_eq!(
+            render(pattern, "${strip_prefix(tag, 'v')}").unwrap(),
+            "1.2.0"
+        );
+        assert_eq!(
+            render(pattern, "${lower(file)}").unwrap(),
+            "tool_linux.tar.gz"
+        );
+        assert_eq!(render(pattern, "${upper(region)}").unwrap(), "CHINA");
+        assert_eq!(
+            render(
+                pattern,
+                "${replace(strip_suffix(file, \".tar.gz\"), '_', '-')}"
+            )
+            .unwrap(),
+            "Tool-Linux"
+        );
+        assert_eq!(
+            render(pattern, "${url_encode('a b/c')}").unwrap(),
+            "a%20b%2Fc"
+        );
+    }
+
+    #[test]
+    fn test_unmatched_optional_group() {
+        assert!(render("^https://(github)\\.com/(foo)?", "$1/$2").is_err());
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        assert!(UrlTemplate::parse("${owner").is_err());
+        assert!(UrlTemplate::parse("${nope(owner)}").is_err());
+        assert!(UrlTemplate::parse("${lower(a, b)}").is_err());
+        assert!(UrlTemplate::parse("${strip_prefix(tag, 'v)}").is_err());
+
+        let pattern = Regex::new("^https://(?P<owner>[^/]+)/").unwrap();
+        assert!(UrlTemplate::parse("$2")
+            .unwrap()
+            .validate(&pattern)
+            .is_err());
+        assert!(UrlTemplate::parse("${repo}")
+            .unwrap()
+            .validate(&pattern)
+            .is_err());
+        assert!(UrlTemplate::parse("${lower(owner)}/${region}")
+            .unwrap()
+            .validate(&pattern)
+            .is_ok());
+    }
+}
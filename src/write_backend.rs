@@ -0,0 +1,460 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Write backends for downloaded files
+//!
+//! Concurrent range requests complete out of order, so every backend writes
+//! at explicit offsets:
+//! - [`WriteBackendKind::Mmap`] copies into a memory map of the output file
+//! - [`WriteBackendKind::Pwrite`] uses positional writes, which need no shared
+//!   file cursor and therefore no lock
+//! - [`WriteBackendKind::Buffered`] streams through one buffered handle, for
+//!   sequential downloads of unknown size
+//!
+//! Once the final size is known, free space is checked up front and mapped
+//! or positionally written files are preallocated, so a full disk fails the
+//! download before any data is transferred rather than near the end.
+
+use crate::config::WriteBackendMode;
+use crate::error::{Result, TurboCdnError};
+use crate::mmap_writer::{should_use_mmap, MmapWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt, BufWriter, SeekFrom};
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+/// Concrete backend chosen for a download
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteBackendKind {
+    /// Memory-mapped output file
+    Mmap,
+    /// Positional writes
+    Pwrite,
+    /// Buffered sequential writes
+    Buffered,
+}
+
+impl WriteBackendKind {
+    /// Resolve the configured mode for a download
+    ///
+    /// `total_size` is 0 when the server did not report a length. Memory
+    /// mapping needs the final size and recreates the file, so it is only
+    /// used for fresh downloads of known size; other cases fall back to
+    /// positional or buffered writes.
+    pub fn select(
+        mode: WriteBackendMode,
+        total_size: u64,
+        existing_size: u64,
+        mmap_threshold: u64,
+    ) -> Self {
+        let can_mmap = total_size > 0 && existing_size == 0;
+
+        match mode {
+            WriteBackendMode::Buffered => Self::Buffered,
+            WriteBackendMode::Pwrite => Self::Pwrite,
+            WriteBackendMode::Mmap if can_mmap => Self::Mmap,
+            WriteBackendMode::Mmap | WriteBackendMode::Auto => {
+                if total_size == 0 {
+                    Self::Buffered
+                } else if can_mmap && should_use_mmap(total_size, Some(mmap_threshold)) {
+                    Self::Mmap
+                } else {
+                    Self::Pwrite
+                }
+            }
+        }
+    }
+}
+
+/// Output file writer for a single download
+pub enum FileWriter {
+    /// Memory-mapped writer
+    Mmap(MmapWriter),
+    /// File written with positional writes
+    Pwrite(Arc<std::fs::File>),
+    /// Buffered file and its current position
+    Buffered(Mutex<(BufWriter<tokio::fs::File>, u64)>),
+}
+
+impl FileWriter {
+    /// Open the output file for a download
+    ///
+    /// When `existing_size` is non-zero the file is kept and written from
+    /// that offset on (resume); otherwise it is truncated. Fails with
+    /// [`TurboCdnError::InsufficientSpace`] when the remaining bytes do not
+    /// fit on the target filesystem.
+    pub async fn open(
+        path: &Path,
+        total_size: u64,
+        existing_size: u64,
+        mode: WriteBackendMode,
+        mmap_threshold: u64,
+    ) -> Result<Self> {
+        let kind = WriteBackendKind::select(mode, total_size, existing_size, mmap_threshold);
+        debug!(
+            "Using {:?} write backend for {} ({} bytes)",
+            kind,
+            path.display(),
+            total_size
+        );
+
+        if total_size > 0 {
+            // A truncated file gives its space back
+            let reclaimable = match existing_size {
+                0 => std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+                _ => 0,
+            };
+            ensure_free_space(path, total_size.saturating_sub(existing_size), reclaimable)?;
+        }
+
+        match kind {
+            // Threshold 0: the backend is already chosen, always map
+            WriteBackendKind::Mmap => Ok(Self::Mmap(
+                MmapWriter::new(path, total_size, Some(0)).await?,
+            )),
+            WriteBackendKind::Pwrite => {
+                let file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(existing_size == 0)
+                    .open(path)
+                    .map_err(|e| TurboCdnError::io(format!("Failed to open file: {e}")))?;
+                if total_size > 0 {
+                    preallocate(&file, path, total_size)?;
+                }
+                Ok(Self::Pwrite(Arc::new(file)))
+            }
+            WriteBackendKind::Buffered => {
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(existing_size == 0)
+                    .open(path)
+                    .await
+                    .map_err(|e| TurboCdnError::io(format!("Failed to open file: {e}")))?;
+                if existing_size > 0 {
+                    file.seek(SeekFrom::Start(existing_size))
+                        .await
+                        .map_err(|e| TurboCdnError::io(format!("Failed to seek in file: {e}")))?;
+                }
+                Ok(Self::Buffered(Mutex::new((
+                    BufWriter::new(file),
+                    existing_size,
+                ))))
+            }
+        }
+    }
+
+    /// Backend used by this writer
+    pub fn kind(&self) -> WriteBackendKind {
+        match self {
+            Self::Mmap(_) => WriteBackendKind::Mmap,
+            Self::Pwrite(_) => WriteBackendKind::Pwrite,
+            Self::Buffered(_) => WriteBackendKind::Buffered,
+        }
+    }
+
+    /// Write an owned buffer at `offset` and hand it back for reuse
+    ///
+    /// Positional writes run on the blocking pool; owning the buffer lets
+    /// them take it along without copying.
+    pub async fn write_owned<B>(&self, offset: u64, data: B) -> (B, Result<()>)
+    where
+        B: AsRef<[u8]> + Default + Send + 'static,
+    {
+        match self {
+            Self::Pwrite(file) => {
+                let file = file.clone();
+                let task = tokio::task::spawn_blocking(move || {
+                    let result = write_all_at(&file, data.as_ref(), offset);
+                    (data, result)
+                });
+                match task.await {
+                    Ok((data, result)) => (
+                        data,
+                        result
+                            .map_err(|e| TurboCdnError::io(format!("Failed to write chunk: {e}"))),
+                    ),
+                    Err(e) => (B::default(), Err(TurboCdnError::internal(e.to_string()))),
+                }
+            }
+            _ => {
+                let result = self.write_at(offset, data.as_ref()).await;
+                (data, result)
+            }
+        }
+    }
+
+    /// Write `data` at `offset`
+    ///
+    /// Positional writes copy `data` to move it to the blocking pool; prefer
+    /// [`Self::write_owned`] on hot paths.
+    pub async fn write_at(&self, offset: u64, data: &[u8]) -> Result<()> {
+        match self {
+            Self::Mmap(writer) => writer.write_at(offset, data).await.map(|_| ()),
+            Self::Pwrite(file) => {
+                // Positional writes block, keep them off the runtime threads
+                let file = file.clone();
+                let data = data.to_vec();
+                tokio::task::spawn_blocking(move || write_all_at(&file, &data, offset))
+                    .await
+                    .map_err(|e| TurboCdnError::internal(e.to_string()))?
+                    .map_err(|e| TurboCdnError::io(format!("Failed to write chunk: {e}")))
+            }
+            Self::Buffered(state) => {
+                let mut guard = state.lock().await;
+                let (file, position) = &mut *guard;
+                if *position != offset {
+                    file.seek(SeekFrom::Start(offset))
+                        .await
+                        .map_err(|e| TurboCdnError::io(format!("Failed to seek in file: {e}")))?;
+                }
+                file.write_all(data)
+                    .await
+                    .map_err(|e| TurboCdnError::io(format!("Failed to write chunk: {e}")))?;
+                *position = offset + data.len() as u64;
+                Ok(())
+            }
+        }
+    }
+
+    /// Make the bytes written so far visible to other readers of the file
+    ///
+    /// Only the buffered backend holds data back; mapped and positional
+    /// writes already land in the page cache.
+    pub async fn flush(&self) -> Result<()> {
+        match self {
+            Self::Mmap(_) | Self::Pwrite(_) => Ok(()),
+            Self::Buffered(state) => state
+                .lock()
+                .await
+                .0
+                .flush()
+                .await
+                .map_err(|e| TurboCdnError::io(format!("Failed to flush file: {e}"))),
+        }
+    }
+
+    /// Flush buffered data once all writes are done
+    pub async fn finish(&self) -> Result<()> {
+        match self {
+            Self::Mmap(writer) => writer.flush().await,
+            Self::Pwrite(_) | Self::Buffered(_) => self.flush().await,
+        }
+    }
+}
+
+impl std::fmt::Debug for FileWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("FileWriter").field(&self.kind()).finish()
+    }
+}
+
+/// Fail early when `path` cannot hold `required` more bytes
+///
+/// `reclaimable` is space the download frees before writing, such as an
+/// existing file it truncates. Filesystems that cannot report free space are
+/// not checked.
+pub fn ensure_free_space(path: &Path, required: u64, reclaimable: u64) -> Result<()> {
+    let Some(available) = available_space(path) else {
+        return Ok(());
+    };
+    let available = available.saturating_add(reclaimable);
+
+    if required > available {
+        return Err(TurboCdnError::insufficient_space(
+            path.display().to_string(),
+            required,
+            available,
+        ));
+    }
+    Ok(())
+}
+
+/// Reserve `len` bytes of disk space for a file
+///
+/// Uses `fallocate` (or the platform equivalent) so out-of-order chunk writes
+/// don't fragment the file and the space can't be taken mid-download. Falls
+/// back to `set_len` on filesystems without allocation support.
+pub fn preallocate(file: &std::fs::File, path: &Path, len: u64) -> Result<()> {
+    match fs4::FileExt::allocate(file, len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::StorageFull => {
+            return Err(TurboCdnError::insufficient_space(
+                path.display().to_string(),
+                len,
+                available_space(path).unwrap_or(0),
+            ));
+        }
+        Err(e) => debug!(
+            "Preallocation not supported for {}: {}, extending instead",
+            path.display(),
+            e
+        ),
+    }
+
+    // The logical size is not always extended by the allocation
+    let current = file
+        .metadata()
+        .map_err(|e| TurboCdnError::io(format!("Failed to get file metadata: {e}")))?
+        .len();
+    if current < len {
+        file.set_len(len)
+            .map_err(|e| TurboCdnError::io(format!("Failed to set file size: {e}")))?;
+    }
+    Ok(())
+}
+
+/// Free space available to the current user on the filesystem holding `path`
+fn available_space(path: &Path) -> Option<u64> {
+    // The file itself may not exist yet, so ask about its directory
+    let dir = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    match fs4::available_space(&dir) {
+        Ok(available) => Some(available),
+        Err(e) => {
+            warn!(
+                "Could not determine free space for {}: {}",
+                dir.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+#[cfg(unix)]
+fn write_all_at(file: &std::fs::File, data: &[u8], offset: u64) -> std::io::Result<()> {
+    std::os::unix::fs::FileExt::write_all_at(file, data, offset)
+}
+
+#[cfg(windows)]
+fn write_all_at(file: &std::fs::File, mut data: &[u8], mut offset: u64) -> std::io::Result<()> {
+    use std::os::windows::fs::FileExt;
+
+    while !data.is_empty() {
+        let written = file.seek_write(data, offset)?;
+        if written == 0 {
+            return Err(std::io::ErrorKind::WriteZero.into());
+        }
+        data = &data[written..];
+        offset += written as u64;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const MB: u64 = 1024 * 1024;
+
+    #[test]
+    fn test_select_backend() {
+        use WriteBackendMode::*;
+
+        assert_eq!(
+            WriteBackendKind::select(Auto, 100 * MB, 0, 10 * MB),
+            WriteBackendKind::Mmap
+        );
+        assert_eq!(
+            WriteBackendKind::select(Auto, MB, 0, 10 * MB),
+            WriteBackendKind::Pwrite
+        );
+        // Beyond the mmap size limit and when resuming
+        assert_eq!(
+            WriteBackendKind::select(Auto, 8 * 1024 * MB, 0, 10 * MB),
+            WriteBackendKind::Pwrite
+        );
+        assert_eq!(
+            WriteBackendKind::select(Auto, 100 * MB, MB, 10 * MB),
+            WriteBackendKind::Pwrite
+        );
+        assert_eq!(
+            WriteBackendKind::select(Auto, 0, 0, 10 * MB),
+            WriteBackendKind::Buffered
+        );
+
+        assert_eq!(
+            WriteBackendKind::select(Mmap, MB, 0, 10 * MB),
+            WriteBackendKind::Mmap
+        );
+        assert_eq!(
+            WriteBackendKind::select(Mmap, MB, 1, 10 * MB),
+            WriteBackendKind::Pwrite
+        );
+        assert_eq!(
+            WriteBackendKind::select(Pwrite, 0, 0, 10 * MB),
+            WriteBackendKind::Pwrite
+        );
+        assert_eq!(
+            WriteBackendKind::select(Buffered, 100 * MB, 0, 10 * MB),
+            WriteBackendKind::Buffered
+        );
+    }
+
+    #[tokio::test]
+    async fn test_out_of_order_writes() {
+        let dir = tempdir().unwrap();
+
+        for mode in [
+            WriteBackendMode::Mmap,
+            WriteBackendMode::Pwrite,
+            WriteBackendMode::Buffered,
+        ] {
+            let path = dir.path().join(format!("{mode:?}.bin"));
+            let writer = FileWriter::open(&path, 12, 0, mode, 0).await.unwrap();
+
+            writer.write_at(8, b"ijkl").await.unwrap();
+            writer.write_at(0, b"abcd").await.unwrap();
+            writer.write_at(4, b"efgh").await.unwrap();
+            writer.finish().await.unwrap();
+            drop(writer);
+
+            assert_eq!(std::fs::read(&path).unwrap(), b"abcdefghijkl", "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn test_free_space_check() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("file.bin");
+
+        assert!(ensure_free_space(&path, 1, 0).is_ok());
+        let error = ensure_free_space(&path, u64::MAX, 0).unwrap_err();
+        assert_eq!(error.category(), "insufficient_space");
+    }
+
+    #[test]
+    fn test_preallocate() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("file.bin");
+        let file = std::fs::File::create(&path).unwrap();
+
+        preallocate(&file, &path, 64 * 1024).unwrap();
+        assert_eq!(file.metadata().unwrap().len(), 64 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_resume_keeps_existing_data() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("partial.bin");
+        std::fs::write(&path, b"abcd").unwrap();
+
+        let writer = FileWriter::open(&path, 8, 4, WriteBackendMode::Auto, 0)
+            .await
+            .unwrap();
+        assert_eq!(writer.kind(), WriteBackendKind::Pwrite);
+        writer.write_at(4, b"efgh").await.unwrap();
+        writer.finish().await.unwrap();
+        drop(writer);
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"abcdefgh");
+    }
+}
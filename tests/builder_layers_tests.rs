@@ -0,0 +1,35 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Builder configuration layer tests
+//!
+//! The builder starts from the same layered configuration as
+//! `TurboCdn::new`. Kept in a binary of its own because it sets process
+//! environment variables.
+
+use turbo_cdn::*;
+
+#[tokio::test]
+async fn test_builder_uses_layered_config() {
+    std::env::set_var("TURBO_CDN_GEO_DETECTION__AUTO_DETECT_REGION", "false");
+    std::env::set_var("TURBO_CDN_PERFORMANCE__TIMEOUT", "77");
+    let turbo_cdn = TurboCdn::builder().build().await.unwrap();
+    assert_eq!(turbo_cdn.config().performance.timeout, 77);
+
+    // A broken layer is reported instead of silently skipped
+    std::env::set_var("TURBO_CDN_PERFORMANCE__TIMEOUT", "soon");
+    let error = TurboCdn::builder().build().await.unwrap_err();
+    assert_eq!(error.category(), "config");
+
+    // An explicit configuration replaces the layers
+    let mut config = TurboCdnConfig::default();
+    config.geo_detection.auto_detect_region = false;
+    assert!(TurboCdn::builder()
+        .with_config(config)
+        .build()
+        .await
+        .is_ok());
+
+    std::env::remove_var("TURBO_CDN_GEO_DETECTION__AUTO_DETECT_REGION");
+    std::env::remove_var("TURBO_CDN_PERFORMANCE__TIMEOUT");
+}
@@ -0,0 +1,20 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Fixtures shared by the integration tests
+
+use std::path::Path;
+use turbo_cdn::TurboCdnConfig;
+
+/// Built-in configuration for tests against local servers
+///
+/// HTTP/2 prior knowledge, region detection and the download cache are
+/// off, and the cache lives in `dir` so nothing touches the user's cache.
+pub fn test_config(dir: &Path) -> TurboCdnConfig {
+    let mut config = TurboCdnConfig::load().unwrap();
+    config.performance.http2_prior_knowledge = false;
+    config.geo_detection.auto_detect_region = false;
+    config.cache.enabled = false;
+    config.cache.directory = Some(dir.join("cache"));
+    config
+}
@@ -0,0 +1,185 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Configuration hot-reload tests
+//!
+//! A long-running client swaps its mapping rules on `reload_config` or when
+//! a watched file changes, and keeps the previous rules when the new ones
+//! don't validate.
+
+use std::path::Path;
+use std::time::Duration;
+use turbo_cdn::config::ConfigLoader;
+use turbo_cdn::*;
+
+const TEAM_URL: &str = "https://github.com/team/tool/releases/download/v1/tool.zip";
+const TEAM_MIRROR: &str = "https://mirror.team.example/tool/releases/download/v1/tool.zip";
+
+fn config_file(team_enabled: bool, pattern: &str) -> String {
+    format!(
+        r#"
+[general]
+default_region = "China"
+
+[geo_detection]
+auto_detect_region = false
+
+[cache]
+enabled = false
+
+[[url_mapping_rules]]
+name = "Team"
+pattern = '{pattern}'
+replacements = ["https://mirror.team.example/$1"]
+regions = []
+priority = 0
+enabled = {team_enabled}
+"#
+    )
+}
+
+const TEAM_PATTERN: &str = r"^https://github\.com/team/(.+)$";
+
+/// Rule mapping `https://github.com/<owner>/...` to `https://<owner>.example/...`
+fn owner_rule(name: &str, owner: &str) -> config::UrlMappingRuleConfig {
+    config::UrlMappingRuleConfig {
+        name: name.to_string(),
+        pattern: format!(r"^https://github\.com/{owner}/(.+)$"),
+        replacements: vec![config::ReplacementConfig::Url(format!(
+            "https://{owner}.example/$1"
+        ))],
+        regions: vec![],
+        priority: 0,
+        enabled: true,
+        examples: vec![],
+    }
+}
+
+async fn maps_to_owner_mirror(turbo_cdn: &TurboCdn, owner: &str) -> bool {
+    turbo_cdn
+        .get_all_cdn_urls(&format!("https://github.com/{owner}/tool.zip"))
+        .await
+        .unwrap()
+        .iter()
+        .any(|url| *url == format!("https://{owner}.example/tool.zip"))
+}
+
+fn load(path: &Path) -> TurboCdnConfig {
+    ConfigLoader::new().with_file(path).load().unwrap().config
+}
+
+async fn uses_team_mirror(turbo_cdn: &TurboCdn) -> bool {
+    turbo_cdn
+        .get_all_cdn_urls(TEAM_URL)
+        .await
+        .unwrap()
+        .iter()
+        .any(|url| url == TEAM_MIRROR)
+}
+
+/// Poll the watcher until `done` holds, failing after a few seconds
+async fn wait_for(watcher: &ConfigWatcher, done: impl Fn(&WatchStatus) -> bool) -> WatchStatus {
+    for _ in 0..200 {
+        let status = watcher.status();
+        if done(&status) {
+            return status;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    panic!("watcher did not reload: {:?}", watcher.status());
+}
+
+#[tokio::test]
+async fn test_reload_config_swaps_rules() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("turbo-cdn.toml");
+    std::fs::write(&path, config_file(false, TEAM_PATTERN)).unwrap();
+    let turbo_cdn = TurboCdn::with_config(load(&path)).await.unwrap();
+    assert!(!uses_team_mirror(&turbo_cdn).await);
+
+    std::fs::write(&path, config_file(true, TEAM_PATTERN)).unwrap();
+    turbo_cdn.reload_config(load(&path)).await.unwrap();
+    assert!(uses_team_mirror(&turbo_cdn).await);
+}
+
+#[tokio::test]
+async fn test_reload_config_keeps_added_rules() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("turbo-cdn.toml");
+    std::fs::write(&path, config_file(false, TEAM_PATTERN)).unwrap();
+    let turbo_cdn = TurboCdn::builder()
+        .with_config(load(&path))
+        .with_rule(owner_rule("Built", "built"))
+        .build()
+        .await
+        .unwrap();
+    turbo_cdn
+        .add_rule(owner_rule("Added", "added"))
+        .await
+        .unwrap();
+
+    std::fs::write(&path, config_file(true, TEAM_PATTERN)).unwrap();
+    turbo_cdn.reload_config(load(&path)).await.unwrap();
+
+    assert!(uses_team_mirror(&turbo_cdn).await);
+    assert!(maps_to_owner_mirror(&turbo_cdn, "built").await);
+    assert!(maps_to_owner_mirror(&turbo_cdn, "added").await);
+    let names: Vec<String> = turbo_cdn
+        .list_rules()
+        .await
+        .into_iter()
+        .map(|rule| rule.name)
+        .collect();
+    assert!(names.ends_with(&["Team", "Built", "Added"].map(String::from)));
+}
+
+#[tokio::test]
+async fn test_invalid_config_keeps_previous_rules() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("turbo-cdn.toml");
+    std::fs::write(&path, config_file(true, TEAM_PATTERN)).unwrap();
+    let turbo_cdn = TurboCdn::with_config(load(&path)).await.unwrap();
+
+    std::fs::write(&path, config_file(true, "^https://github.com/(team")).unwrap();
+    let error = turbo_cdn.reload_config(load(&path)).await.unwrap_err();
+    assert!(error.to_string().contains("Team"), "{error}");
+    assert!(uses_team_mirror(&turbo_cdn).await);
+}
+
+#[tokio::test]
+async fn test_watcher_reloads_changed_file() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("turbo-cdn.toml");
+    std::fs::write(&path, config_file(false, TEAM_PATTERN)).unwrap();
+    let loader = ConfigLoader::new().with_file(&path);
+    let turbo_cdn = TurboCdn::with_config(loader.load().unwrap().config)
+        .await
+        .unwrap();
+    let watcher = turbo_cdn.watch_config(loader, Duration::from_millis(20));
+
+    std::fs::write(&path, config_file(true, TEAM_PATTERN)).unwrap();
+    wait_for(&watcher, |status| status.reloads == 1).await;
+    assert!(uses_team_mirror(&turbo_cdn).await);
+
+    // A broken rule is reported and the working rules stay in use
+    std::fs::write(&path, config_file(false, "^https://github.com/(team")).unwrap();
+    let status = wait_for(&watcher, |status| status.last_error.is_some()).await;
+    assert_eq!(status.reloads, 1);
+    assert!(uses_team_mirror(&turbo_cdn).await);
+
+    // So is a file that no longer parses
+    std::fs::write(&path, "[general\n").unwrap();
+    wait_for(&watcher, |status| {
+        status
+            .last_error
+            .as_deref()
+            .is_some_and(|e| e.contains("turbo-cdn.toml"))
+    })
+    .await;
+    assert!(uses_team_mirror(&turbo_cdn).await);
+
+    std::fs::write(&path, config_file(false, TEAM_PATTERN)).unwrap();
+    let status = wait_for(&watcher, |status| status.reloads == 2).await;
+    assert!(status.last_error.is_none());
+    assert!(!uses_team_mirror(&turbo_cdn).await);
+}
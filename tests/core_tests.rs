@@ -63,6 +63,68 @@ fn test_url_mapping() {
     );
 }
 
+/// Test weighted replacements and the rule combination mode
+#[test]
+fn test_weighted_replacements_config() {
+    let rule: config::UrlMappingRuleConfig = toml::from_str(
+        r#"
+        name = "Team mirror"
+        pattern = "^https://github\\.com/team/(.+)$"
+        replacements = [
+            { url = "https://mirror.team.example/$1", weight = 300 },
+            "https://github.com/team/$1",
+        ]
+        regions = []
+        priority = 1
+        enabled = true
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        rule.replacements[0].url(),
+        Some("https://mirror.team.example/$1")
+    );
+    assert_eq!(rule.replacements[0].weight(), 300);
+    assert_eq!(
+        rule.replacements[1].weight(),
+        config::DEFAULT_REPLACEMENT_WEIGHT
+    );
+
+    let config = config::TurboCdnConfig::load().unwrap();
+    assert_eq!(config.mapping.mode, config::RuleMode::First);
+}
+
+/// Test that the embedded rules compile and pass their examples
+#[test]
+fn test_default_rules_pass_check() {
+    let config = config::TurboCdnConfig::load().unwrap();
+    let report = rule_check::check_rules(&config);
+
+    assert_eq!(report.rules, config.url_mapping_rules.len());
+    assert!(report.examples > 0);
+    assert!(report.issues.is_empty(), "{:#?}", report.issues);
+}
+
+/// Test that a custom file is merged over the defaults instead of replacing them
+#[test]
+fn test_load_from_file_merges_defaults() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("turbo-cdn.toml");
+    std::fs::write(&path, "[performance]\ntimeout = 5\n").unwrap();
+
+    let defaults = config::TurboCdnConfig::load().unwrap();
+    let config = config::TurboCdnConfig::load_from_file(&path).unwrap();
+    assert_eq!(config.performance.timeout, 5);
+    assert_eq!(
+        config.url_mapping_rules.len(),
+        defaults.url_mapping_rules.len()
+    );
+
+    let missing = config::TurboCdnConfig::load_from_file(dir.path().join("missing.toml")).unwrap();
+    assert_eq!(missing.performance.timeout, defaults.performance.timeout);
+}
+
 /// Test jsDelivr URL mapping
 #[test]
 fn test_jsdelivr_mapping() {
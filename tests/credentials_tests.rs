@@ -0,0 +1,233 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Per-host credential tests
+//!
+//! Verify that configured credentials reach the host they belong to and are
+//! never sent to mirror hosts or across cross-host redirects.
+
+mod common;
+
+use tempfile::TempDir;
+use turbo_cdn::config::HostCredentialConfig;
+use turbo_cdn::*;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const BODY: &[u8] = b"private artifact";
+const TOKEN: &str = "s3cret-token";
+
+fn test_config(dir: &TempDir, server: &MockServer) -> TurboCdnConfig {
+    let mut config = common::test_config(dir.path());
+    config.credentials.hosts = vec![HostCredentialConfig {
+        host: server.address().to_string(),
+        token: Some(TOKEN.to_string()),
+        username: None,
+        password: None,
+    }];
+    config
+}
+
+async fn mount(server: &MockServer, route: &str, response: ResponseTemplate) {
+    for verb in ["HEAD", "GET"] {
+        Mock::given(method(verb))
+            .and(path(route))
+            .respond_with(response.clone())
+            .mount(server)
+            .await;
+    }
+}
+
+async fn assert_no_authorization(server: &MockServer) {
+    let requests = server.received_requests().await.unwrap();
+    assert!(!requests.is_empty());
+    assert!(requests
+        .iter()
+        .all(|r| !r.headers.contains_key("authorization")));
+}
+
+#[tokio::test]
+async fn test_credentials_applied_to_configured_host() {
+    let origin = MockServer::start().await;
+    for verb in ["HEAD", "GET"] {
+        Mock::given(method(verb))
+            .and(path("/private.bin"))
+            .and(header("authorization", format!("Bearer {TOKEN}").as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(BODY))
+            .mount(&origin)
+            .await;
+    }
+    let dir = TempDir::new().unwrap();
+
+    let downloader = ConcurrentDownloader::with_config(&test_config(&dir, &origin)).unwrap();
+    let result = downloader
+        .download(
+            &[format!("{}/private.bin", origin.uri())],
+            dir.path().join("private.bin"),
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(std::fs::read(&result.path).unwrap(), BODY);
+}
+
+#[tokio::test]
+async fn test_credentials_not_sent_to_mirror() {
+    let origin = MockServer::start().await;
+    let mirror = MockServer::start().await;
+    mount(
+        &origin,
+        "/private.bin",
+        ResponseTemplate::new(200).set_body_bytes(BODY),
+    )
+    .await;
+    mount(&mirror, "/private.bin", ResponseTemplate::new(404)).await;
+    let dir = TempDir::new().unwrap();
+
+    let downloader = ConcurrentDownloader::with_config(&test_config(&dir, &origin)).unwrap();
+    downloader
+        .download(
+            &[
+                format!("{}/private.bin", mirror.uri()),
+                format!("{}/private.bin", origin.uri()),
+            ],
+            dir.path().join("private.bin"),
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert_no_authorization(&mirror).await;
+}
+
+#[tokio::test]
+async fn test_credentials_stripped_on_cross_host_redirect() {
+    let origin = MockServer::start().await;
+    let storage = MockServer::start().await;
+    mount(
+        &origin,
+        "/private.bin",
+        ResponseTemplate::new(302).insert_header("location", format!("{}/blob", storage.uri())),
+    )
+    .await;
+    mount(
+        &storage,
+        "/blob",
+        ResponseTemplate::new(200).set_body_bytes(BODY),
+    )
+    .await;
+    let dir = TempDir::new().unwrap();
+
+    let downloader = ConcurrentDownloader::with_config(&test_config(&dir, &origin)).unwrap();
+    let result = downloader
+        .download(
+            &[format!("{}/private.bin", origin.uri())],
+            dir.path().join("private.bin"),
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(std::fs::read(&result.path).unwrap(), BODY);
+    assert!(origin
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .all(|r| r.headers.contains_key("authorization")));
+    assert_no_authorization(&storage).await;
+}
+
+#[test]
+fn test_credentialed_host_is_not_rewritten_to_mirrors() {
+    let dir = TempDir::new().unwrap();
+    let url = "https://github.com/owner/private/releases/download/v1/tool.zip";
+    let mut config = common::test_config(dir.path());
+
+    let mapper = UrlMapper::new(&config, Region::China).unwrap();
+    assert!(mapper.map_url(url).unwrap().len() > 1);
+
+    config.credentials.hosts = vec![HostCredentialConfig {
+        host: "github.com".to_string(),
+        token: Some(TOKEN.to_string()),
+        username: None,
+        password: None,
+    }];
+    let mapper = UrlMapper::new(&config, Region::China).unwrap();
+    assert_eq!(mapper.map_url(url).unwrap(), vec![url.to_string()]);
+    assert!(!mapper.explain(url).policy.rewrite_allowed);
+}
+
+/// Follows redirects itself, dropping credentials when the host changes
+struct RedirectingBackend(reqwest::Client);
+
+#[async_trait::async_trait]
+impl HttpBackend for RedirectingBackend {
+    async fn execute(&self, mut request: reqwest::Request) -> Result<reqwest::Response> {
+        loop {
+            let retry = request.try_clone().unwrap();
+            let response = self.0.execute(request).await?;
+            let Some(location) = response.headers().get("location") else {
+                return Ok(response);
+            };
+            let target = retry.url().join(location.to_str().unwrap()).unwrap();
+
+            let mut next = reqwest::Request::new(retry.method().clone(), target.clone());
+            *next.headers_mut() = retry.headers().clone();
+            if target.host_str() != retry.url().host_str()
+                || target.port_or_known_default() != retry.url().port_or_known_default()
+            {
+                next.headers_mut().remove("authorization");
+            }
+            request = next;
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_custom_backend_redirect_keeps_credentials_on_origin() {
+    let origin = MockServer::start().await;
+    let storage = MockServer::start().await;
+    mount(
+        &origin,
+        "/private.bin",
+        ResponseTemplate::new(302).insert_header("location", format!("{}/blob", storage.uri())),
+    )
+    .await;
+    mount(
+        &storage,
+        "/blob",
+        ResponseTemplate::new(200).set_body_bytes(BODY),
+    )
+    .await;
+    let dir = TempDir::new().unwrap();
+
+    let client = client_builder::client_builder(&TurboCdnConfig::default())
+        .unwrap()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let turbo_cdn = TurboCdn::builder()
+        .with_config(test_config(&dir, &origin))
+        .with_http_backend(std::sync::Arc::new(RedirectingBackend(client)))
+        .build()
+        .await
+        .unwrap();
+    let result = turbo_cdn
+        .download_to_path(
+            &format!("{}/private.bin", origin.uri()),
+            dir.path().join("private.bin"),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(std::fs::read(&result.path).unwrap(), BODY);
+    assert!(origin
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .all(|r| r.headers.contains_key("authorization")));
+    assert_no_authorization(&storage).await;
+}
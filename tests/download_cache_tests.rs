@@ -0,0 +1,162 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Download cache integration tests
+//!
+//! These tests run against a local mock server to verify that repeated
+//! downloads are served from the on-disk cache and revalidated with
+//! conditional requests.
+
+mod common;
+
+use tempfile::TempDir;
+use turbo_cdn::*;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const BODY: &[u8] = b"cached artifact payload";
+
+fn test_config(dir: &std::path::Path) -> TurboCdnConfig {
+    let mut config = common::test_config(dir);
+    config.cache.enabled = true;
+    config
+}
+
+async fn mount_artifact(server: &MockServer, etag: &str) {
+    Mock::given(method("HEAD"))
+        .and(path("/artifact.bin"))
+        .and(header("If-None-Match", etag))
+        .respond_with(ResponseTemplate::new(304))
+        .with_priority(1)
+        .mount(server)
+        .await;
+
+    Mock::given(method("HEAD"))
+        .and(path("/artifact.bin"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("etag", etag)
+                .set_body_bytes(BODY),
+        )
+        .mount(server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/artifact.bin"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("etag", etag)
+                .set_body_bytes(BODY),
+        )
+        .mount(server)
+        .await;
+}
+
+async fn get_requests(server: &MockServer) -> usize {
+    server
+        .received_requests()
+        .await
+        .unwrap_or_default()
+        .iter()
+        .filter(|r| r.method.as_str() == "GET")
+        .count()
+}
+
+#[tokio::test]
+async fn test_second_download_is_served_from_cache() {
+    let server = MockServer::start().await;
+    mount_artifact(&server, "\"v1\"").await;
+
+    let dir = TempDir::new().unwrap();
+    let turbo_cdn = TurboCdn::builder()
+        .with_config(test_config(dir.path()))
+        .build()
+        .await
+        .unwrap();
+
+    let url = format!("{}/artifact.bin", server.uri());
+
+    let first = turbo_cdn
+        .download_direct_to_path(&url, dir.path().join("first.bin"))
+        .await
+        .unwrap();
+    assert!(!first.from_cache);
+    assert_eq!(first.etag.as_deref(), Some("\"v1\""));
+
+    let second = turbo_cdn
+        .download_direct_to_path(&url, dir.path().join("second.bin"))
+        .await
+        .unwrap();
+    assert!(second.from_cache);
+    assert_eq!(second.size, BODY.len() as u64);
+    assert_eq!(std::fs::read(dir.path().join("second.bin")).unwrap(), BODY);
+
+    // Only the first download transferred the body
+    assert_eq!(get_requests(&server).await, 1);
+
+    let stats = turbo_cdn.get_stats().await;
+    assert_eq!(stats.cache_hit_rate, 0.5);
+}
+
+#[tokio::test]
+async fn test_changed_resource_is_downloaded_again() {
+    let server = MockServer::start().await;
+    mount_artifact(&server, "\"v1\"").await;
+
+    let dir = TempDir::new().unwrap();
+    let config = test_config(dir.path());
+    let url = format!("{}/artifact.bin", server.uri());
+
+    let turbo_cdn = TurboCdn::builder()
+        .with_config(config.clone())
+        .build()
+        .await
+        .unwrap();
+    turbo_cdn
+        .download_direct_to_path(&url, dir.path().join("first.bin"))
+        .await
+        .unwrap();
+
+    // The server now publishes a new version
+    server.reset().await;
+    mount_artifact(&server, "\"v2\"").await;
+
+    let result = turbo_cdn
+        .download_direct_to_path(&url, dir.path().join("second.bin"))
+        .await
+        .unwrap();
+    assert!(!result.from_cache);
+    assert_eq!(result.etag.as_deref(), Some("\"v2\""));
+
+    let cache = DownloadCache::open(&config.cache).unwrap();
+    let entry = cache.lookup(&url).unwrap();
+    assert_eq!(entry.validators.etag.as_deref(), Some("\"v2\""));
+}
+
+#[tokio::test]
+async fn test_disabled_cache_always_downloads() {
+    let server = MockServer::start().await;
+    mount_artifact(&server, "\"v1\"").await;
+
+    let dir = TempDir::new().unwrap();
+    let mut config = test_config(dir.path());
+    config.cache.enabled = false;
+
+    let turbo_cdn = TurboCdn::builder()
+        .with_config(config)
+        .build()
+        .await
+        .unwrap();
+    assert!(turbo_cdn.download_cache().is_none());
+
+    let url = format!("{}/artifact.bin", server.uri());
+    for name in ["a.bin", "b.bin"] {
+        let result = turbo_cdn
+            .download_direct_to_path(&url, dir.path().join(name))
+            .await
+            .unwrap();
+        assert!(!result.from_cache);
+    }
+
+    assert_eq!(get_requests(&server).await, 2);
+}
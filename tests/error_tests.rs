@@ -129,6 +129,32 @@ fn test_unsupported_error_creation() {
     );
 }
 
+#[test]
+fn test_offline_error_creation() {
+    let error = TurboCdnError::offline("https://example.com/file.zip");
+
+    assert_eq!(error.category(), "offline");
+    assert!(!error.is_retryable());
+    assert!(!error.should_try_next_mirror());
+    assert_eq!(
+        error.to_string(),
+        "Offline mode: https://example.com/file.zip is not available locally"
+    );
+}
+
+#[test]
+fn test_insufficient_space_error_creation() {
+    let error = TurboCdnError::insufficient_space("/tmp/toolchain.tar.gz", 4096, 1024);
+
+    assert_eq!(error.category(), "insufficient_space");
+    assert!(!error.is_retryable());
+    assert!(!error.should_try_next_mirror());
+    assert_eq!(
+        error.to_string(),
+        "Insufficient disk space for /tmp/toolchain.tar.gz: 4096 bytes required, 1024 bytes available"
+    );
+}
+
 #[test]
 fn test_internal_error_creation() {
     let error = TurboCdnError::internal("Internal error occurred");
@@ -209,6 +235,11 @@ fn test_error_categories_comprehensive() {
         (TurboCdnError::timeout("test"), "timeout"),
         (TurboCdnError::checksum_mismatch("a", "b"), "checksum"),
         (TurboCdnError::file_not_found("test"), "file_not_found"),
+        (TurboCdnError::offline("test"), "offline"),
+        (
+            TurboCdnError::insufficient_space("test", 2, 1),
+            "insufficient_space",
+        ),
         (TurboCdnError::unsupported("test"), "unsupported"),
         (TurboCdnError::internal("test"), "internal"),
     ];
@@ -0,0 +1,130 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Private GitHub release asset tests
+//!
+//! A mock GitHub API redirects asset requests to a mock storage host, as
+//! github.com does for private repositories.
+
+mod common;
+
+use tempfile::TempDir;
+use turbo_cdn::config::HostCredentialConfig;
+use turbo_cdn::*;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const BODY: &[u8] = b"private release asset";
+
+struct Mocks {
+    api: MockServer,
+    storage: MockServer,
+}
+
+async fn start_mocks() -> Mocks {
+    let api = MockServer::start().await;
+    let storage = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/private/releases/assets/7"))
+        .and(header("accept", "application/octet-stream"))
+        .respond_with(ResponseTemplate::new(302).insert_header(
+            "location",
+            format!("{}/signed/tool.zip?sig=abc", storage.uri()),
+        ))
+        .mount(&api)
+        .await;
+    for verb in ["HEAD", "GET"] {
+        Mock::given(method(verb))
+            .and(path("/signed/tool.zip"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(BODY))
+            .mount(&storage)
+            .await;
+    }
+
+    Mocks { api, storage }
+}
+
+fn test_config(dir: &TempDir, api: &MockServer) -> TurboCdnConfig {
+    let mut config = common::test_config(dir.path());
+    config.cache.enabled = true;
+    config.github.api_base = api.uri();
+    config.credentials.hosts = vec![HostCredentialConfig {
+        host: api.address().to_string(),
+        token: Some("ghp_test".to_string()),
+        username: None,
+        password: None,
+    }];
+    config
+}
+
+#[tokio::test]
+async fn test_download_private_asset() {
+    let mocks = start_mocks().await;
+    let dir = TempDir::new().unwrap();
+    let turbo_cdn = TurboCdn::with_config(test_config(&dir, &mocks.api))
+        .await
+        .unwrap();
+
+    let result = turbo_cdn
+        .download_github_asset("owner", "private", 7, dir.path().join("tool.zip"))
+        .await
+        .unwrap();
+
+    assert_eq!(std::fs::read(&result.path).unwrap(), BODY);
+    assert!(result.url.starts_with(&mocks.storage.uri()));
+
+    let api_requests = mocks.api.received_requests().await.unwrap();
+    assert!(api_requests
+        .iter()
+        .all(|r| r.headers.contains_key("authorization")));
+    let storage_requests = mocks.storage.received_requests().await.unwrap();
+    assert!(!storage_requests.is_empty());
+    assert!(storage_requests
+        .iter()
+        .all(|r| !r.headers.contains_key("authorization")));
+}
+
+#[tokio::test]
+async fn test_private_asset_served_from_cache() {
+    let mocks = start_mocks().await;
+    let dir = TempDir::new().unwrap();
+    let turbo_cdn = TurboCdn::with_config(test_config(&dir, &mocks.api))
+        .await
+        .unwrap();
+
+    turbo_cdn
+        .download_github_asset("owner", "private", 7, dir.path().join("first.zip"))
+        .await
+        .unwrap();
+    let api_requests = mocks.api.received_requests().await.unwrap().len();
+
+    let result = turbo_cdn
+        .download_github_asset("owner", "private", 7, dir.path().join("second.zip"))
+        .await
+        .unwrap();
+
+    assert!(result.from_cache);
+    assert_eq!(std::fs::read(&result.path).unwrap(), BODY);
+    assert_eq!(
+        mocks.api.received_requests().await.unwrap().len(),
+        api_requests
+    );
+}
+
+#[tokio::test]
+async fn test_missing_asset_reports_not_found() {
+    let mocks = start_mocks().await;
+    let dir = TempDir::new().unwrap();
+    let turbo_cdn = TurboCdn::with_config(test_config(&dir, &mocks.api))
+        .await
+        .unwrap();
+
+    let error = turbo_cdn
+        .download_github_asset("owner", "private", 8, dir.path().join("tool.zip"))
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.status_code(), Some(404));
+    assert!(mocks.storage.received_requests().await.unwrap().is_empty());
+}
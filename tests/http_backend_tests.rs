@@ -0,0 +1,194 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Custom HTTP backend tests
+//!
+//! A backend set on the builder sends the requests of every subsystem:
+//! downloads, GitHub API calls and region detection all reach it, and
+//! nothing goes out on the network.
+
+mod common;
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+use turbo_cdn::*;
+
+const ARTIFACT: &str = "https://artifacts.example/tool.bin";
+const BODY: &[u8] = b"served from memory";
+
+/// Answers requests from a table of canned responses
+#[derive(Default)]
+struct MemoryBackend {
+    responses: HashMap<String, (u16, Vec<u8>)>,
+    requests: Mutex<Vec<(String, String)>>,
+}
+
+impl MemoryBackend {
+    fn with(mut self, url: &str, status: u16, body: impl Into<Vec<u8>>) -> Self {
+        self.responses
+            .insert(url.to_string(), (status, body.into()));
+        self
+    }
+
+    fn requests(&self) -> Vec<(String, String)> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl HttpBackend for MemoryBackend {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    async fn execute(&self, request: reqwest::Request) -> Result<reqwest::Response> {
+        let url = request.url().to_string();
+        self.requests
+            .lock()
+            .unwrap()
+            .push((request.method().to_string(), url.clone()));
+
+        let (status, body) = self
+            .responses
+            .get(&url)
+            .cloned()
+            .unwrap_or((404, Vec::new()));
+        let length = body.len();
+        let body = if request.method() == reqwest::Method::HEAD {
+            Vec::new()
+        } else {
+            body
+        };
+        let response = http::Response::builder()
+            .status(status)
+            .header("content-length", length)
+            .body(body)
+            .unwrap();
+        Ok(reqwest::Response::from(response))
+    }
+}
+
+fn test_config(dir: &TempDir) -> TurboCdnConfig {
+    let mut config = common::test_config(dir.path());
+    config.github.api_base = "https://api.example".to_string();
+    config
+}
+
+#[tokio::test]
+async fn test_downloads_use_backend() {
+    let dir = TempDir::new().unwrap();
+    let backend = Arc::new(MemoryBackend::default().with(ARTIFACT, 200, BODY));
+
+    let turbo_cdn = TurboCdn::builder()
+        .with_config(test_config(&dir))
+        .with_http_backend(backend.clone())
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(turbo_cdn.http_backend().name(), "memory");
+
+    let result = turbo_cdn
+        .download_to_path(ARTIFACT, dir.path().join("tool.bin"))
+        .await
+        .unwrap();
+
+    assert_eq!(std::fs::read(&result.path).unwrap(), BODY);
+    assert_eq!(
+        backend.requests(),
+        [
+            ("HEAD".to_string(), ARTIFACT.to_string()),
+            ("GET".to_string(), ARTIFACT.to_string())
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_github_api_uses_backend() {
+    let dir = TempDir::new().unwrap();
+    let releases = serde_json::json!([
+        { "tag_name": "v2.0.0", "name": null, "prerelease": false, "draft": false,
+          "published_at": null, "assets": [] }
+    ]);
+    let backend = Arc::new(MemoryBackend::default().with(
+        "https://api.example/repos/owner/repo/releases?per_page=100",
+        200,
+        releases.to_string(),
+    ));
+
+    let turbo_cdn = TurboCdn::builder()
+        .with_config(test_config(&dir))
+        .with_http_backend(backend.clone())
+        .build()
+        .await
+        .unwrap();
+    let resolution = turbo_cdn
+        .resolve_url("https://github.com/owner/repo/releases/latest/download/tool.zip")
+        .await
+        .unwrap();
+
+    assert_eq!(resolution.resolved_tag.as_deref(), Some("v2.0.0"));
+    assert!(backend
+        .requests()
+        .iter()
+        .all(|(_, url)| url.starts_with("https://api.example/")));
+}
+
+#[tokio::test]
+async fn test_region_detection_uses_backend() {
+    let dir = TempDir::new().unwrap();
+    let location = serde_json::json!({
+        "status": "success", "country": "China", "countryCode": "CN",
+        "region": "BJ", "regionName": "Beijing", "city": "Beijing",
+        "timezone": "Asia/Shanghai"
+    });
+    let backend = Arc::new(MemoryBackend::default().with(
+        "http://ip-api.com/json/?fields=status,country,countryCode,region,regionName,city,timezone",
+        200,
+        location.to_string(),
+    ));
+    let mut config = test_config(&dir);
+    config.geo_detection.auto_detect_region = true;
+    config.general.default_region = Region::Global;
+
+    let turbo_cdn = TurboCdn::builder()
+        .with_config(config)
+        .with_http_backend(backend.clone())
+        .build()
+        .await
+        .unwrap();
+
+    assert_eq!(turbo_cdn.explain_url(ARTIFACT).await.region, "China");
+    assert_eq!(backend.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_mirror_checks_and_probes_use_backend() {
+    let dir = TempDir::new().unwrap();
+    let backend = Arc::new(MemoryBackend::default().with(ARTIFACT, 200, BODY));
+
+    let turbo_cdn = TurboCdn::builder()
+        .with_config(test_config(&dir))
+        .with_http_backend(backend.clone())
+        .build()
+        .await
+        .unwrap();
+
+    let health = turbo_cdn.check_mirror_health().await.unwrap();
+    assert!(!health.is_empty());
+    assert_eq!(backend.requests().len(), health.len());
+
+    let response = turbo_cdn.http_client_manager().get(ARTIFACT).await.unwrap();
+    assert_eq!(response.body, BODY);
+
+    let metrics = turbo_cdn
+        .quality_assessor()
+        .assess_cdn_quality(ARTIFACT)
+        .await
+        .unwrap();
+    assert!(metrics.availability > 0.0);
+    assert!(backend.requests()[health.len()..]
+        .iter()
+        .all(|(_, url)| url == ARTIFACT));
+}
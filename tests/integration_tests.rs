@@ -91,6 +91,40 @@ fn test_sync_api() {
     assert!(!optimal.is_empty());
 }
 
+/// Test that explain reports the configured region and the rule used
+#[tokio::test]
+async fn test_explain_url() {
+    let cdn = TurboCdn::builder()
+        .with_auto_detect_region(false)
+        .with_region(Region::China)
+        .build()
+        .await
+        .expect("Failed to create TurboCdn");
+
+    let url = "https://github.com/user/repo/releases/download/v1.0.0/file.zip";
+    let explanation = cdn.explain_url(url).await;
+
+    assert_eq!(explanation.region, "China");
+    assert!(matches!(
+        explanation.region_source,
+        RegionSource::Configured
+    ));
+    let used: Vec<_> = explanation
+        .rules
+        .iter()
+        .filter(|r| r.outcome == RuleOutcome::Used)
+        .collect();
+    assert_eq!(used.len(), 1);
+    assert!(explanation
+        .candidates
+        .iter()
+        .any(|c| c.url == url && c.rule.is_none()));
+    assert_eq!(
+        explanation.urls,
+        cdn.get_all_cdn_urls(url).await.expect("Failed to map URL")
+    );
+}
+
 /// Test async API module
 #[tokio::test]
 async fn test_async_api_module() {
@@ -0,0 +1,74 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! `releases/latest` resolution tests
+//!
+//! A mock GitHub API lists releases so that latest download URLs are
+//! rewritten to the newest stable tag before mirror mapping.
+
+use turbo_cdn::*;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const LATEST_URL: &str = "https://github.com/owner/repo/releases/latest/download/tool.zip";
+
+async fn mock_api() -> MockServer {
+    let api = MockServer::start().await;
+    let releases = serde_json::json!([
+        { "tag_name": "v3.0.0-rc.1", "name": null, "prerelease": true, "draft": false,
+          "published_at": null, "assets": [] },
+        { "tag_name": "v2.0.0", "name": null, "prerelease": false, "draft": false,
+          "published_at": null, "assets": [] },
+        { "tag_name": "v1.0.0", "name": null, "prerelease": false, "draft": false,
+          "published_at": null, "assets": [] }
+    ]);
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(releases))
+        .expect(1)
+        .mount(&api)
+        .await;
+    api
+}
+
+async fn client(api: &MockServer) -> TurboCdn {
+    let mut config = TurboCdnConfig::load().unwrap();
+    config.geo_detection.auto_detect_region = false;
+    config.general.default_region = Region::China;
+    config.cache.enabled = false;
+    config.github.api_base = api.uri();
+    TurboCdn::with_config(config).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_latest_release_resolved_before_mapping() {
+    let api = mock_api().await;
+    let turbo_cdn = client(&api).await;
+
+    let resolution = turbo_cdn.resolve_url(LATEST_URL).await.unwrap();
+    let concrete = "https://github.com/owner/repo/releases/download/v2.0.0/tool.zip";
+
+    assert_eq!(resolution.url, LATEST_URL);
+    assert_eq!(resolution.resolved_tag.as_deref(), Some("v2.0.0"));
+    assert!(resolution.candidates.iter().any(|c| c.url == concrete));
+    assert!(resolution
+        .candidates
+        .iter()
+        .all(|c| !c.url.contains("/releases/latest/")));
+
+    // The tag is remembered, the mock expects a single API call
+    let urls = turbo_cdn.get_all_cdn_urls(LATEST_URL).await.unwrap();
+    assert!(urls.contains(&format!("https://ghproxy.net/{concrete}")));
+}
+
+#[tokio::test]
+async fn test_tagged_release_not_resolved() {
+    let api = MockServer::start().await;
+    let turbo_cdn = client(&api).await;
+
+    let url = "https://github.com/owner/repo/releases/download/v1.0.0/tool.zip";
+    let resolution = turbo_cdn.resolve_url(url).await.unwrap();
+
+    assert!(resolution.resolved_tag.is_none());
+    assert!(api.received_requests().await.unwrap().is_empty());
+}
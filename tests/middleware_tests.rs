@@ -0,0 +1,161 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Request middleware tests
+//!
+//! Middleware registered on the builder sees the probe, download and GitHub
+//! API requests of a client, can add headers to them and can stop them.
+
+mod common;
+
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+use turbo_cdn::*;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const BODY: &[u8] = b"traced artifact";
+
+/// Adds a trace header and records what it saw
+#[derive(Default)]
+struct Audit {
+    requests: Mutex<Vec<(RequestKind, String)>>,
+    responses: Mutex<Vec<(RequestKind, u16)>>,
+}
+
+#[async_trait]
+impl RequestMiddleware for Audit {
+    async fn on_request(&self, request: &mut reqwest::Request, kind: RequestKind) -> Result<()> {
+        request
+            .headers_mut()
+            .insert("x-trace-id", "build-42".parse().unwrap());
+        self.requests
+            .lock()
+            .unwrap()
+            .push((kind, request.url().to_string()));
+        Ok(())
+    }
+
+    async fn on_response(&self, response: &reqwest::Response, kind: RequestKind) {
+        self.responses
+            .lock()
+            .unwrap()
+            .push((kind, response.status().as_u16()));
+    }
+}
+
+/// Refuses every request
+struct Deny;
+
+#[async_trait]
+impl RequestMiddleware for Deny {
+    async fn on_request(&self, request: &mut reqwest::Request, _kind: RequestKind) -> Result<()> {
+        Err(TurboCdnError::compliance(format!(
+            "{} is not allowed",
+            request.url()
+        )))
+    }
+}
+
+fn test_config(dir: &TempDir, api: &MockServer) -> TurboCdnConfig {
+    let mut config = common::test_config(dir.path());
+    config.github.api_base = api.uri();
+    config
+}
+
+async fn mount_artifact(server: &MockServer) {
+    for verb in ["HEAD", "GET"] {
+        Mock::given(method(verb))
+            .and(path("/artifact.bin"))
+            .and(header("x-trace-id", "build-42"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(BODY))
+            .mount(server)
+            .await;
+    }
+}
+
+#[tokio::test]
+async fn test_middleware_sees_download_requests() {
+    let server = MockServer::start().await;
+    mount_artifact(&server).await;
+    let dir = TempDir::new().unwrap();
+    let audit = Arc::new(Audit::default());
+
+    let turbo_cdn = TurboCdn::builder()
+        .with_config(test_config(&dir, &server))
+        .with_middleware(audit.clone())
+        .build()
+        .await
+        .unwrap();
+    let url = format!("{}/artifact.bin", server.uri());
+    let result = turbo_cdn
+        .download_to_path(&url, dir.path().join("artifact.bin"))
+        .await
+        .unwrap();
+
+    assert_eq!(std::fs::read(&result.path).unwrap(), BODY);
+    let kinds: Vec<RequestKind> = audit.requests.lock().unwrap().iter().map(|r| r.0).collect();
+    assert_eq!(kinds, [RequestKind::Probe, RequestKind::Download]);
+    assert!(audit.requests.lock().unwrap().iter().all(|r| r.1 == url));
+    assert_eq!(
+        *audit.responses.lock().unwrap(),
+        [(RequestKind::Probe, 200), (RequestKind::Download, 200)]
+    );
+}
+
+#[tokio::test]
+async fn test_middleware_sees_github_api_calls() {
+    let api = MockServer::start().await;
+    let releases = serde_json::json!([
+        { "tag_name": "v1.0.0", "name": null, "prerelease": false, "draft": false,
+          "published_at": null, "assets": [] }
+    ]);
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases"))
+        .and(header("x-trace-id", "build-42"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(releases))
+        .mount(&api)
+        .await;
+    let dir = TempDir::new().unwrap();
+    let audit = Arc::new(Audit::default());
+
+    let turbo_cdn = TurboCdn::builder()
+        .with_config(test_config(&dir, &api))
+        .with_middleware(audit.clone())
+        .build()
+        .await
+        .unwrap();
+    let resolution = turbo_cdn
+        .resolve_url("https://github.com/owner/repo/releases/latest/download/tool.zip")
+        .await
+        .unwrap();
+
+    assert_eq!(resolution.resolved_tag.as_deref(), Some("v1.0.0"));
+    assert_eq!(
+        *audit.responses.lock().unwrap(),
+        [(RequestKind::GitHubApi, 200)]
+    );
+}
+
+#[tokio::test]
+async fn test_middleware_error_stops_request() {
+    let server = MockServer::start().await;
+    mount_artifact(&server).await;
+    let dir = TempDir::new().unwrap();
+
+    let turbo_cdn = TurboCdn::builder()
+        .with_config(test_config(&dir, &server))
+        .with_middleware(Arc::new(Deny))
+        .build()
+        .await
+        .unwrap();
+    let url = format!("{}/artifact.bin", server.uri());
+    let error = turbo_cdn
+        .download_to_path(&url, dir.path().join("artifact.bin"))
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("is not allowed"), "{error}");
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
@@ -0,0 +1,233 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Mirror catalog tests
+//!
+//! Probe mirror health check URLs against a local server, verify that the
+//! embedded catalog covers every mirror the default rules reference and that
+//! the downloader skips mirrors whose capabilities don't fit the request.
+
+use tempfile::TempDir;
+use turbo_cdn::config::{MirrorCapabilities, MirrorConfig, MirrorStyle};
+use turbo_cdn::mirror::check_health;
+use turbo_cdn::*;
+use wiremock::matchers::{method, path, path_regex};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+fn backend() -> ReqwestBackend {
+    ReqwestBackend::from_config(&TurboCdnConfig::default()).unwrap()
+}
+
+fn mirror(name: &str, url: String, health_check_url: Option<String>) -> MirrorConfig {
+    MirrorConfig {
+        name: name.to_string(),
+        url,
+        style: MirrorStyle::PrefixProxy,
+        regions: vec![],
+        capabilities: Default::default(),
+        health_check_url,
+        enabled: true,
+    }
+}
+
+#[tokio::test]
+async fn test_health_check_probes_configured_url() {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .and(path("/healthz"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("HEAD"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+
+    let backend = backend();
+    let healthy = mirror(
+        "healthy",
+        format!("{}/", server.uri()),
+        Some(format!("{}/healthz", server.uri())),
+    );
+    let health = check_health(&backend, TIMEOUT, &healthy).await;
+    assert!(health.is_healthy());
+    assert_eq!(health.status, Some(200));
+
+    // Without a health check URL the mirror URL itself is probed
+    let down = mirror("down", format!("{}/", server.uri()), None);
+    let health = check_health(&backend, TIMEOUT, &down).await;
+    assert!(!health.is_healthy());
+    assert_eq!(health.status, Some(503));
+}
+
+#[tokio::test]
+async fn test_health_check_reports_connection_errors() {
+    let unreachable = mirror("gone", "http://127.0.0.1:9/".to_string(), None);
+    let health = check_health(&backend(), TIMEOUT, &unreachable).await;
+
+    assert!(!health.is_healthy());
+    assert!(health.status.is_none());
+    assert!(health.error.is_some());
+}
+
+#[test]
+fn test_default_rules_reference_known_mirrors() {
+    let config = TurboCdnConfig::load().unwrap();
+    let catalog = MirrorCatalog::new(&config.mirrors, &config.mapping.disabled_mirrors).unwrap();
+    assert!(!catalog.is_empty());
+
+    for rule in &config.url_mapping_rules {
+        for name in rule.replacements.iter().filter_map(|r| r.mirror()) {
+            assert!(
+                catalog.get(name).is_some(),
+                "rule '{}' references unknown mirror '{name}'",
+                rule.name
+            );
+        }
+    }
+
+    let mapper = UrlMapper::new(&config, Region::Global).unwrap();
+    assert_eq!(mapper.rule_count(), config.url_mapping_rules.len());
+}
+
+const BODY: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Serves the body at /file.bin, and counts requests to the /limited mirror
+async fn origin_with_limited_mirror() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(path("/file.bin"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(BODY))
+        .mount(&server)
+        .await;
+    Mock::given(path_regex("^/limited/"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(BODY))
+        .expect(0)
+        .mount(&server)
+        .await;
+    server
+}
+
+fn config_with_mirror(server: &MockServer, capabilities: MirrorCapabilities) -> TurboCdnConfig {
+    let mut limited = mirror("limited", format!("{}/limited", server.uri()), None);
+    limited.style = MirrorStyle::PathRewrite;
+    limited.capabilities = capabilities;
+
+    let mut config = TurboCdnConfig::default();
+    config.performance.http2_prior_knowledge = false;
+    config.geo_detection.auto_detect_region = false;
+    config.cache.enabled = false;
+    config.mirrors = vec![limited];
+    config
+}
+
+#[tokio::test]
+async fn test_oversized_file_skips_limited_mirror() {
+    let server = origin_with_limited_mirror().await;
+    let config = config_with_mirror(
+        &server,
+        MirrorCapabilities {
+            max_file_size: Some(10),
+            ..Default::default()
+        },
+    );
+    let downloader = ConcurrentDownloader::with_config(&config).unwrap();
+    let dir = TempDir::new().unwrap();
+    let mirror_url = format!("{}/limited/file.bin", server.uri());
+
+    let result = downloader
+        .download_in_order(
+            &[mirror_url.clone(), format!("{}/file.bin", server.uri())],
+            dir.path().join("file.bin"),
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(result.url, format!("{}/file.bin", server.uri()));
+    assert!(downloader.get_server_detail(&mirror_url).is_none());
+}
+
+#[tokio::test]
+async fn test_resume_skips_mirror_without_ranges() {
+    let server = origin_with_limited_mirror().await;
+    let config = config_with_mirror(
+        &server,
+        MirrorCapabilities {
+            range_requests: false,
+            ..Default::default()
+        },
+    );
+    let downloader = ConcurrentDownloader::with_config(&config).unwrap();
+    let dir = TempDir::new().unwrap();
+    let output = dir.path().join("file.bin");
+    std::fs::write(&output, &BODY[..5]).unwrap();
+
+    let error = downloader
+        .download_in_order(
+            &[format!("{}/limited/file.bin", server.uri())],
+            &output,
+            None,
+        )
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("cannot resume"), "{error}");
+}
+
+#[tokio::test]
+async fn test_incompatible_mirrors_are_dropped_before_truncating() {
+    let server = origin_with_limited_mirror().await;
+    let config = config_with_mirror(
+        &server,
+        MirrorCapabilities {
+            max_file_size: Some(10),
+            ..Default::default()
+        },
+    );
+    let downloader = ConcurrentDownloader::with_config(&config).unwrap();
+    let dir = TempDir::new().unwrap();
+
+    // More limited candidates than are ever tried, ahead of the origin
+    let mut urls: Vec<String> = (0..MAX_URLS_TO_TRY + 2)
+        .map(|n| format!("{}/limited/file.bin?n={n}", server.uri()))
+        .collect();
+    urls.push(format!("{}/file.bin", server.uri()));
+
+    let result = downloader
+        .download_in_order(&urls, dir.path().join("file.bin"), None)
+        .await
+        .unwrap();
+    assert_eq!(result.url, format!("{}/file.bin", server.uri()));
+}
+
+#[tokio::test]
+async fn test_downloader_follows_mapper_catalog() {
+    let server = origin_with_limited_mirror().await;
+    let mut config = config_with_mirror(&server, MirrorCapabilities::default());
+    let downloader = ConcurrentDownloader::with_config(&config).unwrap();
+    let mut mapper = UrlMapper::new(&config, Region::Global)
+        .unwrap()
+        .with_shared_mirrors(downloader.shared_mirrors());
+
+    // The limit only arrives with a rebuilt mapper
+    config.mirrors[0].capabilities.max_file_size = Some(10);
+    mapper.reconfigure(&config).unwrap();
+
+    let dir = TempDir::new().unwrap();
+    let result = downloader
+        .download_in_order(
+            &[
+                format!("{}/limited/file.bin", server.uri()),
+                format!("{}/file.bin", server.uri()),
+            ],
+            dir.path().join("file.bin"),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.url, format!("{}/file.bin", server.uri()));
+}
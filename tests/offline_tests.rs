@@ -0,0 +1,132 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Offline mode tests
+//!
+//! Verify that offline mode never reaches the network and answers only from
+//! the local download cache and persisted metadata.
+
+mod common;
+
+use tempfile::TempDir;
+use turbo_cdn::geo_detection::GeoDetector;
+use turbo_cdn::*;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const BODY: &[u8] = b"offline artifact";
+
+fn test_config(dir: &std::path::Path, offline: bool) -> TurboCdnConfig {
+    let mut config = common::test_config(dir);
+    config.cache.enabled = true;
+    config.general.offline = offline;
+    config
+}
+
+#[tokio::test]
+async fn test_offline_serves_cached_download() {
+    let server = MockServer::start().await;
+    for verb in ["HEAD", "GET"] {
+        Mock::given(method(verb))
+            .and(path("/tool.tar.gz"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("etag", "\"1\"")
+                    .set_body_bytes(BODY),
+            )
+            .mount(&server)
+            .await;
+    }
+
+    let dir = TempDir::new().unwrap();
+    let url = format!("{}/tool.tar.gz", server.uri());
+
+    let online = TurboCdn::with_config(test_config(dir.path(), false))
+        .await
+        .unwrap();
+    online
+        .download_to_path(&url, dir.path().join("online.tar.gz"))
+        .await
+        .unwrap();
+    let requests_before = server.received_requests().await.unwrap().len();
+
+    let offline = TurboCdn::with_config(test_config(dir.path(), true))
+        .await
+        .unwrap();
+    let result = offline
+        .download_to_path(&url, dir.path().join("offline.tar.gz"))
+        .await
+        .unwrap();
+
+    assert!(result.from_cache);
+    assert_eq!(std::fs::read(&result.path).unwrap(), BODY);
+    assert_eq!(
+        server.received_requests().await.unwrap().len(),
+        requests_before
+    );
+}
+
+#[tokio::test]
+async fn test_offline_cache_miss_reports_missing_resource() {
+    let dir = TempDir::new().unwrap();
+    let turbo_cdn = TurboCdn::with_config(test_config(dir.path(), true))
+        .await
+        .unwrap();
+
+    let url = "https://github.com/owner/repo/releases/download/v1.0.0/tool.zip";
+    let error = turbo_cdn
+        .download_to_path(url, dir.path().join("tool.zip"))
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.category(), "offline");
+    assert!(error.to_string().contains(url));
+}
+
+#[tokio::test]
+async fn test_offline_url_mapper_returns_original_only() {
+    let dir = TempDir::new().unwrap();
+    let config = test_config(dir.path(), true);
+    let mapper = UrlMapper::new(&config, Region::China).unwrap();
+
+    let url = "https://github.com/owner/repo/releases/download/v1.0.0/tool.zip";
+    assert_eq!(mapper.map_url(url).unwrap(), vec![url.to_string()]);
+}
+
+#[tokio::test]
+async fn test_offline_geo_detection_fails_fast() {
+    let dir = TempDir::new().unwrap();
+    let mut detector = GeoDetector::new(test_config(dir.path(), true)).unwrap();
+
+    let error = detector.detect_region().await.unwrap_err();
+    assert!(matches!(error, TurboCdnError::Offline { .. }));
+}
+
+#[tokio::test]
+async fn test_offline_github_releases_use_persisted_metadata() {
+    let dir = TempDir::new().unwrap();
+    let metadata_dir = dir.path().join("metadata");
+    let options = FetchOptions::new()
+        .with_offline(true)
+        .with_metadata_dir(&metadata_dir);
+    let fetcher = GitHubReleasesFetcher::with_options(options);
+
+    let error = fetcher.fetch_versions("owner", "repo").await.unwrap_err();
+    assert_eq!(error.category(), "offline");
+
+    let repo_dir = metadata_dir.join("github").join("owner");
+    std::fs::create_dir_all(&repo_dir).unwrap();
+    std::fs::write(
+        repo_dir.join("repo.json"),
+        r#"[{"tag_name":"v2.0.0","name":null,"prerelease":false,"draft":false,"published_at":null,"assets":[]},
+            {"tag_name":"v2.1.0-rc1","name":null,"prerelease":true,"draft":false,"published_at":null,"assets":[]}]"#,
+    )
+    .unwrap();
+
+    let result = fetcher
+        .fetch_versions_with_source("owner", "repo")
+        .await
+        .unwrap();
+    assert_eq!(result.source, DataSource::LocalCache);
+    assert_eq!(result.versions, vec!["v2.0.0".to_string()]);
+}
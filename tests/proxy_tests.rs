@@ -0,0 +1,125 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Outbound proxy tests
+//!
+//! Use a mock server as the HTTP proxy and verify that downloads are routed
+//! through it, and that per-URL rules and `no_proxy` bypass it.
+
+mod common;
+
+use tempfile::TempDir;
+use turbo_cdn::config::ProxyRuleConfig;
+use turbo_cdn::*;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const BODY: &[u8] = b"proxied artifact";
+
+/// Unreachable address used where a proxy must never be contacted
+const DEAD_PROXY: &str = "http://127.0.0.1:9";
+
+async fn start_server() -> MockServer {
+    let server = MockServer::start().await;
+    for verb in ["HEAD", "GET"] {
+        Mock::given(method(verb))
+            .and(path("/tool.bin"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(BODY))
+            .mount(&server)
+            .await;
+    }
+    server
+}
+
+fn test_config(dir: &TempDir) -> TurboCdnConfig {
+    let mut config = common::test_config(dir.path());
+    config.performance.retry_attempts = 0;
+    config
+}
+
+#[tokio::test]
+async fn test_download_routed_through_http_proxy() {
+    let proxy = start_server().await;
+    let dir = TempDir::new().unwrap();
+
+    let mut config = test_config(&dir);
+    config.network.proxy.http = Some(proxy.uri());
+    let turbo_cdn = TurboCdn::with_config(config).await.unwrap();
+
+    // The origin host does not exist; only the proxy can answer
+    let result = turbo_cdn
+        .download_to_path(
+            "http://origin.invalid/tool.bin",
+            dir.path().join("tool.bin"),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(std::fs::read(&result.path).unwrap(), BODY);
+    let requests = proxy.received_requests().await.unwrap();
+    assert!(!requests.is_empty());
+    assert!(requests
+        .iter()
+        .all(|r| r.url.host_str() == Some("origin.invalid")));
+}
+
+#[tokio::test]
+async fn test_direct_rule_bypasses_proxy() {
+    let origin = start_server().await;
+    let dir = TempDir::new().unwrap();
+
+    let mut config = test_config(&dir);
+    config.network.proxy.http = Some(DEAD_PROXY.to_string());
+    config.network.proxy.rules = vec![ProxyRuleConfig {
+        pattern: r"^http://127\.0\.0\.1:\d+/".to_string(),
+        proxy: "direct".to_string(),
+    }];
+    let turbo_cdn = TurboCdn::with_config(config).await.unwrap();
+
+    let url = format!("{}/tool.bin", origin.uri());
+    let result = turbo_cdn
+        .download_to_path(&url, dir.path().join("tool.bin"))
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&result.path).unwrap(), BODY);
+}
+
+#[tokio::test]
+async fn test_no_proxy_bypasses_proxy() {
+    let origin = start_server().await;
+    let dir = TempDir::new().unwrap();
+
+    let mut config = test_config(&dir);
+    config.network.proxy.set_all(DEAD_PROXY);
+    config.network.proxy.no_proxy = vec!["127.0.0.1".to_string()];
+    let turbo_cdn = TurboCdn::with_config(config).await.unwrap();
+
+    let url = format!("{}/tool.bin", origin.uri());
+    let result = turbo_cdn
+        .download_to_path(&url, dir.path().join("tool.bin"))
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&result.path).unwrap(), BODY);
+}
+
+#[tokio::test]
+async fn test_invalid_proxy_is_rejected() {
+    let dir = TempDir::new().unwrap();
+
+    let mut config = test_config(&dir);
+    config.network.proxy.http = Some("not a proxy".to_string());
+
+    let error = TurboCdn::with_config(config).await.unwrap_err();
+    assert_eq!(error.category(), "config");
+}
+
+#[test]
+fn test_geo_detector_rejects_invalid_proxy() {
+    let dir = TempDir::new().unwrap();
+
+    let mut config = test_config(&dir);
+    config.network.proxy.http = Some("not a proxy".to_string());
+
+    let error = geo_detection::GeoDetector::new(config).unwrap_err();
+    assert_eq!(error.category(), "config");
+}
@@ -0,0 +1,107 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Runtime rule management tests
+//!
+//! Rules added, removed or toggled through the API are validated like
+//! configuration rules, ordered by priority and invalidate cached mappings.
+
+use turbo_cdn::config::{ReplacementConfig, UrlMappingRuleConfig};
+use turbo_cdn::*;
+
+const NODE_URL: &str = "https://nodejs.org/dist/v20.11.0/node-v20.11.0-linux-x64.tar.xz";
+const NODE_MIRROR: &str =
+    "https://npmmirror.com/mirrors/node/v20.11.0/node-v20.11.0-linux-x64.tar.xz";
+
+fn node_rule(name: &str, mirror: &str, priority: u32) -> UrlMappingRuleConfig {
+    UrlMappingRuleConfig {
+        name: name.to_string(),
+        pattern: r"^https://nodejs\.org/dist/(.+)$".to_string(),
+        replacements: vec![ReplacementConfig::Url(format!("{mirror}/$1"))],
+        regions: vec![],
+        priority,
+        enabled: true,
+        examples: vec![],
+    }
+}
+
+fn builder() -> TurboCdnBuilder {
+    let mut config = TurboCdnConfig::load().unwrap();
+    config.general.default_region = Region::China;
+    config.geo_detection.auto_detect_region = false;
+    config.cache.enabled = false;
+    TurboCdn::builder().with_config(config)
+}
+
+async fn optimal_url(turbo_cdn: &TurboCdn) -> String {
+    turbo_cdn.get_optimal_url(NODE_URL).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_add_and_remove_rule() {
+    let turbo_cdn = builder().build().await.unwrap();
+    assert_eq!(optimal_url(&turbo_cdn).await, NODE_URL);
+
+    turbo_cdn
+        .add_rule(node_rule("Node", "https://npmmirror.com/mirrors/node", 0))
+        .await
+        .unwrap();
+    assert_eq!(optimal_url(&turbo_cdn).await, NODE_MIRROR);
+    assert!(turbo_cdn
+        .list_rules()
+        .await
+        .iter()
+        .any(|rule| rule.name == "Node"));
+
+    turbo_cdn.set_rule_enabled("Node", false).await.unwrap();
+    assert_eq!(optimal_url(&turbo_cdn).await, NODE_URL);
+    turbo_cdn.set_rule_enabled("Node", true).await.unwrap();
+    assert_eq!(optimal_url(&turbo_cdn).await, NODE_MIRROR);
+
+    turbo_cdn.remove_rule("Node").await.unwrap();
+    assert_eq!(optimal_url(&turbo_cdn).await, NODE_URL);
+    assert!(turbo_cdn.remove_rule("Node").await.is_err());
+    assert!(turbo_cdn.set_rule_enabled("Node", true).await.is_err());
+}
+
+#[tokio::test]
+async fn test_rules_follow_priority() {
+    let turbo_cdn = builder()
+        .with_rule(node_rule("Fallback", "https://fallback.example/node", 200))
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(
+        optimal_url(&turbo_cdn).await,
+        "https://fallback.example/node/v20.11.0/node-v20.11.0-linux-x64.tar.xz"
+    );
+
+    turbo_cdn
+        .add_rule(node_rule("Node", "https://npmmirror.com/mirrors/node", 0))
+        .await
+        .unwrap();
+    assert_eq!(optimal_url(&turbo_cdn).await, NODE_MIRROR);
+}
+
+#[tokio::test]
+async fn test_invalid_rules_are_rejected() {
+    let turbo_cdn = builder().build().await.unwrap();
+    let rules = turbo_cdn.list_rules().await.len();
+
+    let mut broken = node_rule("Broken", "https://npmmirror.com/mirrors/node", 0);
+    broken.pattern = "^https://nodejs.org/(dist".to_string();
+    let error = turbo_cdn.add_rule(broken.clone()).await.unwrap_err();
+    assert!(error.to_string().contains("Broken"), "{error}");
+
+    turbo_cdn
+        .add_rule(node_rule("Node", "https://npmmirror.com/mirrors/node", 0))
+        .await
+        .unwrap();
+    let duplicate = node_rule("Node", "https://other.example/node", 0);
+    assert!(turbo_cdn.add_rule(duplicate).await.is_err());
+
+    assert_eq!(turbo_cdn.list_rules().await.len(), rules + 1);
+    assert_eq!(optimal_url(&turbo_cdn).await, NODE_MIRROR);
+
+    assert!(builder().with_rule(broken).build().await.is_err());
+}
@@ -0,0 +1,163 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Security policy tests
+//!
+//! Verify that `allowed_protocols` is enforced before requests and on
+//! redirects, and that invalid TLS settings are reported as configuration
+//! errors by every client that would use them.
+
+mod common;
+
+use tempfile::TempDir;
+use turbo_cdn::*;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const BODY: &[u8] = b"secure artifact";
+
+async fn mount(server: &MockServer, route: &str, response: ResponseTemplate) {
+    for verb in ["HEAD", "GET"] {
+        Mock::given(method(verb))
+            .and(path(route))
+            .respond_with(response.clone())
+            .mount(server)
+            .await;
+    }
+}
+
+#[tokio::test]
+async fn test_disallowed_protocol_rejected_before_request() {
+    let server = MockServer::start().await;
+    mount(
+        &server,
+        "/tool.bin",
+        ResponseTemplate::new(200).set_body_bytes(BODY),
+    )
+    .await;
+    let dir = TempDir::new().unwrap();
+
+    let mut config = common::test_config(dir.path());
+    config.security.allowed_protocols = vec!["https".to_string()];
+    let turbo_cdn = TurboCdn::with_config(config).await.unwrap();
+
+    let error = turbo_cdn
+        .download_to_path(
+            &format!("{}/tool.bin", server.uri()),
+            dir.path().join("tool.bin"),
+        )
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.category(), "compliance");
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_redirect_to_disallowed_protocol_rejected() {
+    let server = MockServer::start().await;
+    mount(
+        &server,
+        "/tool.bin",
+        ResponseTemplate::new(302).insert_header("location", "https://origin.invalid/tool.bin"),
+    )
+    .await;
+    let dir = TempDir::new().unwrap();
+
+    let mut config = common::test_config(dir.path());
+    config.security.allowed_protocols = vec!["http".to_string()];
+    let turbo_cdn = TurboCdn::with_config(config).await.unwrap();
+
+    let error = turbo_cdn
+        .download_to_path(
+            &format!("{}/tool.bin", server.uri()),
+            dir.path().join("tool.bin"),
+        )
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.category(), "compliance");
+    assert!(error
+        .to_string()
+        .contains("https://origin.invalid/tool.bin"));
+}
+
+#[tokio::test]
+async fn test_redirect_to_allowed_protocol_followed() {
+    let server = MockServer::start().await;
+    mount(
+        &server,
+        "/old.bin",
+        ResponseTemplate::new(302).insert_header("location", "/tool.bin"),
+    )
+    .await;
+    mount(
+        &server,
+        "/tool.bin",
+        ResponseTemplate::new(200).set_body_bytes(BODY),
+    )
+    .await;
+    let dir = TempDir::new().unwrap();
+
+    let mut config = common::test_config(dir.path());
+    config.security.allowed_protocols = vec!["http".to_string()];
+    let turbo_cdn = TurboCdn::with_config(config).await.unwrap();
+
+    let result = turbo_cdn
+        .download_to_path(
+            &format!("{}/old.bin", server.uri()),
+            dir.path().join("tool.bin"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&result.path).unwrap(), BODY);
+}
+
+#[tokio::test]
+async fn test_invalid_tls_settings_are_config_errors() {
+    let dir = TempDir::new().unwrap();
+
+    let mut config = common::test_config(dir.path());
+    config.security.ca_bundle = Some(dir.path().join("missing-ca.pem"));
+    let error = TurboCdn::with_config(config).await.unwrap_err();
+    assert_eq!(error.category(), "config");
+
+    let invalid_pem = dir.path().join("invalid.pem");
+    std::fs::write(&invalid_pem, "not a certificate").unwrap();
+    let mut config = common::test_config(dir.path());
+    config.security.client_cert = Some(invalid_pem);
+    let error = TurboCdn::with_config(config).await.unwrap_err();
+    assert_eq!(error.category(), "config");
+
+    let mut config = common::test_config(dir.path());
+    config.security.min_tls_version = Some("0.9".to_string());
+    let error = TurboCdn::with_config(config).await.unwrap_err();
+    assert_eq!(error.category(), "config");
+}
+
+#[test]
+fn test_geo_detector_keeps_tls_settings() {
+    let dir = TempDir::new().unwrap();
+
+    let mut config = common::test_config(dir.path());
+    config.security.ca_bundle = Some(dir.path().join("missing-ca.pem"));
+    let error = geo_detection::GeoDetector::new(config).unwrap_err();
+    assert_eq!(error.category(), "config");
+
+    let mut config = common::test_config(dir.path());
+    config.security.min_tls_version = Some("0.9".to_string());
+    let error = geo_detection::GeoDetector::new(config).unwrap_err();
+    assert_eq!(error.category(), "config");
+}
+
+#[test]
+fn test_security_config_check_url() {
+    let security = config::SecurityConfig::default();
+    assert!(security.check_url("https://example.com/file.zip").is_ok());
+    assert!(security.check_url("HTTP://example.com/file.zip").is_ok());
+
+    let error = security
+        .check_url("ftp://example.com/file.zip")
+        .unwrap_err();
+    assert!(matches!(error, TurboCdnError::Compliance { .. }));
+}
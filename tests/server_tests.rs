@@ -0,0 +1,381 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Local proxy server tests
+//!
+//! Start `ProxyServer` on an ephemeral port in front of a mock upstream and
+//! verify the `/fetch` and `/resolve` endpoints, forward-proxy requests,
+//! chunked streaming and range support.
+
+#![cfg(feature = "server")]
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use turbo_cdn::server::ProxyServer;
+use turbo_cdn::*;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+const BODY: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+const LARGE_SIZE: usize = 1024 * 1024;
+
+/// Delay of every chunk but the first one of the large file
+const LATER_CHUNK_DELAY: std::time::Duration = std::time::Duration::from_secs(3);
+
+fn large_body() -> Vec<u8> {
+    (0..LARGE_SIZE).map(|i| (i % 251) as u8).collect()
+}
+
+/// Serves `bytes=start-end` ranges of the body, holding back all but the first
+struct RangeResponder(Vec<u8>);
+
+impl Respond for RangeResponder {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let range = request
+            .headers
+            .get("range")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("bytes="))
+            .and_then(|v| v.split_once('-'))
+            .map(|(start, end)| {
+                (
+                    start.parse::<usize>().unwrap(),
+                    end.parse::<usize>().unwrap(),
+                )
+            });
+
+        match range {
+            Some((start, end)) => {
+                let response = ResponseTemplate::new(206)
+                    .insert_header("accept-ranges", "bytes")
+                    .set_body_bytes(self.0[start..=end].to_vec());
+                match start {
+                    0 => response,
+                    _ => response.set_delay(LATER_CHUNK_DELAY),
+                }
+            }
+            None => ResponseTemplate::new(200)
+                .insert_header("accept-ranges", "bytes")
+                .set_body_bytes(self.0.clone()),
+        }
+    }
+}
+
+async fn start_upstream() -> MockServer {
+    let server = MockServer::start().await;
+    for verb in ["HEAD", "GET"] {
+        Mock::given(method(verb))
+            .and(path("/pkg.tgz"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("etag", "\"pkg-1\"")
+                    .set_body_bytes(BODY),
+            )
+            .mount(&server)
+            .await;
+    }
+    server
+}
+
+async fn start_proxy(dir: &TempDir) -> SocketAddr {
+    start_proxy_with_mode(dir, false).await
+}
+
+async fn start_proxy_with_mode(dir: &TempDir, resolve_only: bool) -> SocketAddr {
+    start_proxy_with_config(dir, proxy_config(dir), resolve_only).await
+}
+
+fn proxy_config(dir: &TempDir) -> TurboCdnConfig {
+    let mut config = TurboCdnConfig::default();
+    config.performance.http2_prior_knowledge = false;
+    config.geo_detection.auto_detect_region = false;
+    config.cache.enabled = true;
+    config.cache.directory = Some(dir.path().join("cache"));
+    config
+}
+
+async fn start_proxy_with_config(
+    dir: &TempDir,
+    config: TurboCdnConfig,
+    resolve_only: bool,
+) -> SocketAddr {
+    let turbo_cdn = Arc::new(TurboCdn::with_config(config).await.unwrap());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = ProxyServer::new(turbo_cdn)
+        .with_scratch_dir(dir.path().join("scratch"))
+        .with_resolve_only(resolve_only);
+    tokio::spawn(server.serve(listener));
+    addr
+}
+
+fn client() -> reqwest::Client {
+    turbo_cdn::init_rustls_provider();
+    reqwest::Client::builder().no_proxy().build().unwrap()
+}
+
+fn fetch_url(proxy: SocketAddr, url: &str) -> String {
+    format!(
+        "http://{proxy}/fetch?url={}",
+        url::form_urlencoded::byte_serialize(url.as_bytes()).collect::<String>()
+    )
+}
+
+#[tokio::test]
+async fn test_fetch_endpoint_streams_file_and_caches() {
+    let upstream = start_upstream().await;
+    let dir = TempDir::new().unwrap();
+    let proxy = start_proxy(&dir).await;
+    let url = format!("{}/pkg.tgz", upstream.uri());
+
+    let response = client().get(fetch_url(proxy, &url)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["x-turbo-cdn-cache"], "MISS");
+    assert_eq!(response.headers()["etag"], "\"pkg-1\"");
+    assert_eq!(response.headers()["accept-ranges"], "bytes");
+    assert_eq!(response.bytes().await.unwrap().as_ref(), BODY);
+
+    // The relayed copy is stored once the last chunk was sent
+    let mut cached = false;
+    for _ in 0..50 {
+        let response = client().get(fetch_url(proxy, &url)).send().await.unwrap();
+        cached = response.headers()["x-turbo-cdn-cache"] == "HIT";
+        assert_eq!(response.bytes().await.unwrap().as_ref(), BODY);
+        if cached {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(cached);
+}
+
+#[tokio::test]
+async fn test_fetch_streams_before_download_completes() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Upstream that answers the size probe, then holds back the second half
+    // of the body until released
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let upstream = listener.local_addr().unwrap();
+    let (release, released) = tokio::sync::oneshot::channel::<()>();
+    tokio::spawn(async move {
+        let mut request = [0u8; 1024];
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let _ = stream.read(&mut request).await.unwrap();
+        let head = format!(
+            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+            BODY.len()
+        );
+        stream.write_all(head.as_bytes()).await.unwrap();
+        drop(stream);
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let _ = stream.read(&mut request).await.unwrap();
+        let head = format!("HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n", BODY.len());
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream.write_all(&BODY[..18]).await.unwrap();
+        let _ = released.await;
+        stream.write_all(&BODY[18..]).await.unwrap();
+    });
+
+    let dir = TempDir::new().unwrap();
+    let proxy = start_proxy(&dir).await;
+    let url = format!("http://{upstream}/pkg.tgz");
+
+    let mut response = client().get(fetch_url(proxy, &url)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-length"], "36");
+    let first = response.chunk().await.unwrap().unwrap();
+    assert_eq!(first.as_ref(), &BODY[..18]);
+
+    release.send(()).unwrap();
+    let mut body = first.to_vec();
+    while let Some(chunk) = response.chunk().await.unwrap() {
+        body.extend_from_slice(&chunk);
+    }
+    assert_eq!(body, BODY);
+}
+
+#[tokio::test]
+async fn test_fetch_streams_chunks_in_order() {
+    let upstream = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .and(path("/large.bin"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("accept-ranges", "bytes")
+                .set_body_bytes(large_body()),
+        )
+        .mount(&upstream)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/large.bin"))
+        .respond_with(RangeResponder(large_body()))
+        .mount(&upstream)
+        .await;
+
+    let dir = TempDir::new().unwrap();
+    let mut config = proxy_config(&dir);
+    config.performance.min_chunk_size = 64 * 1024;
+    config.performance.chunk_size = 128 * 1024;
+    let proxy = start_proxy_with_config(&dir, config, false).await;
+    let url = format!("{}/large.bin", upstream.uri());
+
+    let start = std::time::Instant::now();
+    let mut response = client().get(fetch_url(proxy, &url)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-length"], LARGE_SIZE.to_string());
+
+    // The first chunk is sent while the later ones are still held back
+    let mut body = response.chunk().await.unwrap().unwrap().to_vec();
+    assert!(start.elapsed() < LATER_CHUNK_DELAY);
+    while let Some(chunk) = response.chunk().await.unwrap() {
+        body.extend_from_slice(&chunk);
+    }
+    assert!(body == large_body());
+
+    let requests = upstream.received_requests().await.unwrap();
+    let ranged = requests
+        .iter()
+        .filter(|request| request.headers.contains_key("range"))
+        .count();
+    assert!(
+        ranged > 1,
+        "expected a chunked download, got {ranged} ranges"
+    );
+}
+
+#[tokio::test]
+async fn test_fetch_endpoint_range_requests() {
+    let upstream = start_upstream().await;
+    let dir = TempDir::new().unwrap();
+    let proxy = start_proxy(&dir).await;
+    let url = format!("{}/pkg.tgz", upstream.uri());
+
+    let response = client()
+        .get(fetch_url(proxy, &url))
+        .header("Range", "bytes=10-19")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 206);
+    assert_eq!(response.headers()["content-range"], "bytes 10-19/36");
+    assert_eq!(response.bytes().await.unwrap().as_ref(), &BODY[10..20]);
+
+    let response = client()
+        .get(fetch_url(proxy, &url))
+        .header("Range", "bytes=100-")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 416);
+    assert_eq!(response.headers()["content-range"], "bytes */36");
+}
+
+#[tokio::test]
+async fn test_forward_proxy_request() {
+    let upstream = start_upstream().await;
+    let dir = TempDir::new().unwrap();
+    let proxy = start_proxy(&dir).await;
+
+    turbo_cdn::init_rustls_provider();
+    let proxied = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{proxy}")).unwrap())
+        .build()
+        .unwrap();
+
+    let response = proxied
+        .get(format!("{}/pkg.tgz", upstream.uri()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().contains_key("x-turbo-cdn-source"));
+    assert_eq!(response.bytes().await.unwrap().as_ref(), BODY);
+}
+
+#[tokio::test]
+async fn test_upstream_errors_are_forwarded() {
+    let upstream = start_upstream().await;
+    let dir = TempDir::new().unwrap();
+    let proxy = start_proxy(&dir).await;
+
+    let url = format!("{}/missing.tgz", upstream.uri());
+    let response = client().get(fetch_url(proxy, &url)).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+
+    let response = client()
+        .get(format!("http://{proxy}/fetch"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    let response = client()
+        .delete(fetch_url(proxy, &url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 405);
+}
+
+#[tokio::test]
+async fn test_connect_tunnels_are_refused() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let dir = TempDir::new().unwrap();
+    let proxy = start_proxy(&dir).await;
+
+    let mut stream = tokio::net::TcpStream::connect(proxy).await.unwrap();
+    stream
+        .write_all(b"CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\r\n")
+        .await
+        .unwrap();
+    let mut status = [0u8; 12];
+    stream.read_exact(&mut status).await.unwrap();
+    assert_eq!(&status, b"HTTP/1.1 405");
+}
+
+#[tokio::test]
+async fn test_resolve_endpoint_returns_candidates() {
+    let dir = TempDir::new().unwrap();
+    let proxy = start_proxy_with_mode(&dir, true).await;
+    let url = "https://github.com/owner/repo/releases/download/v1.0.0/tool.zip";
+
+    let response = client()
+        .get(format!(
+            "http://{proxy}/resolve?url={}",
+            url::form_urlencoded::byte_serialize(url.as_bytes()).collect::<String>()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-type"], "application/json");
+
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(json["url"], url);
+    assert_eq!(json["region"], "Global");
+    assert!(json["matched_rule"].is_string());
+    assert_eq!(json["policy"]["rewrite_allowed"], true);
+
+    let candidates = json["candidates"].as_array().unwrap();
+    assert!(candidates.len() > 1);
+    assert!(candidates.iter().any(|c| c["url"] == url));
+    assert!(candidates.iter().all(|c| c["attempts"] == 0));
+}
+
+#[tokio::test]
+async fn test_resolve_only_mode_does_not_download() {
+    let upstream = start_upstream().await;
+    let dir = TempDir::new().unwrap();
+    let proxy = start_proxy_with_mode(&dir, true).await;
+
+    let url = format!("{}/pkg.tgz", upstream.uri());
+    let response = client().get(fetch_url(proxy, &url)).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+    assert!(upstream.received_requests().await.unwrap().is_empty());
+}
@@ -0,0 +1,216 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Remote rule-set subscription tests
+//!
+//! A mock server publishes signed rule sets; updates must be verified before
+//! the mapper uses them and rejected updates must keep the last good set.
+
+use base64::Engine;
+use ed25519_dalek::{Signer, SigningKey};
+use std::path::Path;
+use turbo_cdn::config::SubscriptionConfig;
+use turbo_cdn::*;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const TEAM_URL: &str = "https://github.com/team/tool/releases/download/v1/tool.zip";
+const TEAM_MIRROR: &str =
+    "https://mirror.team.example/https://github.com/team/tool/releases/download/v1/tool.zip";
+
+fn signing_key() -> SigningKey {
+    SigningKey::from_bytes(&[42; 32])
+}
+
+fn rule_set(version: u64) -> String {
+    format!(
+        r#"
+version = {version}
+
+[[mirrors]]
+name = "team"
+url = "https://mirror.team.example/"
+
+[[url_mapping_rules]]
+name = "Team"
+pattern = "^https://github\\.com/team/(.+)$"
+replacements = [{{ mirror = "team" }}, "https://github.com/team/$1"]
+regions = []
+priority = 0
+enabled = true
+"#
+    )
+}
+
+fn sign(content: &str) -> String {
+    base64::engine::general_purpose::STANDARD
+        .encode(signing_key().sign(content.as_bytes()).to_bytes())
+}
+
+/// Serve a rule set and its signature, replacing what was served before
+async fn publish(server: &MockServer, content: &str, signature: &str) {
+    server.reset().await;
+    Mock::given(method("GET"))
+        .and(path("/team.toml"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(content))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/team.toml.sig"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(signature))
+        .mount(server)
+        .await;
+}
+
+async fn client(server: &MockServer, cache_dir: &Path) -> TurboCdn {
+    let mut config = TurboCdnConfig::load().unwrap();
+    config.geo_detection.auto_detect_region = false;
+    config.performance.http2_prior_knowledge = false;
+    config.cache.enabled = false;
+    config.cache.directory = Some(cache_dir.to_path_buf());
+    config.subscriptions = vec![SubscriptionConfig {
+        name: "team".to_string(),
+        url: format!("{}/team.toml", server.uri()),
+        signature_url: None,
+        public_key: base64::engine::general_purpose::STANDARD
+            .encode(signing_key().verifying_key().as_bytes()),
+        // Only refresh on demand so the tests control every fetch
+        refresh_interval: 0,
+        enabled: true,
+    }];
+    TurboCdn::with_config(config).await.unwrap()
+}
+
+async fn refresh(turbo_cdn: &TurboCdn) -> SubscriptionStatus {
+    let mut statuses = turbo_cdn.refresh_subscriptions().await.unwrap();
+    assert_eq!(statuses.len(), 1);
+    statuses.remove(0)
+}
+
+async fn uses_team_mirror(turbo_cdn: &TurboCdn) -> bool {
+    turbo_cdn
+        .get_all_cdn_urls(TEAM_URL)
+        .await
+        .unwrap()
+        .iter()
+        .any(|url| url == TEAM_MIRROR)
+}
+
+#[tokio::test]
+async fn test_verified_update_is_applied() {
+    let server = MockServer::start().await;
+    let dir = tempfile::TempDir::new().unwrap();
+    let turbo_cdn = client(&server, dir.path()).await;
+    assert!(!uses_team_mirror(&turbo_cdn).await);
+
+    let content = rule_set(3);
+    publish(&server, &content, &sign(&content)).await;
+    let status = refresh(&turbo_cdn).await;
+    assert_eq!(status.outcome, RefreshOutcome::Updated);
+    assert_eq!(status.version, Some(3));
+    assert!(uses_team_mirror(&turbo_cdn).await);
+
+    let status = refresh(&turbo_cdn).await;
+    assert_eq!(status.outcome, RefreshOutcome::Unchanged);
+}
+
+#[tokio::test]
+async fn test_bad_signature_keeps_last_good_set() {
+    let server = MockServer::start().await;
+    let dir = tempfile::TempDir::new().unwrap();
+    let turbo_cdn = client(&server, dir.path()).await;
+
+    let good = rule_set(3);
+    publish(&server, &good, &sign(&good)).await;
+    refresh(&turbo_cdn).await;
+
+    // Signed by someone else
+    let forged = rule_set(4).replace("mirror.team.example", "evil.example");
+    let other_key = SigningKey::from_bytes(&[1; 32]);
+    let signature = base64::engine::general_purpose::STANDARD
+        .encode(other_key.sign(forged.as_bytes()).to_bytes());
+    publish(&server, &forged, &signature).await;
+
+    let status = refresh(&turbo_cdn).await;
+    assert!(matches!(
+        status.outcome,
+        RefreshOutcome::KeptLastGood { .. }
+    ));
+    assert_eq!(status.version, Some(3));
+    assert!(uses_team_mirror(&turbo_cdn).await);
+}
+
+#[tokio::test]
+async fn test_version_downgrade_is_rejected() {
+    let server = MockServer::start().await;
+    let dir = tempfile::TempDir::new().unwrap();
+    let turbo_cdn = client(&server, dir.path()).await;
+
+    let current = rule_set(3);
+    publish(&server, &current, &sign(&current)).await;
+    refresh(&turbo_cdn).await;
+
+    let older = rule_set(2);
+    publish(&server, &older, &sign(&older)).await;
+    let status = refresh(&turbo_cdn).await;
+    assert!(matches!(
+        status.outcome,
+        RefreshOutcome::KeptLastGood { .. }
+    ));
+    assert_eq!(status.version, Some(3));
+}
+
+#[tokio::test]
+async fn test_unverified_set_without_fallback_is_unavailable() {
+    let server = MockServer::start().await;
+    let dir = tempfile::TempDir::new().unwrap();
+    let turbo_cdn = client(&server, dir.path()).await;
+
+    let content = rule_set(1);
+    publish(&server, &content, &sign("something else")).await;
+    let status = refresh(&turbo_cdn).await;
+    assert!(matches!(status.outcome, RefreshOutcome::Unavailable { .. }));
+    assert_eq!(status.version, None);
+    assert!(!uses_team_mirror(&turbo_cdn).await);
+}
+
+#[tokio::test]
+async fn test_cached_set_is_used_after_restart() {
+    let server = MockServer::start().await;
+    let dir = tempfile::TempDir::new().unwrap();
+    let content = rule_set(5);
+    publish(&server, &content, &sign(&content)).await;
+    refresh(&client(&server, dir.path()).await).await;
+
+    // Nothing is served anymore, the verified cache is enough
+    server.reset().await;
+    let turbo_cdn = client(&server, dir.path()).await;
+    assert_eq!(turbo_cdn.subscriptions().version("team"), Some(5));
+    assert!(uses_team_mirror(&turbo_cdn).await);
+
+    // A tampered cache is ignored
+    let cache_file = turbo_cdn.subscriptions().subscriptions()[0].cache_file();
+    std::fs::write(cache_file, rule_set(6)).unwrap();
+    let turbo_cdn = client(&server, dir.path()).await;
+    assert_eq!(turbo_cdn.subscriptions().version("team"), None);
+}
+
+#[tokio::test]
+async fn test_oversized_set_keeps_last_good_set() {
+    let server = MockServer::start().await;
+    let dir = tempfile::TempDir::new().unwrap();
+    let turbo_cdn = client(&server, dir.path()).await;
+
+    let good = rule_set(3);
+    publish(&server, &good, &sign(&good)).await;
+    refresh(&turbo_cdn).await;
+
+    let padded = format!("{}#{}\n", rule_set(4), "x".repeat(5 * 1024 * 1024));
+    publish(&server, &padded, &sign(&padded)).await;
+    let status = refresh(&turbo_cdn).await;
+    assert!(matches!(
+        status.outcome,
+        RefreshOutcome::KeptLastGood { .. }
+    ));
+    assert_eq!(status.version, Some(3));
+}
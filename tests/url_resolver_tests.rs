@@ -0,0 +1,122 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Pluggable URL resolver tests
+//!
+//! Resolver candidates are merged with rule candidates, ranked with them
+//! and cached; failing resolvers are skipped.
+
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use turbo_cdn::*;
+
+const TARBALL: &str = "https://registry.example.org/left-pad/-/left-pad-1.3.0.tgz";
+
+/// Resolver answering tarball URLs of a package registry with fixed candidates
+#[derive(Default)]
+struct NpmResolver {
+    calls: AtomicUsize,
+}
+
+#[async_trait]
+impl UrlResolver for NpmResolver {
+    fn name(&self) -> &str {
+        "npm"
+    }
+
+    async fn resolve(&self, url: &str, region: &Region) -> Result<Vec<ResolverCandidate>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let Some(path) = url.strip_prefix("https://registry.example.org/") else {
+            return Ok(vec![]);
+        };
+        Ok(vec![
+            ResolverCandidate::new(format!("https://slow.example/{path}")).with_weight(50),
+            ResolverCandidate::new(format!("https://mirror.example.cn/{path}"))
+                .with_weight(500)
+                .with_metadata("region", region.to_string()),
+        ])
+    }
+}
+
+struct FailingResolver;
+
+#[async_trait]
+impl UrlResolver for FailingResolver {
+    fn name(&self) -> &str {
+        "failing"
+    }
+
+    async fn resolve(&self, _url: &str, _region: &Region) -> Result<Vec<ResolverCandidate>> {
+        Err(TurboCdnError::network("registry unavailable"))
+    }
+}
+
+fn builder() -> TurboCdnBuilder {
+    let mut config = TurboCdnConfig::load().unwrap();
+    config.general.default_region = Region::China;
+    config.geo_detection.auto_detect_region = false;
+    config.cache.enabled = false;
+    TurboCdn::builder().with_config(config)
+}
+
+#[tokio::test]
+async fn test_resolver_candidates_are_ranked_and_cached() {
+    let resolver = Arc::new(NpmResolver::default());
+    let turbo_cdn = builder()
+        .with_resolver(resolver.clone())
+        .with_resolver(Arc::new(FailingResolver))
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(turbo_cdn.resolvers(), ["npm", "failing"]);
+
+    let urls = turbo_cdn.get_all_cdn_urls(TARBALL).await.unwrap();
+    assert_eq!(
+        urls.first().map(String::as_str),
+        Some("https://mirror.example.cn/left-pad/-/left-pad-1.3.0.tgz")
+    );
+    assert!(urls.contains(&TARBALL.to_string()));
+    assert!(
+        urls.iter()
+            .position(|u| u.starts_with("https://slow.example/"))
+            > urls.iter().position(|u| u == TARBALL)
+    );
+
+    // The merged mapping is cached
+    turbo_cdn.get_all_cdn_urls(TARBALL).await.unwrap();
+    assert_eq!(resolver.calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_explain_reports_resolver_metadata() {
+    let turbo_cdn = builder().build().await.unwrap();
+    turbo_cdn
+        .add_resolver(Arc::new(NpmResolver::default()))
+        .await;
+
+    let explanation = turbo_cdn.explain_url(TARBALL).await;
+    let candidate = explanation
+        .candidates
+        .iter()
+        .find(|c| c.url.starts_with("https://mirror.example.cn/"))
+        .unwrap();
+    assert_eq!(candidate.rule.as_deref(), Some("npm"));
+    assert_eq!(candidate.metadata["region"], "China");
+}
+
+#[tokio::test]
+async fn test_removing_resolver_clears_mapping() {
+    let turbo_cdn = builder().build().await.unwrap();
+    turbo_cdn
+        .add_resolver(Arc::new(NpmResolver::default()))
+        .await;
+    assert!(turbo_cdn.can_optimize_url(TARBALL).await);
+
+    assert!(turbo_cdn.remove_resolver("npm").await);
+    assert!(!turbo_cdn.remove_resolver("npm").await);
+    assert_eq!(
+        turbo_cdn.get_all_cdn_urls(TARBALL).await.unwrap(),
+        [TARBALL]
+    );
+}
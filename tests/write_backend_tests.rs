@@ -0,0 +1,197 @@
+// Licensed under the MIT License
+// Copyright (c) 2025 Hal <hal.long@outlook.com>
+
+//! Write backend tests
+//!
+//! Download a ranged resource through every write backend and verify the
+//! assembled file byte for byte. Also covers the disk space preflight and
+//! restarting interrupted preallocated downloads.
+
+use tempfile::TempDir;
+use turbo_cdn::config::WriteBackendMode;
+use turbo_cdn::*;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+const SIZE: usize = 1024 * 1024;
+
+fn body() -> Vec<u8> {
+    (0..SIZE).map(|i| (i % 251) as u8).collect()
+}
+
+/// Serves `bytes=start-end` ranges of the body
+struct RangeResponder(Vec<u8>);
+
+impl Respond for RangeResponder {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let range = request
+            .headers
+            .get("range")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("bytes="))
+            .and_then(|v| v.split_once('-'))
+            .map(|(start, end)| {
+                let start: usize = start.parse().unwrap();
+                let end = end.parse().map_or(self.0.len() - 1, |end: usize| end);
+                (start, end.min(self.0.len() - 1))
+            });
+
+        match range {
+            Some((start, end)) => ResponseTemplate::new(206)
+                .insert_header("accept-ranges", "bytes")
+                .set_body_bytes(self.0[start..=end].to_vec()),
+            None => ResponseTemplate::new(200)
+                .insert_header("accept-ranges", "bytes")
+                .set_body_bytes(self.0.clone()),
+        }
+    }
+}
+
+async fn ranged_server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .and(path("/file.bin"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("accept-ranges", "bytes")
+                .set_body_bytes(body()),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/file.bin"))
+        .respond_with(RangeResponder(body()))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn test_config(mode: WriteBackendMode) -> TurboCdnConfig {
+    let mut config = TurboCdnConfig::default();
+    config.performance.http2_prior_knowledge = false;
+    config.geo_detection.auto_detect_region = false;
+    config.cache.enabled = false;
+    config.performance.write_backend = mode;
+    config.performance.mmap_threshold = 64 * 1024;
+    config.performance.write_buffer_size = 16 * 1024;
+    config.performance.min_chunk_size = 64 * 1024;
+    config.performance.chunk_size = 128 * 1024;
+    config
+}
+
+#[tokio::test]
+async fn test_chunked_download_with_each_backend() {
+    let server = ranged_server().await;
+    let dir = TempDir::new().unwrap();
+
+    for mode in [
+        WriteBackendMode::Auto,
+        WriteBackendMode::Mmap,
+        WriteBackendMode::Pwrite,
+        WriteBackendMode::Buffered,
+    ] {
+        let downloader = ConcurrentDownloader::with_config(&test_config(mode)).unwrap();
+        let output = dir.path().join(format!("{mode:?}.bin"));
+        let result = downloader
+            .download(&[format!("{}/file.bin", server.uri())], &output, None)
+            .await
+            .unwrap();
+
+        assert_eq!(result.size, SIZE as u64, "{mode:?}");
+        assert!(std::fs::read(&output).unwrap() == body(), "{mode:?}");
+    }
+}
+
+#[tokio::test]
+async fn test_chunked_download_resumes_partial_file() {
+    let server = ranged_server().await;
+    let dir = TempDir::new().unwrap();
+    let output = dir.path().join("partial.bin");
+    std::fs::write(&output, &body()[..100_000]).unwrap();
+
+    let downloader =
+        ConcurrentDownloader::with_config(&test_config(WriteBackendMode::Auto)).unwrap();
+    let result = downloader
+        .download(&[format!("{}/file.bin", server.uri())], &output, None)
+        .await
+        .unwrap();
+
+    assert!(result.resumed);
+    assert!(std::fs::read(&output).unwrap() == body());
+}
+
+#[tokio::test]
+async fn test_client_resumes_partial_file_without_cache() {
+    let server = ranged_server().await;
+    let dir = TempDir::new().unwrap();
+    let output = dir.path().join("partial.bin");
+    std::fs::write(&output, &body()[..100_000]).unwrap();
+
+    let turbo_cdn = TurboCdn::with_config(test_config(WriteBackendMode::Auto))
+        .await
+        .unwrap();
+    let result = turbo_cdn
+        .download_to_path(&format!("{}/file.bin", server.uri()), &output)
+        .await
+        .unwrap();
+
+    assert!(!result.from_cache);
+    assert!(result.resumed);
+    assert!(std::fs::read(&output).unwrap() == body());
+}
+
+#[tokio::test]
+async fn test_insufficient_space_fails_before_download() {
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .and(path("/huge.bin"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("accept-ranges", "bytes")
+                .insert_header("content-length", (1u64 << 62).to_string().as_str()),
+        )
+        .mount(&server)
+        .await;
+    let dir = TempDir::new().unwrap();
+
+    let downloader =
+        ConcurrentDownloader::with_config(&test_config(WriteBackendMode::Auto)).unwrap();
+    let error = downloader
+        .download(
+            &[format!("{}/huge.bin", server.uri())],
+            dir.path().join("huge.bin"),
+            None,
+        )
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(error, TurboCdnError::InsufficientSpace { .. }),
+        "{error}"
+    );
+    let requests = server.received_requests().await.unwrap();
+    assert!(requests.iter().all(|r| r.method.as_str() == "HEAD"));
+}
+
+#[tokio::test]
+async fn test_interrupted_preallocated_download_restarts() {
+    let server = ranged_server().await;
+    let dir = TempDir::new().unwrap();
+    let output = dir.path().join("interrupted.bin");
+    let marker = dir.path().join("interrupted.bin.incomplete");
+
+    // Full size but with a gap, as left behind by a killed download
+    std::fs::write(&output, vec![0u8; SIZE]).unwrap();
+    std::fs::write(&marker, b"").unwrap();
+
+    let downloader =
+        ConcurrentDownloader::with_config(&test_config(WriteBackendMode::Auto)).unwrap();
+    let result = downloader
+        .download(&[format!("{}/file.bin", server.uri())], &output, None)
+        .await
+        .unwrap();
+
+    assert!(!result.resumed);
+    assert!(std::fs::read(&output).unwrap() == body());
+    assert!(!marker.exists());
+}